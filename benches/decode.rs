@@ -0,0 +1,60 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use valid_utf8::{next_code_point, validate_next};
+
+fn valid_input(text: &str) -> Vec<u8> {
+    text.repeat(1 << 20 >> text.len().ilog2()).into_bytes()
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let inputs = [
+        (
+            "ascii",
+            valid_input("The quick brown fox jumps over the lazy dog. "),
+        ),
+        (
+            "cyrillic",
+            valid_input("Съешь же ещё этих мягких французских булок. "),
+        ),
+        (
+            "thai",
+            valid_input("นายสังฆภัณฑ์ เฮงพิทักษ์ฝั่ง ผู้เฒ่าซึ่งมีอาชีพเป็นฅนขายฃวด "),
+        ),
+        ("emoji", valid_input("😀😃😄😁😆😅🤣😂🙂🙃🫠😉😊😇")),
+    ];
+    let mut group = c.benchmark_group("decode");
+    for (name, input) in inputs.iter() {
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_function(format!("next_code_point {}", name), |b| {
+            b.iter(|| {
+                let mut it = black_box(input).iter();
+                let mut sum = 0u32;
+                while let Some(Ok(c)) = next_code_point(&mut it) {
+                    sum = sum.wrapping_add(u32::from(c));
+                }
+                sum
+            })
+        });
+        group.bench_function(format!("validate_next {}", name), |b| {
+            b.iter(|| {
+                let mut it = black_box(input).iter();
+                let mut sum = 0u32;
+                while let Ok(code_point) = validate_next(&mut it) {
+                    sum = sum.wrapping_add(code_point);
+                }
+                sum
+            })
+        });
+        group.bench_function(format!("std chars {}", name), |b| {
+            let text = std::str::from_utf8(input).unwrap();
+            b.iter(|| {
+                black_box(text)
+                    .chars()
+                    .fold(0u32, |sum, c| sum.wrapping_add(u32::from(c)))
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use valid_utf8::{lossy_decoded_len, to_string_lossy};
+
+fn corrupt_input() -> Vec<u8> {
+    let mut input = Vec::new();
+    for i in 0..4096 {
+        input.extend_from_slice("The quick brown fox ขฃค 😀 ".as_bytes());
+        if i % 7 == 0 {
+            input.extend_from_slice(&[0xf0, 0x9f, 0xff]);
+        }
+    }
+    input
+}
+
+fn bench_lossy(c: &mut Criterion) {
+    let input = corrupt_input();
+    c.bench_function("lossy_decoded_len", |b| {
+        b.iter(|| lossy_decoded_len(black_box(&input)))
+    });
+    c.bench_function("to_string_lossy", |b| {
+        b.iter(|| to_string_lossy(black_box(&input)))
+    });
+    c.bench_function("std from_utf8_lossy", |b| {
+        b.iter(|| String::from_utf8_lossy(black_box(&input)).into_owned())
+    });
+}
+
+criterion_group!(benches, bench_lossy);
+criterion_main!(benches);
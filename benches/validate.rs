@@ -0,0 +1,42 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use valid_utf8::{
+    validate, validate_bulk, validate_dfa, validate_with_hint, Backend, Scalar, TextHint,
+};
+
+fn valid_input(text: &str) -> Vec<u8> {
+    text.repeat(1 << 20 >> text.len().ilog2()).into_bytes()
+}
+
+fn bench_validate(c: &mut Criterion) {
+    let inputs = [
+        (
+            "ascii",
+            valid_input("The quick brown fox jumps over the lazy dog. "),
+        ),
+        ("mixed", valid_input("The quick brown fox ขฃค 😀 é ")),
+        ("cjk", valid_input("漢字仮名交じり文")),
+    ];
+    for (name, input) in inputs.iter() {
+        c.bench_function(&format!("validate {}", name), |b| {
+            b.iter(|| validate(black_box(input)))
+        });
+        c.bench_function(&format!("validate cjk hint {}", name), |b| {
+            b.iter(|| validate_with_hint(black_box(input), TextHint::Cjk))
+        });
+        c.bench_function(&format!("bulk {}", name), |b| {
+            b.iter(|| validate_bulk(black_box(input)))
+        });
+        c.bench_function(&format!("dfa {}", name), |b| {
+            b.iter(|| validate_dfa(black_box(input)))
+        });
+        c.bench_function(&format!("scalar {}", name), |b| {
+            b.iter(|| Scalar.validate(black_box(input)))
+        });
+        c.bench_function(&format!("std from_utf8 {}", name), |b| {
+            b.iter(|| std::str::from_utf8(black_box(input)).is_ok())
+        });
+    }
+}
+
+criterion_group!(benches, bench_validate);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+import assert from 'node:assert/strict'
+import { createRequire } from 'node:module'
+import test from 'node:test'
+
+const { validate, toStringLossy, analyze } = createRequire(import.meta.url)('../index.js')
+
+test('valid buffer', () => {
+  const buffer = Buffer.from('aé€😀', 'utf8')
+  assert.equal(validate(buffer), null)
+  assert.equal(toStringLossy(buffer), 'aé€😀')
+  assert.deepEqual(analyze(buffer), { chars: 4, asciiOnly: false, errors: [] })
+})
+
+test('invalid buffer', () => {
+  const buffer = Buffer.from([0x61, 0xff, 0x62, 0xf0, 0x9f])
+  assert.equal(validate(buffer), 1)
+  assert.equal(toStringLossy(buffer), 'a�b�')
+  assert.deepEqual(analyze(buffer), {
+    chars: 2,
+    asciiOnly: false,
+    errors: [
+      { offset: 1, length: 1, kind: 'InvalidLead', code: 1 },
+      { offset: 3, length: 2, kind: 'NotEnoughRoom', code: 3 },
+    ],
+  })
+})
+
+test('empty buffer', () => {
+  const buffer = Buffer.alloc(0)
+  assert.equal(validate(buffer), null)
+  assert.equal(toStringLossy(buffer), '')
+  assert.deepEqual(analyze(buffer), { chars: 0, asciiOnly: true, errors: [] })
+})
@@ -0,0 +1,14 @@
+// Loads the addon built by `npm run build` (set VALID_UTF8_PROFILE=release
+// to load a release build).
+const path = require('path')
+
+const file = {
+  win32: 'valid_utf8_node.dll',
+  darwin: 'libvalid_utf8_node.dylib',
+}[process.platform] || 'libvalid_utf8_node.so'
+const profile = process.env.VALID_UTF8_PROFILE || 'debug'
+
+const addon = { exports: {} }
+process.dlopen(addon, path.join(__dirname, 'target', profile, file))
+
+module.exports = addon.exports
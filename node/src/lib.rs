@@ -0,0 +1,61 @@
+//! Node.js bindings, built as a `cdylib` with `npm run build` and loaded as
+//! a `.node` addon by `index.js`.
+
+use napi::bindgen_prelude::Buffer;
+use napi_derive::napi;
+
+use valid_utf8::Utf8Validator;
+
+/// An invalid region as seen from JS.
+#[napi(object)]
+pub struct InvalidRegion {
+    pub offset: i64,
+    pub length: i64,
+    pub kind: String,
+    /// Stable numeric code, see `UtfError::code`.
+    pub code: u16,
+}
+
+/// Result of `analyze()` as seen from JS.
+#[napi(object)]
+pub struct Analysis {
+    pub chars: i64,
+    pub ascii_only: bool,
+    pub errors: Vec<InvalidRegion>,
+}
+
+/// Returns the offset of the first invalid sequence, or `null`.
+#[napi]
+pub fn validate(buffer: Buffer) -> Option<i64> {
+    let mut validator = Utf8Validator::new();
+    validator
+        .push(&buffer)
+        .and_then(|()| validator.finish())
+        .err()
+        .map(|err| err.offset() as i64)
+}
+
+#[napi]
+pub fn to_string_lossy(buffer: Buffer) -> String {
+    valid_utf8::to_string_lossy(&buffer)
+}
+
+#[napi]
+pub fn analyze(buffer: Buffer) -> Analysis {
+    let stats = valid_utf8::analyze(&buffer);
+    let errors = stats
+        .errors
+        .iter()
+        .map(|err| InvalidRegion {
+            offset: err.offset() as i64,
+            length: err.skip_len() as i64,
+            kind: format!("{:?}", err.kind()),
+            code: err.code(),
+        })
+        .collect();
+    Analysis {
+        chars: stats.chars as i64,
+        ascii_only: stats.ascii_only,
+        errors,
+    }
+}
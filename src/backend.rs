@@ -0,0 +1,392 @@
+use crate::core::{decode_step, decode_word};
+use crate::dfa::validate_dfa;
+use crate::error::Utf8ErrorAt;
+use crate::utf16::{validate_utf16, validate_utf16_scalar, Endian, Utf16ErrorAt};
+use crate::validate::{validate, validate_bulk, validate_exact};
+
+/// An implementation of whole-buffer validation.
+///
+/// Every backend must give the same verdict and the same error as
+/// [`Scalar`] for every input.
+pub trait Backend {
+    fn name(&self) -> &'static str;
+
+    fn validate(&self, bytes: &[u8]) -> Result<(), Utf8ErrorAt>;
+}
+
+/// The reference backend, decoding one slice at a time.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Scalar;
+
+impl Backend for Scalar {
+    fn name(&self) -> &'static str {
+        "scalar"
+    }
+
+    fn validate(&self, bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+        validate_exact(bytes)
+    }
+}
+
+/// Decodes from 4-byte words with [`decode_word`], falling back to
+/// [`Scalar`] for the last 3 bytes.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Word;
+
+impl Backend for Word {
+    fn name(&self) -> &'static str {
+        "word"
+    }
+
+    fn validate(&self, bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+        let mut pos = 0;
+        while bytes.len() - pos >= 4 {
+            let rest = &bytes[pos..];
+            let word = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+            match decode_word(word) {
+                Ok((_, length)) => pos += length as usize,
+                // The error length is only known to the slice decoder.
+                Err(kind) => {
+                    let err = decode_step(rest).err();
+                    return Err(err.unwrap_or(Utf8ErrorAt::new(0, kind, 1)).at(pos));
+                }
+            }
+        }
+        validate(&bytes[pos..]).map_err(|err| err.at(pos))
+    }
+}
+
+/// The table-driven DFA of [`validate_dfa`].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Dfa;
+
+impl Backend for Dfa {
+    fn name(&self) -> &'static str {
+        "dfa"
+    }
+
+    fn validate(&self, bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+        validate_dfa(bytes)
+    }
+}
+
+/// [`validate_bulk`], skipping ASCII in 16-byte blocks.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Bulk;
+
+impl Backend for Bulk {
+    fn name(&self) -> &'static str {
+        "bulk"
+    }
+
+    fn validate(&self, bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+        validate_bulk(bytes)
+    }
+}
+
+/// Every backend compiled in.
+pub fn backends() -> &'static [&'static dyn Backend] {
+    &[&Scalar, &Word, &Dfa, &Bulk]
+}
+
+/// An implementation of whole-buffer UTF-16 validation, in either byte
+/// order.
+///
+/// Every backend must give the same verdict and the same error as
+/// [`Utf16Scalar`] for every input.
+pub trait Utf16Backend {
+    fn name(&self) -> &'static str;
+
+    fn validate(&self, bytes: &[u8], endian: Endian) -> Result<(), Utf16ErrorAt>;
+}
+
+/// The reference UTF-16 backend, decoding one code unit at a time.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Utf16Scalar;
+
+impl Utf16Backend for Utf16Scalar {
+    fn name(&self) -> &'static str {
+        "utf16-scalar"
+    }
+
+    fn validate(&self, bytes: &[u8], endian: Endian) -> Result<(), Utf16ErrorAt> {
+        validate_utf16_scalar(bytes, endian)
+    }
+}
+
+/// Skips blocks of four code units without surrogates, as
+/// [`validate_utf16le`](crate::validate_utf16le) and
+/// [`validate_utf16be`](crate::validate_utf16be) do.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Utf16Word;
+
+impl Utf16Backend for Utf16Word {
+    fn name(&self) -> &'static str {
+        "utf16-word"
+    }
+
+    fn validate(&self, bytes: &[u8], endian: Endian) -> Result<(), Utf16ErrorAt> {
+        validate_utf16(bytes, endian)
+    }
+}
+
+/// Every UTF-16 backend compiled in.
+pub fn utf16_backends() -> &'static [&'static dyn Utf16Backend] {
+    &[&Utf16Scalar, &Utf16Word]
+}
+
+/// Two backends disagreeing on one input, reported by [`Paranoid`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub primary: &'static str,
+    pub reference: &'static str,
+    pub primary_result: Result<(), Utf8ErrorAt>,
+    pub reference_result: Result<(), Utf8ErrorAt>,
+    pub len: usize,
+}
+
+/// Runs every call through two backends and reports when they disagree,
+/// for canary deployments of a new backend.
+///
+/// Results come from the primary backend. This is the paranoid mode of the
+/// dispatcher: there is no global switch, code that picks a backend opts in
+/// by wrapping it, e.g. only when `debug_assertions` are on.
+///
+/// ```
+/// use valid_utf8::{Backend, Mismatch, Paranoid, Scalar, Word};
+///
+/// let backend = Paranoid::new(Word, Scalar, |mismatch: &Mismatch| {
+///     panic!("{} disagrees with {}", mismatch.primary, mismatch.reference)
+/// });
+/// assert!(backend.validate("grüß dich".as_bytes()).is_ok());
+/// ```
+pub struct Paranoid<P, R, F> {
+    primary: P,
+    reference: R,
+    on_mismatch: F,
+}
+
+impl<P: Backend, R: Backend, F: Fn(&Mismatch)> Paranoid<P, R, F> {
+    pub fn new(primary: P, reference: R, on_mismatch: F) -> Self {
+        Paranoid {
+            primary,
+            reference,
+            on_mismatch,
+        }
+    }
+}
+
+impl<P: Backend, R: Backend, F: Fn(&Mismatch)> Backend for Paranoid<P, R, F> {
+    fn name(&self) -> &'static str {
+        self.primary.name()
+    }
+
+    fn validate(&self, bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+        let primary_result = self.primary.validate(bytes);
+        let reference_result = self.reference.validate(bytes);
+        if primary_result != reference_result {
+            (self.on_mismatch)(&Mismatch {
+                primary: self.primary.name(),
+                reference: self.reference.name(),
+                primary_result,
+                reference_result,
+                len: bytes.len(),
+            });
+        }
+        primary_result
+    }
+}
+
+#[cfg(test)]
+mod test_backend {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::core::UtfError;
+    use crate::test_util::{kuhn_lines, random_corrupt, XorShift};
+
+    /// Byte values at the edges of the UTF-8 ranges.
+    const EDGES: &[u8] = &[
+        0x00, 0x41, 0x7f, 0x80, 0x8f, 0x90, 0x9f, 0xa0, 0xbf, 0xc0, 0xc1, 0xc2, 0xdf, 0xe0, 0xe1,
+        0xec, 0xed, 0xee, 0xef, 0xf0, 0xf1, 0xf3, 0xf4, 0xf5, 0xf7, 0xf8, 0xff,
+    ];
+
+    fn check(bytes: &[u8]) {
+        let expected = Scalar.validate(bytes);
+        for backend in backends() {
+            assert_eq!(
+                backend.validate(bytes),
+                expected,
+                "{} {:x?}",
+                backend.name(),
+                bytes
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends_exhaustive_short_inputs() {
+        check(b"");
+        for word in 0..=0xffffu32 {
+            check(&word.to_le_bytes()[..1]);
+            check(&word.to_le_bytes()[..2]);
+        }
+        for &a in EDGES {
+            for &b in EDGES {
+                for &c in EDGES {
+                    check(&[a, b, c]);
+                    for &d in EDGES {
+                        check(&[a, b, c, d]);
+                        check(&[0x61, a, b, c, d]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_backends_injected_errors() {
+        let text = "aé€😀ข plain ascii ".repeat(50).into_bytes();
+        let faults: &[&[u8]] = &[
+            &[0xff],
+            &[0x80],
+            &[0xe0, 0x80],
+            &[0xf0, 0x9f, 0x41],
+            &[0xed, 0xa0],
+        ];
+        for offset in (0..text.len()).step_by(7) {
+            for fault in faults {
+                let mut bytes = text.clone();
+                bytes.splice(offset..offset, fault.iter().copied());
+                check(&bytes);
+                // Cut right after the fault too.
+                check(&bytes[..offset + fault.len()]);
+            }
+        }
+        let mut rng = XorShift::new(222);
+        for _ in 0..500 {
+            let fragments = rng.below(400);
+            check(&random_corrupt(&mut rng, fragments));
+        }
+    }
+
+    #[test]
+    fn test_backends_kuhn_stress_test() {
+        let mut file = Vec::new();
+        for (line, valid) in kuhn_lines() {
+            assert_eq!(std::str::from_utf8(&line).is_ok(), valid, "{:x?}", line);
+            assert_eq!(Scalar.validate(&line).is_ok(), valid, "{:x?}", line);
+            check(&line);
+            file.extend_from_slice(&line);
+        }
+        // The whole file fails at its first malformed line.
+        check(&file);
+    }
+
+    /// Code units at the edges of the surrogate ranges, and ones that are
+    /// surrogates only in the other byte order.
+    const UNIT_EDGES: &[u16] = &[
+        0x0061, 0x00d8, 0x00dc, 0xd7ff, 0xd800, 0xdbff, 0xdc00, 0xdfff, 0xe000, 0xffff,
+    ];
+
+    fn check_utf16(units: &[u16]) {
+        for endian in [Endian::Little, Endian::Big].iter() {
+            let bytes: Vec<u8> = units
+                .iter()
+                .flat_map(|unit| match endian {
+                    Endian::Little => unit.to_le_bytes(),
+                    Endian::Big => unit.to_be_bytes(),
+                })
+                .collect();
+            for input in [&bytes[..], &bytes[..bytes.len().saturating_sub(1)]].iter() {
+                let expected = Utf16Scalar.validate(input, *endian);
+                for backend in utf16_backends() {
+                    assert_eq!(
+                        backend.validate(input, *endian),
+                        expected,
+                        "{} {:?} {:x?}",
+                        backend.name(),
+                        endian,
+                        units
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_utf16_backends_exhaustive_short_inputs() {
+        check_utf16(&[]);
+        for &a in UNIT_EDGES {
+            for &b in UNIT_EDGES {
+                for &c in UNIT_EDGES {
+                    for &d in UNIT_EDGES {
+                        check_utf16(&[a, b, c, d]);
+                        check_utf16(&[a, b, c, d, 0x62]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_utf16_backends_pairs_straddling_blocks() {
+        let (high, low) = (0xd83d, 0xde00);
+        // The pair starts at every position of the first three blocks, so
+        // some straddle the 4-unit boundaries.
+        for pad in 0..12 {
+            let mut units = vec![0x61; pad];
+            units.extend_from_slice(&[high, low]);
+            units.resize(16, 0x62);
+            check_utf16(&units);
+            for bad in [[0x61, low], [high, 0x61], [low, high], [high, high]].iter() {
+                units[pad..pad + 2].copy_from_slice(bad);
+                check_utf16(&units);
+            }
+        }
+        let mut rng = XorShift::new(243);
+        for _ in 0..2000 {
+            let len = rng.below(40);
+            let units: Vec<u16> = (0..len)
+                .map(|_| UNIT_EDGES[rng.below(UNIT_EDGES.len())])
+                .collect();
+            check_utf16(&units);
+        }
+    }
+
+    /// Reports every error one byte too late.
+    struct OffByOne;
+
+    impl Backend for OffByOne {
+        fn name(&self) -> &'static str {
+            "off-by-one"
+        }
+
+        fn validate(&self, bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+            Scalar.validate(bytes).map_err(|err| err.at(1))
+        }
+    }
+
+    #[test]
+    fn test_paranoid_catches_broken_backend() {
+        let mismatches = RefCell::new(Vec::new());
+        let paranoid = Paranoid::new(OffByOne, Scalar, |m: &Mismatch| {
+            mismatches.borrow_mut().push(m.clone())
+        });
+        assert_eq!(paranoid.validate(b"valid"), Ok(()));
+        assert!(mismatches.borrow().is_empty());
+
+        let err = paranoid.validate(b"ab\xff").unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (3, UtfError::InvalidLead));
+        let mismatches = mismatches.into_inner();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(
+            (mismatches[0].primary, mismatches[0].reference),
+            ("off-by-one", "scalar")
+        );
+        assert_eq!(mismatches[0].reference_result.unwrap_err().offset(), 2);
+
+        let quiet = Paranoid::new(Word, Scalar, |m: &Mismatch| panic!("{:?}", m));
+        assert!(quiet.validate("€😀".repeat(10).as_bytes()).is_ok());
+    }
+}
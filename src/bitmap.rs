@@ -0,0 +1,153 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::core::decode_step;
+use crate::error::Utf8ErrorAt;
+
+const ASCII_MASK: u64 = 0x8080_8080_8080_8080;
+
+/// One bit per byte, set where a character starts; see [`char_start_bitmap`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CharStartBitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl CharStartBitmap {
+    /// Length in bytes of the text the bitmap was built from.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether `offset` is a character boundary; the end of the text counts
+    /// as one.
+    #[inline]
+    pub fn is_char_start(&self, offset: usize) -> bool {
+        if offset < self.len {
+            self.words[offset / 64] >> (offset % 64) & 1 == 1
+        } else {
+            offset == self.len
+        }
+    }
+
+    /// Number of characters starting before `offset`, i.e. the character
+    /// index of the character at `offset`.
+    pub fn rank(&self, offset: usize) -> usize {
+        let offset = offset.min(self.len);
+        let (full, rest) = (offset / 64, offset % 64);
+        let mut count: usize = self.words[..full]
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum();
+        if rest != 0 {
+            count += (self.words[full] & ((1 << rest) - 1)).count_ones() as usize;
+        }
+        count
+    }
+
+    /// Byte offset of the character with index `index`.
+    pub fn select(&self, index: usize) -> Option<usize> {
+        let mut remaining = index;
+        for (i, word) in self.words.iter().enumerate() {
+            let ones = word.count_ones() as usize;
+            if remaining < ones {
+                let mut word = *word;
+                for _ in 0..remaining {
+                    word &= word - 1;
+                }
+                return Some(i * 64 + word.trailing_zeros() as usize);
+            }
+            remaining -= ones;
+        }
+        None
+    }
+
+    /// Total number of characters.
+    #[inline]
+    pub fn char_count(&self) -> usize {
+        self.rank(self.len)
+    }
+}
+
+/// Validates `bytes` and records the offset of every character start.
+///
+/// Runs of 8 ASCII bytes are classified a word at a time.
+pub fn char_start_bitmap(bytes: &[u8]) -> Result<CharStartBitmap, Utf8ErrorAt> {
+    let mut words = vec![0u64; bytes.len().div_ceil(64)];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if pos % 8 == 0 && pos + 8 <= bytes.len() {
+            let mut chunk = [0; 8];
+            chunk.copy_from_slice(&bytes[pos..pos + 8]);
+            if u64::from_ne_bytes(chunk) & ASCII_MASK == 0 {
+                words[pos / 64] |= 0xff << (pos % 64);
+                pos += 8;
+                continue;
+            }
+        }
+        let (_, length) = decode_step(&bytes[pos..]).map_err(|err| err.at(pos))?;
+        words[pos / 64] |= 1 << (pos % 64);
+        pos += length;
+    }
+    Ok(CharStartBitmap {
+        words,
+        len: bytes.len(),
+    })
+}
+
+#[cfg(test)]
+mod test_bitmap {
+    use super::*;
+    use crate::core::UtfError;
+
+    fn check(text: &str) {
+        let bitmap = char_start_bitmap(text.as_bytes()).unwrap();
+        assert_eq!(bitmap.len(), text.len());
+        assert_eq!(bitmap.char_count(), text.chars().count());
+        for (index, (offset, _)) in text.char_indices().enumerate() {
+            assert!(bitmap.is_char_start(offset));
+            assert_eq!(bitmap.rank(offset), index);
+            assert_eq!(bitmap.select(index), Some(offset));
+        }
+        for offset in 0..=text.len() {
+            assert_eq!(bitmap.is_char_start(offset), text.is_char_boundary(offset));
+        }
+        assert_eq!(bitmap.select(text.chars().count()), None);
+    }
+
+    #[test]
+    fn test_bitmap_rank_select() {
+        check("");
+        check("plain ascii text that is longer than sixty-four bytes, for sure!!");
+        check("ขฃคฅฆงจฉชซฌญฎฏฐฑฒณดตถทธนบปผฝพฟภมยรฤลฦวศษสหฬอฮฯะัาำิีึืฺุู");
+        check("😀𒀀𒀁𒀂𒀃𒀄𒀅𒀆𒀇𒀈𒀉𒀊𒀋𒀌𒀍𒀎𒀏");
+    }
+
+    #[test]
+    fn test_bitmap_word_boundaries() {
+        // Put a 2-, 3- and 4-byte character across the boundary between the
+        // first two words at every possible shift.
+        for c in ['é', '€', '😀'].iter() {
+            for pad in 56..64 {
+                let mut text = "a".repeat(pad);
+                text.push(*c);
+                text.push_str(&"b".repeat(70));
+                check(&text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitmap_error() {
+        let mut bytes = "a".repeat(20).into_bytes();
+        bytes.push(0xff);
+        let err = char_start_bitmap(&bytes).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (20, UtfError::InvalidLead));
+    }
+}
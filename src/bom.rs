@@ -0,0 +1,71 @@
+use crate::decode_utf8::AsByte;
+
+/// U+FEFF encoded as UTF-8.
+const BOM: [u8; 3] = [0xef, 0xbb, 0xbf];
+
+/// Whether `bytes` starts with the UTF-8 byte order mark.
+#[inline]
+pub fn has_bom(bytes: &[u8]) -> bool {
+    bytes.starts_with(&BOM)
+}
+
+/// Returns `bytes` without its byte order mark, if it has one.
+#[inline]
+pub fn strip_bom(bytes: &[u8]) -> &[u8] {
+    bytes.strip_prefix(&BOM[..]).unwrap_or(bytes)
+}
+
+/// Consumes the byte order mark at the front of `it`, if there is one.
+///
+/// Looks ahead on a clone of `it`, so nothing is consumed unless all three
+/// bytes are there. Iterators that cannot be cloned can be wrapped with
+/// [`decode_utf8`](crate::decode_utf8) and use
+/// [`DecodeUtf8::skip_bom`](crate::DecodeUtf8::skip_bom) instead.
+pub fn skip_bom<I>(it: &mut I) -> bool
+where
+    I: Iterator + Clone,
+    I::Item: AsByte,
+{
+    let mut ahead = it.clone();
+    let found = BOM
+        .iter()
+        .all(|&expected| ahead.next().map(|byte| byte.as_byte()) == Some(expected));
+    if found {
+        *it = ahead;
+    }
+    found
+}
+
+#[cfg(test)]
+mod test_bom {
+    use super::*;
+
+    const PREFIXES: [&[u8]; 5] = [b"", b"\xef", b"\xef\xbb", b"\xef\xbba", b"\xef\xbc\xbf"];
+
+    #[test]
+    fn test_bom_slices() {
+        assert!(has_bom(b"\xef\xbb\xbf"));
+        assert_eq!(strip_bom(b"\xef\xbb\xbf"), b"");
+        assert_eq!(strip_bom(b"\xef\xbb\xbfa\xef\xbb\xbf"), b"a\xef\xbb\xbf");
+        for bytes in PREFIXES.iter() {
+            assert!(!has_bom(bytes), "{:x?}", bytes);
+            assert_eq!(strip_bom(bytes), *bytes);
+        }
+    }
+
+    #[test]
+    fn test_skip_bom_partial_prefixes() {
+        for bytes in PREFIXES.iter() {
+            let mut it = bytes.iter();
+            assert!(!skip_bom(&mut it), "{:x?}", bytes);
+            assert_eq!(it.as_slice(), *bytes);
+        }
+        let mut it = b"\xef\xbb\xbf".iter();
+        assert!(skip_bom(&mut it));
+        assert!(it.as_slice().is_empty());
+        // Only one BOM is skipped, and owned bytes work as well.
+        let mut it = b"\xef\xbb\xbf\xef\xbb\xbfa".iter().copied();
+        assert!(skip_bom(&mut it));
+        assert_eq!(it.collect::<Vec<_>>(), b"\xef\xbb\xbfa");
+    }
+}
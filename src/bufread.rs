@@ -0,0 +1,238 @@
+use std::io::{self, BufRead};
+
+use crate::core::decode_step;
+use crate::error::Utf8ErrorAt;
+use crate::stream::{tail_error, validate_chunk};
+
+/// One piece of the input returned by [`BufChunks::next_chunk`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Chunk<'a> {
+    /// Valid text borrowed from the reader's buffer.
+    Valid(&'a str),
+    /// A character split across two fills of the reader's buffer.
+    Stitched(char),
+    /// A maximal ill-formed subsequence, `skip_len()` bytes long, at its
+    /// stream offset.
+    Invalid(Utf8ErrorAt),
+}
+
+/// Splits the contents of a [`BufRead`] into valid text and invalid
+/// sequences without copying, created by [`chunks_from_bufread`].
+///
+/// The text borrows the reader's buffer, so this is not an `Iterator`; call
+/// [`next_chunk`](Self::next_chunk) in a loop instead.
+#[derive(Debug)]
+pub struct BufChunks<R> {
+    inner: R,
+    /// Start of a character not yet complete at the end of the buffer.
+    carry: [u8; 4],
+    carry_len: usize,
+    /// Bytes of the last returned chunk still to be consumed.
+    returned: usize,
+    /// Stream offset of the next byte, counting carried bytes.
+    offset: u64,
+}
+
+pub fn chunks_from_bufread<R: BufRead>(inner: R) -> BufChunks<R> {
+    BufChunks {
+        inner,
+        carry: [0; 4],
+        carry_len: 0,
+        returned: 0,
+        offset: 0,
+    }
+}
+
+/// What to do after looking at the reader's buffer.
+enum Step {
+    Valid(usize),
+    Other(Chunk<'static>, usize),
+    Carry(usize),
+    Eof,
+}
+
+impl<R: BufRead> BufChunks<R> {
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the next chunk, or `None` at the end of the input.
+    ///
+    /// `Interrupted` errors of the reader are retried.
+    pub fn next_chunk(&mut self) -> io::Result<Option<Chunk<'_>>> {
+        self.inner.consume(self.returned);
+        self.returned = 0;
+        let valid_len = loop {
+            let step = self.step()?;
+            match step {
+                Step::Valid(len) => break len,
+                Step::Other(chunk, consume) => {
+                    self.inner.consume(consume);
+                    return Ok(Some(chunk));
+                }
+                Step::Carry(consume) => self.inner.consume(consume),
+                Step::Eof => return Ok(None),
+            }
+        };
+        self.returned = valid_len;
+        self.offset = self.offset.saturating_add(valid_len as u64);
+        let buf = fill_buf(&mut self.inner)?;
+        let text = &buf[..valid_len];
+        debug_assert!(std::str::from_utf8(text).is_ok());
+        // SAFETY: `step` validated the first `valid_len` bytes of the buffer,
+        // and `fill_buf` returns the same bytes until they are consumed.
+        Ok(Some(Chunk::Valid(unsafe {
+            std::str::from_utf8_unchecked(text)
+        })))
+    }
+
+    fn step(&mut self) -> io::Result<Step> {
+        let buf = fill_buf(&mut self.inner)?;
+        if self.carry_len != 0 {
+            let start = self.offset;
+            if buf.is_empty() {
+                let err = tail_error(&self.carry[..self.carry_len]).at_stream(start);
+                self.offset = self.offset.saturating_add(self.carry_len as u64);
+                self.carry_len = 0;
+                return Ok(Step::Other(Chunk::Invalid(err), 0));
+            }
+            let take = buf.len().min(4 - self.carry_len);
+            let mut joined = self.carry;
+            joined[self.carry_len..self.carry_len + take].copy_from_slice(&buf[..take]);
+            return Ok(match decode_step(&joined[..self.carry_len + take]) {
+                Ok((code_point, length)) => {
+                    let consume = length - self.carry_len;
+                    self.offset = self.offset.saturating_add(length as u64);
+                    self.carry_len = 0;
+                    match char::from_u32(code_point) {
+                        Some(c) => Step::Other(Chunk::Stitched(c), consume),
+                        None => Step::Carry(consume),
+                    }
+                }
+                Err(err) if err.error_len().is_none() => {
+                    self.carry = joined;
+                    self.carry_len += take;
+                    Step::Carry(take)
+                }
+                Err(err) => {
+                    // The carried bytes are a valid prefix or a single lead,
+                    // so the ill-formed subsequence covers all of them.
+                    let consume = err.skip_len() - self.carry_len;
+                    self.offset = self.offset.saturating_add(err.skip_len() as u64);
+                    self.carry_len = 0;
+                    Step::Other(Chunk::Invalid(err.at_stream(start)), consume)
+                }
+            });
+        }
+        if buf.is_empty() {
+            return Ok(Step::Eof);
+        }
+        Ok(match validate_chunk(buf) {
+            Ok(tail) if tail == buf.len() => {
+                self.carry[..tail].copy_from_slice(buf);
+                self.carry_len = tail;
+                Step::Carry(tail)
+            }
+            Ok(tail) => Step::Valid(buf.len() - tail),
+            Err(err) if err.offset() != 0 => Step::Valid(err.offset()),
+            Err(err) => {
+                let skip = err.skip_len();
+                let err = err.at_stream(self.offset);
+                self.offset = self.offset.saturating_add(skip as u64);
+                Step::Other(Chunk::Invalid(err), skip)
+            }
+        })
+    }
+}
+
+/// `fill_buf`, retrying `Interrupted` errors.
+#[inline]
+fn fill_buf<R: BufRead>(inner: &mut R) -> io::Result<&[u8]> {
+    loop {
+        match inner.fill_buf() {
+            Ok(_) => break,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    // Filled already, this only returns the buffer.
+    inner.fill_buf()
+}
+
+#[cfg(test)]
+mod test_bufread {
+    use std::io::BufReader;
+
+    use super::*;
+    use crate::core::UtfError;
+    use crate::lossy::{to_string_lossy, REPLACEMENT};
+    use crate::test_util::{random_corrupt, XorShift};
+
+    fn lossy(bytes: &[u8], capacity: usize) -> String {
+        let mut chunks = chunks_from_bufread(BufReader::with_capacity(capacity, bytes));
+        let mut out = String::new();
+        while let Some(chunk) = chunks.next_chunk().unwrap() {
+            match chunk {
+                Chunk::Valid(text) => out.push_str(text),
+                Chunk::Stitched(c) => out.push(c),
+                Chunk::Invalid(_) => out.push_str(REPLACEMENT),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_bufread_emoji_tiny_buffer() {
+        let text = "😀😁 emoji 😂🤣 heavy 😃 text 😄".repeat(5);
+        for capacity in 1..=8 {
+            assert_eq!(lossy(text.as_bytes(), capacity), text);
+        }
+        let mut chunks = chunks_from_bufread(BufReader::with_capacity(3, "a😀b".as_bytes()));
+        assert_eq!(chunks.next_chunk().unwrap(), Some(Chunk::Valid("a")));
+        assert_eq!(chunks.next_chunk().unwrap(), Some(Chunk::Stitched('😀')));
+        assert_eq!(chunks.next_chunk().unwrap(), Some(Chunk::Valid("b")));
+        assert_eq!(chunks.next_chunk().unwrap(), None);
+    }
+
+    #[test]
+    fn test_bufread_matches_lossy() {
+        let mut rng = XorShift::new(223);
+        for _ in 0..500 {
+            let fragments = rng.below(40);
+            let input = random_corrupt(&mut rng, fragments);
+            for capacity in [1, 2, 3, 5, 64].iter() {
+                assert_eq!(
+                    lossy(&input, *capacity),
+                    to_string_lossy(&input),
+                    "{:x?}",
+                    input
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bufread_invalid_offsets() {
+        let input = b"ab\xf0\x9f\x41c\xe2\x82";
+        let mut chunks = chunks_from_bufread(BufReader::with_capacity(3, &input[..]));
+        let mut errors = Vec::new();
+        while let Some(chunk) = chunks.next_chunk().unwrap() {
+            if let Chunk::Invalid(err) = chunk {
+                errors.push((err.offset(), err.kind(), err.skip_len()));
+            }
+        }
+        assert_eq!(
+            errors,
+            [
+                (2, UtfError::IncompleteSequence, 2),
+                (6, UtfError::NotEnoughRoom, 2)
+            ]
+        );
+    }
+}
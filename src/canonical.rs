@@ -0,0 +1,205 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+use crate::core::{is_continuation, is_overlong_sequence, sequence_length, UtfError};
+use crate::error::Utf8ErrorAt;
+use crate::validate::validate;
+
+/// What to do with a surrogate code point that is not part of a pair.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoneSurrogates {
+    Reject,
+    /// Replace it with U+FFFD.
+    Replace,
+}
+
+/// A UTF-8 variant accepted by [`canonicalize`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    /// Standard UTF-8.
+    Strict,
+    /// UTF-8 that may contain overlong encodings.
+    Overlong,
+    /// CESU-8: supplementary characters as pairs of 3-byte surrogates.
+    Cesu8,
+    /// Java's Modified UTF-8: CESU-8 with U+0000 as `C0 80`.
+    Mutf8,
+    /// WTF-8: UTF-8 that may contain surrogates.
+    Wtf8(LoneSurrogates),
+}
+
+/// Decodes the sequence at the start of `bytes` checking only its shape:
+/// overlongs and surrogates are returned as is.
+fn decode_shape(bytes: &[u8]) -> Result<(u32, usize), Utf8ErrorAt> {
+    let lead = bytes[0];
+    let length = sequence_length(lead);
+    if length == 0 {
+        return Err(Utf8ErrorAt::new(0, UtfError::InvalidLead, 1));
+    }
+    let mut code_point = (lead as u32) & (0x7f >> length);
+    for i in 1..length {
+        match bytes.get(i) {
+            None => return Err(Utf8ErrorAt::truncated(0, i as u8)),
+            Some(&byte) if !is_continuation(byte) => {
+                return Err(Utf8ErrorAt::new(0, UtfError::IncompleteSequence, i as u8))
+            }
+            Some(&byte) => code_point = code_point << 6 | (byte & 0x3f) as u32,
+        }
+    }
+    if code_point > char::MAX as u32 {
+        return Err(Utf8ErrorAt::new(0, UtfError::InvalidCodePoint, 1));
+    }
+    Ok((code_point, length))
+}
+
+#[inline]
+fn is_high_surrogate(code_point: u32) -> bool {
+    (0xd800..=0xdbff).contains(&code_point)
+}
+
+#[inline]
+fn is_low_surrogate(code_point: u32) -> bool {
+    (0xdc00..=0xdfff).contains(&code_point)
+}
+
+/// Decodes one character of `dialect` at the start of `bytes`, returning the
+/// character and the bytes it occupies.
+fn decode_dialect(bytes: &[u8], dialect: Dialect) -> Result<(char, usize), Utf8ErrorAt> {
+    let (code_point, length) = decode_shape(bytes)?;
+    if is_overlong_sequence(code_point, length) {
+        let allowed = match dialect {
+            Dialect::Overlong => true,
+            Dialect::Mutf8 => code_point == 0 && length == 2,
+            _ => false,
+        };
+        if !allowed {
+            return Err(Utf8ErrorAt::new(0, UtfError::OverlongSequence, 1));
+        }
+    }
+    if let Some(c) = char::from_u32(code_point) {
+        return Ok((c, length));
+    }
+    // A surrogate.
+    let paired = match dialect {
+        Dialect::Cesu8 | Dialect::Mutf8 | Dialect::Wtf8(_) => true,
+        Dialect::Strict | Dialect::Overlong => false,
+    };
+    if paired && is_high_surrogate(code_point) && length == 3 && bytes.len() > 3 {
+        if let Ok((low, 3)) = decode_shape(&bytes[3..]) {
+            if is_low_surrogate(low) {
+                let combined = 0x10000 + ((code_point - 0xd800) << 10 | (low - 0xdc00));
+                return char::from_u32(combined)
+                    .map(|c| (c, 6))
+                    .ok_or(Utf8ErrorAt::new(0, UtfError::InvalidCodePoint, 1));
+            }
+        }
+    }
+    match dialect {
+        Dialect::Wtf8(LoneSurrogates::Replace) => Ok(('\u{fffd}', length)),
+        _ => Err(Utf8ErrorAt::new(0, UtfError::InvalidCodePoint, 1)),
+    }
+}
+
+/// Decodes `bytes` as `dialect` and re-encodes it as standard, shortest-form
+/// UTF-8.
+///
+/// Input that is already standard UTF-8 is returned borrowed.
+pub fn canonicalize(bytes: &[u8], dialect: Dialect) -> Result<Cow<'_, [u8]>, Utf8ErrorAt> {
+    if dialect == Dialect::Strict {
+        return validate(bytes).map(|()| Cow::Borrowed(bytes));
+    }
+    let mut out: Option<Vec<u8>> = None;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if bytes[pos] < 0x80 {
+            if let Some(out) = &mut out {
+                out.push(bytes[pos]);
+            }
+            pos += 1;
+            continue;
+        }
+        let (c, length) = decode_dialect(&bytes[pos..], dialect).map_err(|err| err.at(pos))?;
+        let mut buf = [0; 4];
+        let encoded = c.encode_utf8(&mut buf).as_bytes();
+        let canonical = &bytes[pos..pos + length] == encoded;
+        match &mut out {
+            Some(out) => out.extend_from_slice(encoded),
+            None if canonical => {}
+            None => {
+                let mut started = Vec::with_capacity(bytes.len());
+                started.extend_from_slice(&bytes[..pos]);
+                started.extend_from_slice(encoded);
+                out = Some(started);
+            }
+        }
+        pos += length;
+    }
+    Ok(out.map_or(Cow::Borrowed(bytes), Cow::Owned))
+}
+
+#[cfg(test)]
+mod test_canonical {
+    use super::*;
+
+    fn owned(bytes: &[u8], dialect: Dialect) -> Vec<u8> {
+        match canonicalize(bytes, dialect).unwrap() {
+            Cow::Owned(out) => out,
+            Cow::Borrowed(_) => panic!("{:x?} returned borrowed", bytes),
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_clean_input_borrowed() {
+        let text = "plain, é€😀 and \u{fffd}".as_bytes();
+        let dialects = [
+            Dialect::Strict,
+            Dialect::Overlong,
+            Dialect::Cesu8,
+            Dialect::Mutf8,
+            Dialect::Wtf8(LoneSurrogates::Reject),
+            Dialect::Wtf8(LoneSurrogates::Replace),
+        ];
+        for dialect in dialects.iter() {
+            assert!(matches!(canonicalize(text, *dialect), Ok(Cow::Borrowed(b)) if b == text));
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_overlong() {
+        assert_eq!(owned(&[0x61, 0xc0, 0x80, 0x62], Dialect::Mutf8), b"a\0b");
+        assert_eq!(owned(&[0xc0, 0x80], Dialect::Overlong), b"\0");
+        assert_eq!(
+            owned(
+                &[0xe0, 0x81, 0x81, 0xf0, 0x82, 0x82, 0xac],
+                Dialect::Overlong
+            ),
+            "A€".as_bytes()
+        );
+        // Only U+0000 may be overlong in Modified UTF-8.
+        let err = canonicalize(&[0x61, 0xc1, 0x81], Dialect::Mutf8).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, UtfError::OverlongSequence));
+        assert!(canonicalize(&[0xc0, 0x80], Dialect::Strict).is_err());
+    }
+
+    #[test]
+    fn test_canonicalize_surrogates() {
+        // U+1F600 as a CESU-8 surrogate pair.
+        let cesu = [0x78, 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80];
+        assert_eq!(owned(&cesu, Dialect::Cesu8), "x😀".as_bytes());
+        assert_eq!(owned(&cesu, Dialect::Mutf8), "x😀".as_bytes());
+        let err = canonicalize(&cesu, Dialect::Overlong).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, UtfError::InvalidCodePoint));
+
+        let lone = [0x61, 0xed, 0xa0, 0x80, 0x62];
+        let err = canonicalize(&lone, Dialect::Wtf8(LoneSurrogates::Reject)).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, UtfError::InvalidCodePoint));
+        assert!(canonicalize(&lone, Dialect::Cesu8).is_err());
+        assert_eq!(
+            owned(&lone, Dialect::Wtf8(LoneSurrogates::Replace)),
+            "a\u{fffd}b".as_bytes()
+        );
+        // A low surrogate first is not a pair.
+        let swapped = [0xed, 0xb8, 0x80, 0xed, 0xa0, 0xbd];
+        assert!(canonicalize(&swapped, Dialect::Cesu8).is_err());
+    }
+}
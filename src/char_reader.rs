@@ -0,0 +1,209 @@
+use std::io::{self, Read};
+use std::iter::FusedIterator;
+
+use crate::core::decode_step;
+use crate::error::Utf8ErrorAt;
+use crate::io::StreamError;
+
+/// Error of [`CharReader`]: IO failure or invalid UTF-8 at a stream offset.
+pub type CharReadError = StreamError;
+
+const BUF_LEN: usize = 64;
+
+/// Iterator over the characters read from a [`Read`].
+///
+/// IO errors are yielded as they happen and iteration may continue after
+/// them; `Interrupted` reads are retried. After invalid UTF-8, including EOF
+/// in the middle of a character, the iterator ends.
+#[derive(Debug)]
+pub struct CharReader<R> {
+    inner: R,
+    buf: [u8; BUF_LEN],
+    start: usize,
+    end: usize,
+    /// Stream offset of `buf[start]`.
+    offset: u64,
+    done: bool,
+}
+
+impl<R: Read> CharReader<R> {
+    pub fn new(inner: R) -> Self {
+        CharReader {
+            inner,
+            buf: [0; BUF_LEN],
+            start: 0,
+            end: 0,
+            offset: 0,
+            done: false,
+        }
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Bytes read ahead but not yet decoded are lost when the inner reader
+    /// is used directly.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fail(&mut self, err: Utf8ErrorAt) -> Option<Result<char, CharReadError>> {
+        self.done = true;
+        Some(Err(err.into()))
+    }
+}
+
+impl<R: Read> Iterator for CharReader<R> {
+    type Item = Result<char, CharReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            if self.start < self.end {
+                match decode_step(&self.buf[self.start..self.end]) {
+                    Ok((code_point, length)) => {
+                        self.start += length;
+                        self.offset = self.offset.saturating_add(length as u64);
+                        return char::from_u32(code_point).map(Ok);
+                    }
+                    Err(err) if err.error_len().is_none() => {}
+                    Err(err) => return self.fail(err.at_stream(self.offset)),
+                }
+            }
+            // Keep the start of a split character and read the rest of it.
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+            match self.inner.read(&mut self.buf[self.end..]) {
+                Ok(0) if self.end == 0 => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(0) => {
+                    let err = Utf8ErrorAt::truncated(0, self.end as u8);
+                    return self.fail(err.at_stream(self.offset));
+                }
+                Ok(n) => self.end += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+impl<R: Read> FusedIterator for CharReader<R> {}
+
+#[cfg(test)]
+mod test_char_reader {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::core::UtfError;
+
+    /// Returns one byte per read, interrupting every other read, and fails
+    /// once when reaching `fail_at`.
+    struct Trickle {
+        bytes: Vec<u8>,
+        pos: usize,
+        interrupt: bool,
+        fail_at: Option<usize>,
+    }
+
+    impl Trickle {
+        fn new(bytes: &[u8], fail_at: Option<usize>) -> Self {
+            Trickle {
+                bytes: bytes.to_vec(),
+                pos: 0,
+                interrupt: false,
+                fail_at,
+            }
+        }
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            if self.fail_at == Some(self.pos) {
+                self.fail_at = None;
+                return Err(io::Error::other("boom"));
+            }
+            if self.pos == self.bytes.len() {
+                return Ok(0);
+            }
+            buf[0] = self.bytes[self.pos];
+            self.pos += 1;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_char_reader_one_byte_reads() {
+        let text = "aé€😀ข".repeat(20);
+        let chars: Result<String, _> =
+            CharReader::new(Trickle::new(text.as_bytes(), None)).collect();
+        assert_eq!(chars.unwrap(), text);
+        let chars: Result<String, _> = CharReader::new(Cursor::new(text.as_bytes())).collect();
+        assert_eq!(chars.unwrap(), text);
+    }
+
+    #[test]
+    fn test_char_reader_io_error_mid_character() {
+        // Fails after the first 2 bytes of the euro sign.
+        let mut reader = CharReader::new(Trickle::new("a€b".as_bytes(), Some(3)));
+        assert_eq!(reader.next().unwrap().unwrap(), 'a');
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(!err.is_utf8());
+        assert_eq!(reader.next().unwrap().unwrap(), '€');
+        assert_eq!(reader.next().unwrap().unwrap(), 'b');
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_char_reader_utf8_errors() {
+        let mut reader = CharReader::new(Cursor::new(b"ab\xe0\x80c".to_vec()));
+        assert_eq!(reader.by_ref().take(2).count(), 2);
+        match reader.next() {
+            Some(Err(StreamError::Utf8(err))) => {
+                assert_eq!((err.offset(), err.kind()), (2, UtfError::OverlongSequence))
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(reader.next().is_none());
+
+        let mut reader = CharReader::new(Trickle::new(b"a\xf0\x9f\x98", None));
+        assert_eq!(reader.next().unwrap().unwrap(), 'a');
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.utf8_offset(), Some(1));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_char_reader_fused_after_error() {
+        let mut reader = CharReader::new(Trickle::new(b"a\xffbcd", None));
+        assert_eq!(reader.next().unwrap().unwrap(), 'a');
+        assert!(reader.next().unwrap().is_err());
+        let read = reader.get_ref().pos;
+        for _ in 0..10 {
+            assert!(reader.next().is_none());
+        }
+        // Nothing more was read from the inner reader.
+        assert_eq!(reader.get_ref().pos, read);
+
+        let mut reader = CharReader::new(Cursor::new(b"a".to_vec()));
+        assert_eq!(reader.by_ref().count(), 1);
+        assert!(reader.next().is_none());
+    }
+}
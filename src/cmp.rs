@@ -0,0 +1,104 @@
+use core::cmp::Ordering;
+
+use crate::error::Utf8ErrorAt;
+use crate::validate::validate;
+
+/// Sort key of a code point in UTF-16 code unit order: supplementary
+/// characters, encoded with surrogates, sort between U+D7FF and U+E000.
+#[inline]
+const fn utf16_key(code_point: u32) -> u32 {
+    if code_point >= 0xe000 && code_point <= 0xffff {
+        code_point + 0x110000
+    } else {
+        code_point
+    }
+}
+
+/// Compares `a` and `b` in the order of their UTF-16 encodings.
+///
+/// Both are validated completely; an error in `a` is reported before one in
+/// `b`.
+pub fn cmp_utf16_order(a: &[u8], b: &[u8]) -> Result<Ordering, Utf8ErrorAt> {
+    validate(a)?;
+    validate(b)?;
+    // SAFETY: both were just validated.
+    let (a, b) = unsafe {
+        (
+            core::str::from_utf8_unchecked(a),
+            core::str::from_utf8_unchecked(b),
+        )
+    };
+    // Only the first differing character matters; equal bytes before it
+    // mean equal characters.
+    let mut start = a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+    while !a.is_char_boundary(start) {
+        start -= 1;
+    }
+    let ordering = match (a[start..].chars().next(), b[start..].chars().next()) {
+        (Some(x), Some(y)) => utf16_key(x as u32).cmp(&utf16_key(y as u32)),
+        (x, y) => x.is_some().cmp(&y.is_some()),
+    };
+    Ok(ordering)
+}
+
+#[cfg(test)]
+mod test_cmp {
+    use super::*;
+    use crate::test_util::XorShift;
+
+    fn utf16_cmp(a: &str, b: &str) -> Ordering {
+        a.encode_utf16().cmp(b.encode_utf16())
+    }
+
+    #[test]
+    fn test_cmp_utf16_order_fixup() {
+        // U+FF21 sorts before U+1F600 in code point order, after it in UTF-16.
+        let (bmp, emoji) = ("\u{ff21}", "\u{1f600}");
+        assert_eq!(bmp.cmp(emoji), Ordering::Less);
+        assert_eq!(
+            cmp_utf16_order(bmp.as_bytes(), emoji.as_bytes()),
+            Ok(Ordering::Greater)
+        );
+        assert_eq!(cmp_utf16_order(b"ab", b"abc"), Ok(Ordering::Less));
+        assert_eq!(cmp_utf16_order(b"", b""), Ok(Ordering::Equal));
+        assert_eq!(
+            cmp_utf16_order("a€".as_bytes(), "a€".as_bytes()),
+            Ok(Ordering::Equal)
+        );
+        assert_eq!(cmp_utf16_order(b"b\xff", b"a").unwrap_err().offset(), 1);
+    }
+
+    #[test]
+    fn test_cmp_utf16_order_property() {
+        // Characters around the ranges the fix-up moves around.
+        let alphabet = [
+            'a',
+            'é',
+            '\u{d7ff}',
+            '\u{e000}',
+            '\u{ffff}',
+            '\u{10000}',
+            '😀',
+            '\u{10ffff}',
+        ];
+        let mut rng = XorShift::new(230);
+        let mut random = |max_len| -> String {
+            let len = rng.below(max_len);
+            (0..len)
+                .map(|_| alphabet[rng.below(alphabet.len())])
+                .collect()
+        };
+        for _ in 0..5000 {
+            let prefix = random(3);
+            let a = prefix.clone() + &random(4);
+            let b = prefix + &random(4);
+            assert_eq!(
+                cmp_utf16_order(a.as_bytes(), b.as_bytes()),
+                Ok(utf16_cmp(&a, &b)),
+                "{:?} {:?}",
+                a,
+                b
+            );
+        }
+    }
+}
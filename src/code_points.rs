@@ -0,0 +1,137 @@
+use core::iter::FusedIterator;
+
+use crate::core::{decode_step, is_continuation, UtfError};
+use crate::error::Utf8ErrorAt;
+use crate::validate::validate;
+
+/// Counts the characters of `bytes`, or returns its first error.
+///
+/// Once `bytes` is validated, counting only looks for the bytes that are not
+/// continuation bytes.
+pub fn count_code_points(bytes: &[u8]) -> Result<usize, Utf8ErrorAt> {
+    validate(bytes)?;
+    Ok(bytes.iter().filter(|&&byte| !is_continuation(byte)).count())
+}
+
+/// Iterator over the characters of a byte slice and their byte offsets,
+/// created by [`code_point_indices`].
+///
+/// After the first error the iterator is exhausted.
+#[derive(Clone, Debug)]
+pub struct CodePointIndices<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+/// Decodes `bytes` like [`str::char_indices`], yielding each character with
+/// the offset of its first byte.
+pub fn code_point_indices(bytes: &[u8]) -> CodePointIndices<'_> {
+    CodePointIndices {
+        bytes,
+        pos: 0,
+        failed: false,
+    }
+}
+
+impl CodePointIndices<'_> {
+    /// Offset of the next character; after an error, of the offending
+    /// sequence.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+}
+
+impl Iterator for CodePointIndices<'_> {
+    type Item = Result<(usize, char), UtfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let rest = self.bytes.get(self.pos..).filter(|rest| !rest.is_empty())?;
+        match decode_step(rest) {
+            Ok((code_point, length)) => {
+                let start = self.pos;
+                self.pos += length;
+                char::from_u32(code_point).map(|c| Ok((start, c)))
+            }
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err.kind()))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.failed {
+            return (0, Some(0));
+        }
+        let rest = self.bytes.len() - self.pos;
+        // An error ends the iteration early.
+        (usize::from(rest != 0), Some(rest))
+    }
+}
+
+impl FusedIterator for CodePointIndices<'_> {}
+
+#[cfg(test)]
+mod test_code_points {
+    use super::*;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    #[test]
+    fn test_code_point_indices_matches_char_indices() {
+        let inputs = [
+            "",
+            "abc",
+            "¡¢£¤¥¦§¨©ª«¬\u{AD}®¯°",
+            "ขฃคฅฆงจฉชซฌญฎฏฐฑฒณดตถ",
+            "😀𒀀𒀁𒀂 mixed é€😀ข",
+        ];
+        for input in inputs.iter() {
+            let expected: Vec<_> = input.char_indices().map(Ok).collect();
+            let mut it = code_point_indices(input.as_bytes());
+            assert_eq!(it.by_ref().collect::<Vec<_>>(), expected);
+            assert_eq!(it.offset(), input.len());
+            assert_eq!(
+                count_code_points(input.as_bytes()),
+                Ok(input.chars().count())
+            );
+        }
+    }
+
+    #[test]
+    fn test_first_error_matches_validate() {
+        let mut rng = XorShift::new(268);
+        for _ in 0..1000 {
+            let fragments = rng.below(40);
+            let input = random_corrupt(&mut rng, fragments);
+            let mut it = code_point_indices(&input);
+            let decoded = it.by_ref().take_while(Result::is_ok).count();
+            assert_eq!(it.next(), None);
+            match validate(&input) {
+                Ok(()) => {
+                    assert_eq!(it.offset(), input.len());
+                    assert_eq!(count_code_points(&input), Ok(decoded));
+                }
+                Err(err) => {
+                    assert_eq!(it.offset(), err.offset(), "{:x?}", input);
+                    assert_eq!(count_code_points(&input), Err(err));
+                    let prefix = &input[..err.offset()];
+                    assert_eq!(count_code_points(prefix), Ok(decoded));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_code_point_indices_error_kind() {
+        let mut it = code_point_indices(b"a\xe2\x82");
+        assert_eq!(it.next(), Some(Ok((0, 'a'))));
+        assert_eq!(it.next(), Some(Err(UtfError::NotEnoughRoom)));
+        assert_eq!(it.offset(), 1);
+        assert_eq!(it.next(), None);
+    }
+}
@@ -0,0 +1,186 @@
+use alloc::vec::Vec;
+
+use crate::core::decode_step;
+use crate::error::Utf8ErrorAt;
+
+/// Sorted, non-overlapping inclusive ranges of nonspacing (Mn) and enclosing
+/// (Me) marks.
+///
+/// This is the subset of the Unicode data that matters for stacking: the
+/// combining blocks, Hebrew, Arabic, Syriac, Thai, Lao, Tibetan, Devanagari
+/// and the variation selectors. Marks of other scripts are treated as base
+/// characters.
+const COMBINING: &[(u32, u32)] = &[
+    (0x0300, 0x036f),
+    (0x0483, 0x0489),
+    (0x0591, 0x05bd),
+    (0x05bf, 0x05bf),
+    (0x05c1, 0x05c2),
+    (0x05c4, 0x05c5),
+    (0x05c7, 0x05c7),
+    (0x0610, 0x061a),
+    (0x064b, 0x065f),
+    (0x0670, 0x0670),
+    (0x06d6, 0x06dc),
+    (0x06df, 0x06e4),
+    (0x06e7, 0x06e8),
+    (0x06ea, 0x06ed),
+    (0x0711, 0x0711),
+    (0x0730, 0x074a),
+    (0x0900, 0x0902),
+    (0x093a, 0x093a),
+    (0x093c, 0x093c),
+    (0x0941, 0x0948),
+    (0x094d, 0x094d),
+    (0x0951, 0x0957),
+    (0x0962, 0x0963),
+    (0x0e31, 0x0e31),
+    (0x0e34, 0x0e3a),
+    (0x0e47, 0x0e4e),
+    (0x0eb1, 0x0eb1),
+    (0x0eb4, 0x0ebc),
+    (0x0ec8, 0x0ecd),
+    (0x0f18, 0x0f19),
+    (0x0f35, 0x0f35),
+    (0x0f37, 0x0f37),
+    (0x0f39, 0x0f39),
+    (0x0f71, 0x0f7e),
+    (0x0f80, 0x0f84),
+    (0x0f86, 0x0f87),
+    (0x0f8d, 0x0fbc),
+    (0x1ab0, 0x1aff),
+    (0x1dc0, 0x1dff),
+    (0x20d0, 0x20f0),
+    (0x2cef, 0x2cf1),
+    (0x2de0, 0x2dff),
+    (0x302a, 0x302d),
+    (0x3099, 0x309a),
+    (0xa66f, 0xa672),
+    (0xa674, 0xa67d),
+    (0xa69e, 0xa69f),
+    (0xa6f0, 0xa6f1),
+    (0xfe00, 0xfe0f),
+    (0xfe20, 0xfe2f),
+    (0x101fd, 0x101fd),
+    (0x1d167, 0x1d169),
+    (0x1d17b, 0x1d182),
+    (0x1d185, 0x1d18b),
+    (0x1d1aa, 0x1d1ad),
+    (0xe0100, 0xe01ef),
+];
+
+#[inline]
+fn is_combining(code_point: u32) -> bool {
+    code_point >= COMBINING[0].0
+        && COMBINING
+            .binary_search_by(|&(start, end)| {
+                if end < code_point {
+                    core::cmp::Ordering::Less
+                } else if start > code_point {
+                    core::cmp::Ordering::Greater
+                } else {
+                    core::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+}
+
+/// Validates `bytes` and returns the byte offset and byte length of every
+/// run of more than `max_run` consecutive combining marks.
+pub fn scan_combining_runs(
+    bytes: &[u8],
+    max_run: usize,
+) -> Result<Vec<(usize, usize)>, Utf8ErrorAt> {
+    let mut runs = Vec::new();
+    let (mut run_start, mut run_chars) = (0, 0);
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let (code_point, length) = if bytes[pos] < 0x80 {
+            (bytes[pos] as u32, 1)
+        } else {
+            decode_step(&bytes[pos..]).map_err(|err| err.at(pos))?
+        };
+        if is_combining(code_point) {
+            if run_chars == 0 {
+                run_start = pos;
+            }
+            run_chars += 1;
+        } else {
+            if run_chars > max_run {
+                runs.push((run_start, pos - run_start));
+            }
+            run_chars = 0;
+        }
+        pos += length;
+    }
+    if run_chars > max_run {
+        runs.push((run_start, pos - run_start));
+    }
+    Ok(runs)
+}
+
+#[cfg(test)]
+mod test_combining {
+    use super::*;
+    use crate::core::UtfError;
+
+    #[test]
+    fn test_combining_table_sorted() {
+        for pair in COMBINING.windows(2) {
+            assert!(
+                pair[0].0 <= pair[0].1 && pair[0].1 < pair[1].0,
+                "{:x?}",
+                pair
+            );
+        }
+        assert!(is_combining(0x0301));
+        assert!(is_combining(0x0e48));
+        assert!(is_combining(0xe01ef));
+        assert!(!is_combining('a' as u32));
+        assert!(!is_combining(0x0370));
+        assert!(!is_combining(0x10ffff));
+    }
+
+    #[test]
+    fn test_scan_combining_runs_zalgo() {
+        let zalgo = "Z\u{0351}\u{036b}\u{0343}\u{036a}\u{0302}\u{036b}\u{033d}\u{034f}\u{0334}\u{0319}\u{0324}\u{031e}\u{0349}\u{035a}\u{032f}a\u{0310}\u{0301}lgo";
+        let runs = scan_combining_runs(zalgo.as_bytes(), 4).unwrap();
+        assert_eq!(runs, [(1, 30)]);
+        assert_eq!(
+            scan_combining_runs(zalgo.as_bytes(), 1).unwrap(),
+            [(1, 30), (32, 4)]
+        );
+        assert!(scan_combining_runs(zalgo.as_bytes(), 15)
+            .unwrap()
+            .is_empty());
+        // A run at the very end.
+        let trailing = format!("x{}", "\u{20dd}".repeat(10));
+        assert_eq!(
+            scan_combining_runs(trailing.as_bytes(), 3).unwrap(),
+            [(1, 30)]
+        );
+    }
+
+    #[test]
+    fn test_scan_combining_runs_legitimate_text() {
+        // Vietnamese in decomposed form stacks two marks, Thai a vowel and a
+        // tone mark.
+        let vietnamese =
+            "Tie\u{0323}\u{0302}ng Vie\u{0323}\u{0302}t co\u{0301} da\u{0302}\u{0301}u";
+        assert!(scan_combining_runs(vietnamese.as_bytes(), 2)
+            .unwrap()
+            .is_empty());
+        let thai = "ที่นี่มีน้ำ ภาษาไทย";
+        assert!(scan_combining_runs(thai.as_bytes(), 2).unwrap().is_empty());
+        assert_eq!(
+            scan_combining_runs(thai.as_bytes(), 1).unwrap(),
+            [(3, 6), (12, 6)]
+        );
+    }
+
+    #[test]
+    fn test_scan_combining_runs_invalid() {
+        let err = scan_combining_runs(b"a\xcc", 3).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, UtfError::NotEnoughRoom));
+    }
+}
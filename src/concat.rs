@@ -0,0 +1,202 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use crate::core::{decode_step, is_continuation, sequence_length, UtfError};
+use crate::error::Utf8ErrorAt;
+use crate::stream::{tail_error, validate_chunk};
+
+/// One piece of a concatenation checked by [`validate_concat`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ValidatedPiece<'a> {
+    /// Known to be valid UTF-8, except that a character may be cut at either
+    /// end, e.g. a slice of validated data. Only its edges are checked.
+    Validated(&'a [u8]),
+    /// Not validated yet; checked completely.
+    Unvalidated(&'a [u8]),
+}
+
+/// Error of [`validate_concat`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SeamError {
+    piece: usize,
+    error: Utf8ErrorAt,
+}
+
+impl SeamError {
+    #[inline]
+    fn new(piece: usize, error: Utf8ErrorAt) -> Self {
+        SeamError { piece, error }
+    }
+
+    /// Index of the piece in which the offending sequence starts.
+    #[inline]
+    pub fn piece(&self) -> usize {
+        self.piece
+    }
+
+    /// The error, with its offset from the start of the concatenation.
+    #[inline]
+    pub fn error(&self) -> Utf8ErrorAt {
+        self.error
+    }
+}
+
+impl fmt::Display for SeamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (piece {})", self.error, self.piece)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for SeamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static INSPECTED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Counts bytes looked at, so tests can check the work done.
+#[inline]
+fn inspect(_count: usize) {
+    #[cfg(test)]
+    INSPECTED.with(|inspected| inspected.set(inspected.get() + _count));
+}
+
+/// Length of the cut character at the end of valid `bytes`.
+#[inline]
+fn trusted_tail(bytes: &[u8]) -> usize {
+    let floor = bytes.len().saturating_sub(3);
+    for pos in (floor..bytes.len()).rev() {
+        inspect(1);
+        if !is_continuation(bytes[pos]) {
+            let tail = bytes.len() - pos;
+            return if sequence_length(bytes[pos]) > tail {
+                tail
+            } else {
+                0
+            };
+        }
+    }
+    0
+}
+
+/// Checks that the concatenation of `pieces` is valid UTF-8, looking only at
+/// the edges of validated pieces.
+pub fn validate_concat(pieces: &[ValidatedPiece<'_>]) -> Result<(), SeamError> {
+    // A character cut at the end of a piece, and where it started.
+    let mut carry = [0; 4];
+    let mut carry_len = 0;
+    let mut carry_start = (0, 0);
+    let mut base = 0usize;
+    for (index, piece) in pieces.iter().enumerate() {
+        let (mut bytes, validated) = match *piece {
+            ValidatedPiece::Validated(bytes) => (bytes, true),
+            ValidatedPiece::Unvalidated(bytes) => (bytes, false),
+        };
+        base = base.saturating_add(bytes.len());
+        if carry_len != 0 && !bytes.is_empty() {
+            let take = bytes.len().min(4 - carry_len);
+            inspect(take);
+            carry[carry_len..carry_len + take].copy_from_slice(&bytes[..take]);
+            match decode_step(&carry[..carry_len + take]) {
+                Ok((_, length)) => {
+                    bytes = &bytes[length - carry_len..];
+                    carry_len = 0;
+                }
+                Err(err) if err.error_len().is_none() => {
+                    carry_len += take;
+                    continue;
+                }
+                Err(err) => return Err(SeamError::new(carry_start.0, err.at(carry_start.1))),
+            }
+        }
+        let start = base - bytes.len();
+        let tail = if validated {
+            if let Some(&first) = bytes.first() {
+                inspect(1);
+                if is_continuation(first) {
+                    let error = Utf8ErrorAt::new(start, UtfError::InvalidLead, 1);
+                    return Err(SeamError::new(index, error));
+                }
+            }
+            trusted_tail(bytes)
+        } else {
+            inspect(bytes.len());
+            validate_chunk(bytes).map_err(|err| SeamError::new(index, err.at(start)))?
+        };
+        if tail != 0 {
+            carry[..tail].copy_from_slice(&bytes[bytes.len() - tail..]);
+            carry_len = tail;
+            carry_start = (index, base - tail);
+        }
+    }
+    if carry_len != 0 {
+        let error = tail_error(&carry[..carry_len]).at(carry_start.1);
+        return Err(SeamError::new(carry_start.0, error));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_concat {
+    use super::*;
+    use ValidatedPiece::{Unvalidated, Validated};
+
+    fn inspected(pieces: &[ValidatedPiece<'_>]) -> (Result<(), SeamError>, usize) {
+        INSPECTED.with(|inspected| inspected.set(0));
+        let result = validate_concat(pieces);
+        (result, INSPECTED.with(|inspected| inspected.get()))
+    }
+
+    #[test]
+    fn test_concat_clean_pieces_inspect_only_edges() {
+        let long = "long piece of validated text, é€😀 ".repeat(100);
+        let pieces = [Validated(long.as_bytes()); 10];
+        let (result, count) = inspected(&pieces);
+        assert_eq!(result, Ok(()));
+        // The first byte and at most 3 trailing bytes of each piece.
+        assert!(count <= 4 * pieces.len(), "{}", count);
+    }
+
+    #[test]
+    fn test_concat_split_character() {
+        let text = "ab😀cd".as_bytes();
+        for split in 0..=text.len() {
+            let (a, b) = text.split_at(split);
+            assert_eq!(validate_concat(&[Validated(a), Validated(b)]), Ok(()));
+            assert_eq!(validate_concat(&[Unvalidated(a), Validated(b)]), Ok(()));
+        }
+        let bytes: Vec<_> = text.chunks(1).map(Validated).collect();
+        assert_eq!(validate_concat(&bytes), Ok(()));
+
+        // Half of the emoji followed by something else.
+        let err = validate_concat(&[Validated(&text[..4]), Validated(b"cd")]).unwrap_err();
+        assert_eq!(err.piece(), 0);
+        assert_eq!(
+            (err.error().offset(), err.error().kind()),
+            (2, UtfError::IncompleteSequence)
+        );
+        // The end of the emoji without its start.
+        let err = validate_concat(&[Validated(b"ab"), Validated(&text[4..])]).unwrap_err();
+        assert_eq!((err.piece(), err.error().offset()), (1, 2));
+        // Nothing after the first half.
+        let err = validate_concat(&[Validated(b"x"), Validated(&text[..4])]).unwrap_err();
+        assert_eq!(
+            (err.piece(), err.error().kind()),
+            (1, UtfError::NotEnoughRoom)
+        );
+    }
+
+    #[test]
+    fn test_concat_unvalidated_pieces() {
+        let err = validate_concat(&[Validated(b"ok"), Unvalidated(b"a\xffb")]).unwrap_err();
+        assert_eq!((err.piece(), err.error().offset()), (1, 3));
+        let (result, count) = inspected(&[Unvalidated(&[0x61; 50])]);
+        assert_eq!((result, count), (Ok(()), 50));
+    }
+}
@@ -1,5 +1,8 @@
-const LEAD_SURROGATE_MIN: u16 = 0xd800;
-const TRAIL_SURROGATE_MAX: u16 = 0xdfff;
+use crate::decode_utf8::{AsByte, TryAsByte};
+use crate::error::{DecodeError, Utf8ErrorAt};
+
+const LEAD_SURROGATE_MIN: u32 = 0xd800;
+const TRAIL_SURROGATE_MAX: u32 = 0xdfff;
 const CODE_POINT_MAX: u32 = 0x0010ffff;
 
 macro_rules! mask8 {
@@ -16,18 +19,23 @@ macro_rules! is_trail {
 
 macro_rules! is_surrogate {
     ($cp:expr) => {{
-        ($cp >= LEAD_SURROGATE_MIN && $cp <= TRAIL_SURROGATE_MAX)
+        (LEAD_SURROGATE_MIN..=TRAIL_SURROGATE_MAX).contains(&$cp)
     }};
 }
 
 macro_rules! is_code_point_valid {
     ($cp:expr) => {{
-        ($cp <= CODE_POINT_MAX && !is_surrogate!($cp as u16))
+        ($cp <= CODE_POINT_MAX && !is_surrogate!($cp))
     }};
 }
 
 #[inline]
-const fn sequence_length(lead_byte: u8) -> usize {
+pub(crate) const fn is_continuation(byte: u8) -> bool {
+    is_trail!(byte)
+}
+
+#[inline]
+pub(crate) const fn sequence_length(lead_byte: u8) -> usize {
     if lead_byte < 0x80 {
         1
     } else if (lead_byte >> 5) == 0x6 {
@@ -42,7 +50,7 @@ const fn sequence_length(lead_byte: u8) -> usize {
 }
 
 #[inline]
-const fn is_overlong_sequence(cp: u32, length: usize) -> bool {
+pub(crate) const fn is_overlong_sequence(cp: u32, length: usize) -> bool {
     if cp < 0x80 {
         if length != 1 {
             return true;
@@ -51,14 +59,16 @@ const fn is_overlong_sequence(cp: u32, length: usize) -> bool {
         if length != 2 {
             return true;
         }
-    } else if cp < 0x10000 {
-        if length != 3 {
-            return true;
-        }
+    } else if cp < 0x10000 && length != 3 {
+        return true;
     }
     false
 }
 
+/// Kind of a UTF-8 error, with the stable codes of
+/// [`code`](Self::code); this is the error kind type, there is no separate
+/// `UtfErrorKind`.
+#[doc(alias = "UtfErrorKind")]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum UtfError {
     NotEnoughRoom,
@@ -69,15 +79,18 @@ pub enum UtfError {
 }
 
 #[inline]
-fn get_next_byte<'a, I>(it: &mut I) -> Result<&'a u8, UtfError>
+fn get_next_byte<I>(it: &mut I) -> Result<u8, UtfError>
 where
-    I: Iterator<Item = &'a u8>,
+    I: Iterator,
+    I::Item: AsByte,
 {
-    it.next().ok_or(UtfError::NotEnoughRoom)
+    it.next()
+        .map(|byte| byte.as_byte())
+        .ok_or(UtfError::NotEnoughRoom)
 }
 
 #[inline]
-fn is_trail(byte: &u8) -> Result<&u8, UtfError> {
+fn is_trail(byte: u8) -> Result<u8, UtfError> {
     if is_trail!(byte) {
         Ok(byte)
     } else {
@@ -85,87 +98,398 @@ fn is_trail(byte: &u8) -> Result<&u8, UtfError> {
     }
 }
 
+/// Reads a trail byte, returning its 6 payload bits.
 #[inline]
-fn get_sequence_1<'a, I>(it: &mut I) -> Result<u32, UtfError>
+fn get_trail<I>(it: &mut I) -> Result<u32, UtfError>
 where
-    I: Iterator<Item = &'a u8>,
+    I: Iterator,
+    I::Item: AsByte,
 {
-    get_next_byte(it).map(|byte| *byte as u32)
+    get_next_byte(it)
+        .and_then(is_trail)
+        .map(|byte| (byte & 0x3f) as u32)
 }
 
+// The lead byte was already read and dispatched on by the caller, so each of
+// these only reads the trail bytes.
+
 #[inline]
-fn get_sequence_2<'a, I>(it: &mut I) -> Result<u32, UtfError>
+fn get_sequence_2<I>(lead: u8, it: &mut I) -> Result<u32, UtfError>
 where
-    I: Iterator<Item = &'a u8>,
+    I: Iterator,
+    I::Item: AsByte,
 {
-    let code_point = get_sequence_1(it)?;
-    let code_point = get_next_byte(it)
-        .and_then(is_trail)
-        .and_then(|byte| Ok(((code_point << 6) & 0x7ff) + ((byte & 0x3f) as u32)))?;
-    Ok(code_point)
+    let code_point = ((lead & 0x1f) as u32) << 6;
+    Ok(code_point | get_trail(it)?)
 }
 
 #[inline]
-fn get_sequence_3<'a, I>(it: &mut I) -> Result<u32, UtfError>
+fn get_sequence_3<I>(lead: u8, it: &mut I) -> Result<u32, UtfError>
 where
-    I: Iterator<Item = &'a u8>,
+    I: Iterator,
+    I::Item: AsByte,
 {
-    let code_point = get_sequence_1(it)?;
-    let code_point = get_next_byte(it)
-        .and_then(is_trail)
-        .and_then(|byte| Ok(((code_point << 12) & 0xffff) + (((*byte as u32) << 6) & 0xfff)))?;
-    let code_point = get_next_byte(it)
-        .and_then(is_trail)
-        .and_then(|byte| Ok(code_point + ((byte & 0x3f) as u32)))?;
-    Ok(code_point)
+    let code_point = ((lead & 0x0f) as u32) << 12;
+    let code_point = code_point | (get_trail(it)? << 6);
+    Ok(code_point | get_trail(it)?)
 }
 
 #[inline]
-fn get_sequence_4<'a, I>(it: &mut I) -> Result<u32, UtfError>
+fn get_sequence_4<I>(lead: u8, it: &mut I) -> Result<u32, UtfError>
 where
-    I: Iterator<Item = &'a u8>,
+    I: Iterator,
+    I::Item: AsByte,
 {
-    let code_point = get_sequence_1(it)?;
-    let code_point = get_next_byte(it).and_then(is_trail).and_then(|byte| {
-        Ok(((code_point << 18) & 0x1fffff) + (((*byte as u32) << 12) & 0x3ffff))
-    })?;
-    let code_point = get_next_byte(it)
-        .and_then(is_trail)
-        .and_then(|byte| Ok(code_point + (((*byte as u32) << 6) & 0xfff)))?;
-    let code_point = get_next_byte(it)
-        .and_then(is_trail)
-        .and_then(|byte| Ok(code_point + (((byte) & 0x3f) as u32)))?;
-    Ok(code_point)
+    let code_point = ((lead & 0x07) as u32) << 18;
+    let code_point = code_point | (get_trail(it)? << 12);
+    let code_point = code_point | (get_trail(it)? << 6);
+    Ok(code_point | get_trail(it)?)
+}
+
+/// Decodes the next character from `it`, or returns `None` if `it` is
+/// exhausted at a character boundary.
+///
+/// On error the bytes read so far are gone: the lead byte alone for an
+/// [`InvalidLead`](UtfError::InvalidLead), the lead and every trail byte up
+/// to and including the byte that is not one for an
+/// [`IncompleteSequence`](UtfError::IncompleteSequence), and the whole
+/// sequence otherwise. To resume decoding after an error, use
+/// [`validate_next_counted`].
+#[inline]
+pub fn next_code_point<I>(it: &mut I) -> Option<Result<char, UtfError>>
+where
+    I: Iterator,
+    I::Item: AsByte,
+{
+    let lead = it.next()?.as_byte();
+    if lead < 0x80 {
+        return Some(Ok(lead as char));
+    }
+    // The errors of each length never overlap, so they are the same as when
+    // checking the code point first and the length it is encoded in second.
+    let result = match sequence_length(lead) {
+        2 => get_sequence_2(lead, it).and_then(|code_point| match code_point {
+            0x80.. => Ok(code_point),
+            _ => Err(UtfError::OverlongSequence),
+        }),
+        3 => get_sequence_3(lead, it).and_then(|code_point| match code_point {
+            0..=0x7ff => Err(UtfError::OverlongSequence),
+            LEAD_SURROGATE_MIN..=TRAIL_SURROGATE_MAX => Err(UtfError::InvalidCodePoint),
+            _ => Ok(code_point),
+        }),
+        4 => get_sequence_4(lead, it).and_then(|code_point| match code_point {
+            0..=0xffff => Err(UtfError::OverlongSequence),
+            0x10000..=CODE_POINT_MAX => Ok(code_point),
+            _ => Err(UtfError::InvalidCodePoint),
+        }),
+        _ => Err(UtfError::InvalidLead),
+    }
+    .map(|code_point| {
+        debug_assert!(is_code_point_valid!(code_point), "{:#x}", code_point);
+        // SAFETY: surrogates and values above U+10FFFF were rejected above.
+        unsafe { char::from_u32_unchecked(code_point) }
+    });
+    Some(result)
 }
 
+/// [`next_code_point`] returning the code point as a `u32`. An empty
+/// iterator is an [`InvalidLead`](UtfError::InvalidLead) that consumes
+/// nothing.
 #[inline]
-pub fn validate_next<'a, I>(it: &mut I) -> Result<u32, UtfError>
+pub fn validate_next<I>(it: &mut I) -> Result<u32, UtfError>
+where
+    I: Iterator,
+    I::Item: AsByte,
+{
+    match next_code_point(it) {
+        Some(result) => result.map(u32::from),
+        None => Err(UtfError::InvalidLead),
+    }
+}
+
+/// [`next_code_point`] over a source that can fail, like
+/// [`std::io::Bytes`].
+///
+/// An error of the source is returned as is, also in the middle of a
+/// sequence, and ends the sequence: the bytes read before it are gone.
+pub fn validate_next_fallible<I, E>(it: &mut I) -> Option<Result<char, DecodeError<E>>>
+where
+    I: Iterator,
+    I::Item: TryAsByte<Error = E>,
+{
+    let mut error = None;
+    let result = next_code_point(&mut it.map_while(|item| match item.try_as_byte() {
+        Ok(byte) => Some(byte),
+        Err(err) => {
+            error = Some(err);
+            None
+        }
+    }));
+    match error {
+        Some(err) => Some(Err(DecodeError::Source(err))),
+        None => result.map(|result| result.map_err(DecodeError::Utf8)),
+    }
+}
+
+/// A UTF-8 variant accepted by [`validate_next_with`].
+///
+/// See [`canonicalize`](crate::canonicalize) to convert the variants to
+/// standard UTF-8.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ValidationProfile {
+    /// Standard UTF-8, as accepted by [`validate_next`].
+    #[default]
+    Strict,
+    /// WTF-8: UTF-8 that may contain surrogates.
+    Wtf8,
+    /// CESU-8: supplementary characters only as pairs of 3-byte surrogates.
+    Cesu8,
+    /// Java's Modified UTF-8: CESU-8 with U+0000 also as `C0 80`.
+    ModifiedUtf8,
+}
+
+/// Decodes the next code point from `it` as `profile`.
+///
+/// [`Strict`](ValidationProfile::Strict) is [`validate_next`]. With
+/// [`Wtf8`](ValidationProfile::Wtf8) a surrogate is returned as is; since
+/// only one sequence is read, a high surrogate followed by a low one, which
+/// WTF-8 encodes as a single 4-byte sequence, is not caught. The CESU-8
+/// profiles return a surrogate pair as the supplementary code point it
+/// encodes, and reject 4-byte sequences as an
+/// [`InvalidLead`](UtfError::InvalidLead) and unpaired surrogates as an
+/// [`InvalidCodePoint`](UtfError::InvalidCodePoint).
+///
+/// On error the bytes read so far are gone, as with `validate_next`; after a
+/// high surrogate that includes the lead of the sequence after it.
+pub fn validate_next_with<I>(it: &mut I, profile: ValidationProfile) -> Result<u32, UtfError>
 where
-    I: Iterator<Item = &'a u8>,
+    I: Iterator,
+    I::Item: AsByte,
 {
-    let mut it = it.peekable();
-    let lead = it.peek().ok_or(UtfError::InvalidLead)?;
-    let length = sequence_length(**lead);
+    if profile == ValidationProfile::Strict {
+        return validate_next(it);
+    }
+    let cesu = profile != ValidationProfile::Wtf8;
+    let lead = it.next().ok_or(UtfError::InvalidLead)?.as_byte();
+    let length = sequence_length(lead);
     let code_point = match length {
-        0 => Err(UtfError::InvalidLead),
-        1 => get_sequence_1(&mut it),
-        2 => get_sequence_2(&mut it),
-        3 => get_sequence_3(&mut it),
-        4 => get_sequence_4(&mut it),
-        _ => unreachable!(),
-    }
-    .and_then(|code_point| {
-        if is_code_point_valid!(code_point) {
-            if !is_overlong_sequence(code_point, length) {
-                Ok(code_point)
-            } else {
-                Err(UtfError::OverlongSequence)
+        1 => Ok(lead as u32),
+        2 => get_sequence_2(lead, it),
+        3 => get_sequence_3(lead, it),
+        4 if !cesu => get_sequence_4(lead, it),
+        _ => Err(UtfError::InvalidLead),
+    }?;
+    if code_point > CODE_POINT_MAX {
+        return Err(UtfError::InvalidCodePoint);
+    }
+    if is_overlong_sequence(code_point, length) {
+        let nul = profile == ValidationProfile::ModifiedUtf8 && code_point == 0 && length == 2;
+        if !nul {
+            return Err(UtfError::OverlongSequence);
+        }
+    }
+    if !is_surrogate!(code_point) || !cesu {
+        return Ok(code_point);
+    }
+    if code_point >= 0xdc00 {
+        return Err(UtfError::InvalidCodePoint);
+    }
+    let lead = get_next_byte(it)?;
+    if sequence_length(lead) != 3 {
+        return Err(UtfError::InvalidCodePoint);
+    }
+    let low = get_sequence_3(lead, it)?;
+    if !(0xdc00..=TRAIL_SURROGATE_MAX).contains(&low) {
+        return Err(UtfError::InvalidCodePoint);
+    }
+    Ok(0x10000 + ((code_point - LEAD_SURROGATE_MIN) << 10 | (low - 0xdc00)))
+}
+
+/// Decodes the character at the back of `it`, the way
+/// [`validate_next`] decodes the one at the front.
+///
+/// Reads the trail bytes, at most 3, and then the lead; the bytes read are
+/// gone, also on error. A lead that does not take exactly the trail bytes
+/// after it, or a fourth trail byte, is an
+/// [`InvalidLead`](UtfError::InvalidLead); reaching the front of `it` while
+/// still in trail bytes, or a lead that takes more of them, is an
+/// [`IncompleteSequence`](UtfError::IncompleteSequence). An empty iterator is
+/// an `InvalidLead` that consumes nothing.
+pub fn validate_prev<I>(it: &mut I) -> Result<u32, UtfError>
+where
+    I: DoubleEndedIterator,
+    I::Item: AsByte,
+{
+    let mut buf = [0; 4];
+    let mut start = buf.len();
+    loop {
+        let byte = match it.next_back() {
+            Some(byte) => byte.as_byte(),
+            None if start == buf.len() => return Err(UtfError::InvalidLead),
+            None => return Err(UtfError::IncompleteSequence),
+        };
+        if is_continuation(byte) && start == 1 {
+            return Err(UtfError::InvalidLead);
+        }
+        start -= 1;
+        buf[start] = byte;
+        if !is_continuation(byte) {
+            break;
+        }
+    }
+    // The sequence is complete, so decoding it forwards gives the same
+    // errors as `validate_next` apart from a missing trail byte.
+    let mut forward = buf[start..].iter();
+    match next_code_point(&mut forward) {
+        Some(Ok(c)) if forward.len() == 0 => Ok(u32::from(c)),
+        Some(Err(UtfError::NotEnoughRoom)) => Err(UtfError::IncompleteSequence),
+        Some(Err(err)) => Err(err),
+        Some(Ok(_)) | None => Err(UtfError::InvalidLead),
+    }
+}
+
+/// Decodes the next character from `it`, also returning the number of bytes
+/// consumed.
+///
+/// Looks ahead on a clone of `it`, then consumes only the character, or on
+/// error the maximal ill-formed subsequence (see
+/// [`Utf8ErrorAt::skip_len`]): exactly one byte for an
+/// [`InvalidLead`](UtfError::InvalidLead), and never the byte after the
+/// sequence, so decoding can resume right away. An iterator ending in the
+/// middle of a sequence gives a [`NotEnoughRoom`](UtfError::NotEnoughRoom)
+/// that consumes the rest of it, zero bytes if it was empty.
+///
+/// The kind of error is that of [`validate`](crate::validate), decided by the
+/// first byte that cannot continue the sequence. It can differ from the one
+/// [`validate_next`] returns for the same bytes, since `validate_next` reads
+/// the whole sequence before looking at the code point: `E0 80 41` is an
+/// [`OverlongSequence`](UtfError::OverlongSequence) here, at `80`, but an
+/// [`IncompleteSequence`](UtfError::IncompleteSequence) there, at `41`. The
+/// two always agree on whether there is an error and on the code point.
+pub fn validate_next_counted<I>(it: &mut I) -> (Result<u32, UtfError>, usize)
+where
+    I: Iterator + Clone,
+    I::Item: AsByte,
+{
+    let mut buf = [0; 4];
+    let mut len = 0;
+    for (slot, byte) in buf.iter_mut().zip(it.clone()) {
+        *slot = byte.as_byte();
+        len += 1;
+    }
+    let (result, consumed) = match decode_step(&buf[..len]) {
+        Ok((code_point, length)) => (Ok(code_point), length),
+        Err(err) => (Err(err.kind()), err.skip_len()),
+    };
+    if consumed > 0 {
+        it.nth(consumed - 1);
+    }
+    (result, consumed)
+}
+
+/// Range of the second byte of a well-formed sequence starting with `lead`.
+///
+/// Leads that can never start a well-formed sequence (`C0`, `C1`, `F5`..`F7`)
+/// get an empty range.
+#[inline]
+pub(crate) const fn second_byte_range(lead: u8) -> (u8, u8) {
+    match lead {
+        0xe0 => (0xa0, 0xbf),
+        0xed => (0x80, 0x9f),
+        0xf0 => (0x90, 0xbf),
+        0xf4 => (0x80, 0x8f),
+        0xc0 | 0xc1 | 0xf5..=0xf7 => (0xff, 0x00),
+        _ => (0x80, 0xbf),
+    }
+}
+
+/// The error reported for a trail byte outside of [`second_byte_range`].
+#[inline]
+const fn second_byte_error(lead: u8) -> UtfError {
+    match lead {
+        0xc0 | 0xc1 | 0xe0 | 0xf0 => UtfError::OverlongSequence,
+        _ => UtfError::InvalidCodePoint,
+    }
+}
+
+/// Decodes the sequence at the start of `bytes`, returning the code point and
+/// the number of bytes it occupies.
+///
+/// On error the returned offset is `0` and the error length is that of the
+/// maximal ill-formed subsequence, or `None` when `bytes` ends with a
+/// truncated but otherwise well-formed prefix.
+#[inline]
+pub(crate) fn decode_step(bytes: &[u8]) -> Result<(u32, usize), Utf8ErrorAt> {
+    let lead = match bytes.first() {
+        Some(lead) => *lead,
+        None => return Err(Utf8ErrorAt::truncated(0, 0)),
+    };
+    let length = sequence_length(lead);
+    match length {
+        0 => return Err(Utf8ErrorAt::new(0, UtfError::InvalidLead, 1)),
+        1 => return Ok((lead as u32, 1)),
+        _ => {}
+    }
+    let (lo, hi) = second_byte_range(lead);
+    match bytes.get(1) {
+        None if lo > hi => return Err(Utf8ErrorAt::new(0, second_byte_error(lead), 1)),
+        None => return Err(Utf8ErrorAt::truncated(0, 1)),
+        Some(byte) if !is_trail!(*byte) => {
+            return Err(Utf8ErrorAt::new(0, UtfError::IncompleteSequence, 1))
+        }
+        Some(byte) if *byte < lo || *byte > hi => {
+            return Err(Utf8ErrorAt::new(0, second_byte_error(lead), 1))
+        }
+        _ => {}
+    }
+    let mut code_point = (lead as u32) & (0x7f >> length);
+    for i in 1..length {
+        match bytes.get(i) {
+            None => return Err(Utf8ErrorAt::truncated(0, i as u8)),
+            Some(byte) if !is_trail!(*byte) => {
+                return Err(Utf8ErrorAt::new(0, UtfError::IncompleteSequence, i as u8))
             }
-        } else {
-            Err(UtfError::InvalidCodePoint)
+            Some(byte) => code_point = (code_point << 6) | (*byte & 0x3f) as u32,
         }
-    });
-    code_point
+    }
+    debug_assert!(is_code_point_valid!(code_point) && !is_overlong_sequence(code_point, length));
+    Ok((code_point, length))
+}
+
+/// Decodes the character in the low bytes of `word`, which holds the input
+/// in little-endian order, zero-padded past its end.
+///
+/// Returns the code point and its length in bytes without reading memory;
+/// a character running into the padding is an
+/// [`IncompleteSequence`](UtfError::IncompleteSequence).
+#[inline]
+pub fn decode_word(word: u32) -> Result<(u32, u8), UtfError> {
+    let lead = word as u8;
+    let length = sequence_length(lead);
+    match length {
+        0 => return Err(UtfError::InvalidLead),
+        1 => return Ok((lead as u32, 1)),
+        _ => {}
+    }
+    // Index of the first byte after the lead that is not a trail byte, 4 if
+    // there is none.
+    let first_non_trail = ((word ^ 0x8080_8000) & 0xc0c0_c000).trailing_zeros() / 8;
+    let (lo, hi) = second_byte_range(lead);
+    let second = (word >> 8) as u8;
+    if first_non_trail == 1 {
+        return Err(UtfError::IncompleteSequence);
+    }
+    if second < lo || second > hi {
+        return Err(second_byte_error(lead));
+    }
+    if (first_non_trail as usize) < length {
+        return Err(UtfError::IncompleteSequence);
+    }
+    let code_point = ((lead as u32) & (0x7f >> length)) << 18
+        | (word >> 8 & 0x3f) << 12
+        | (word >> 16 & 0x3f) << 6
+        | (word >> 24 & 0x3f);
+    Ok((code_point >> (6 * (4 - length)), length as u8))
 }
 
 #[cfg(test)]
@@ -173,6 +497,7 @@ mod test_core {
     use log::info;
 
     use super::*;
+    use crate::test_util::{random_corrupt, XorShift};
 
     fn init_logger() {
         let _ = env_logger::builder()
@@ -191,7 +516,7 @@ mod test_core {
             info!("try valide {}", c);
             info!("his code {:#b}", c as u32);
             let r = validate_next(&mut it).unwrap();
-            info!("val code {:#b}", r as u32);
+            info!("val code {:#b}", r);
             let r = unsafe { char::from_u32_unchecked(r) };
             assert_eq!(c, r);
         }
@@ -201,13 +526,13 @@ mod test_core {
     #[test]
     fn test_validate_next_2() {
         init_logger();
-        let input = "¡¢£¤¥¦§¨©ª«¬­®¯°±²³´µ¶·¸¹º»¼½¾¿ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔ";
+        let input = "¡¢£¤¥¦§¨©ª«¬\u{AD}®¯°±²³´µ¶·¸¹º»¼½¾¿ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔ";
         let mut it = input.as_bytes().iter();
         for c in input.chars() {
             info!("try valide {}", c);
             info!("his code {:#b}", c as u32);
             let r = validate_next(&mut it).unwrap();
-            info!("val code {:#b}", r as u32);
+            info!("val code {:#b}", r);
             let r = unsafe { char::from_u32_unchecked(r) };
             assert_eq!(c, r);
         }
@@ -223,7 +548,7 @@ mod test_core {
             info!("try valide {}", c);
             info!("his code {:#b}", c as u32);
             let r = validate_next(&mut it).unwrap();
-            info!("val code {:#b}", r as u32);
+            info!("val code {:#b}", r);
             let r = unsafe { char::from_u32_unchecked(r) };
             assert_eq!(c, r);
         }
@@ -239,10 +564,527 @@ mod test_core {
             info!("try valide {}", c);
             info!("his code {:#b}", c as u32);
             let r = validate_next(&mut it).unwrap();
-            info!("val code {:#b}", r as u32);
+            info!("val code {:#b}", r);
             let r = unsafe { char::from_u32_unchecked(r) };
             assert_eq!(c, r);
         }
         assert!(validate_next(&mut it).is_err())
     }
+
+    #[test]
+    fn test_next_code_point_1() {
+        init_logger();
+        let input = "!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+        let mut it = input.as_bytes().iter();
+        for c in input.chars() {
+            info!("try valide {}", c);
+            info!("his code {:#b}", c as u32);
+            assert_eq!(next_code_point(&mut it), Some(Ok(c)));
+        }
+        assert_eq!(next_code_point(&mut it), None);
+    }
+
+    #[test]
+    fn test_next_code_point_2() {
+        init_logger();
+        let input = "¡¢£¤¥¦§¨©ª«¬\u{AD}®¯°±²³´µ¶·¸¹º»¼½¾¿ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔ";
+        let mut it = input.as_bytes().iter();
+        for c in input.chars() {
+            info!("try valide {}", c);
+            info!("his code {:#b}", c as u32);
+            assert_eq!(next_code_point(&mut it), Some(Ok(c)));
+        }
+        assert_eq!(next_code_point(&mut it), None);
+    }
+
+    #[test]
+    fn test_next_code_point_3() {
+        init_logger();
+        let input = "ขฃคฅฆงจฉชซฌญฎฏฐฑฒณดตถทธนบปผฝพฟภมยรฤลฦวศษสหฬอฮฯะัาำิีึืฺุู";
+        let mut it = input.as_bytes().iter();
+        for c in input.chars() {
+            info!("try valide {}", c);
+            info!("his code {:#b}", c as u32);
+            assert_eq!(next_code_point(&mut it), Some(Ok(c)));
+        }
+        assert_eq!(next_code_point(&mut it), None);
+    }
+
+    #[test]
+    fn test_next_code_point_4() {
+        init_logger();
+        let input = "😀𒀀𒀁𒀂𒀃𒀄𒀅𒀆𒀇𒀈𒀉𒀊𒀋𒀌𒀍𒀎𒀏";
+        let mut it = input.as_bytes().iter();
+        for c in input.chars() {
+            info!("try valide {}", c);
+            info!("his code {:#b}", c as u32);
+            assert_eq!(next_code_point(&mut it), Some(Ok(c)));
+        }
+        assert_eq!(next_code_point(&mut it), None);
+    }
+
+    #[test]
+    fn test_next_code_point_end_or_error() {
+        let input = [0x61, 0xe2, 0x82];
+        let mut it = input.iter();
+        assert_eq!(next_code_point(&mut it), Some(Ok('a')));
+        assert_eq!(next_code_point(&mut it), Some(Err(UtfError::NotEnoughRoom)));
+        assert_eq!(next_code_point(&mut it), None);
+
+        let input = [0xed, 0xa0, 0x80];
+        assert_eq!(
+            next_code_point(&mut input.iter()),
+            Some(Err(UtfError::InvalidCodePoint))
+        );
+        // The old signature still reports the end as an error.
+        assert_eq!(
+            validate_next(&mut [0u8; 0].iter()),
+            Err(UtfError::InvalidLead)
+        );
+    }
+
+    #[test]
+    fn test_next_code_point_consumption_on_error() {
+        #[rustfmt::skip]
+        let cases: [(&[u8], UtfError, &[u8]); 8] = [
+            (b"\xffab", UtfError::InvalidLead, b"ab"),
+            (b"\x80\x80a", UtfError::InvalidLead, b"\x80a"),
+            // The byte that is not a trail byte goes with the sequence.
+            (b"\xe2\x82ab", UtfError::IncompleteSequence, b"b"),
+            (b"\xf0\x9fab", UtfError::IncompleteSequence, b"b"),
+            (b"\xc0\xafab", UtfError::OverlongSequence, b"ab"),
+            (b"\xed\xa0\x80ab", UtfError::InvalidCodePoint, b"ab"),
+            (b"\xf4\x90\x80\x80ab", UtfError::InvalidCodePoint, b"ab"),
+            (b"\xf0\x9f\x98", UtfError::NotEnoughRoom, b""),
+        ];
+        for (input, kind, rest) in cases.iter() {
+            let mut it = input.iter();
+            assert_eq!(next_code_point(&mut it), Some(Err(*kind)), "{:x?}", input);
+            assert_eq!(it.as_slice(), *rest, "{:x?}", input);
+        }
+    }
+
+    /// Bytes [`next_code_point`] consumes from the start of `bytes`, as
+    /// documented.
+    fn documented_consumption(bytes: &[u8]) -> usize {
+        let length = sequence_length(bytes[0]);
+        if length == 0 {
+            return 1;
+        }
+        let available = length.min(bytes.len());
+        match bytes[1..available]
+            .iter()
+            .position(|&b| !is_continuation(b))
+        {
+            Some(pos) => pos + 2,
+            None => available,
+        }
+    }
+
+    #[test]
+    fn test_next_code_point_consumption_random() {
+        let mut rng = XorShift::new(274);
+        for _ in 0..2000 {
+            let fragments = rng.below(20);
+            let input = random_corrupt(&mut rng, fragments);
+            let mut it = input.iter();
+            while !it.as_slice().is_empty() {
+                let before = it.as_slice();
+                let result = next_code_point(&mut it);
+                let consumed = before.len() - it.as_slice().len();
+                assert_eq!(consumed, documented_consumption(before), "{:x?}", before);
+                match (result.unwrap(), decode_step(before)) {
+                    (Ok(c), Ok((code_point, _))) => assert_eq!(u32::from(c), code_point),
+                    (Err(_), Err(_)) => {}
+                    other => panic!("{:x?}: {:?}", before, other),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_prev_from_both_ends() {
+        let inputs = [
+            "!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~",
+            "¡¢£¤¥¦§¨©ª«¬\u{AD}®¯°±²³´µ¶·¸¹º»¼½¾¿ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔ",
+            "ขฃคฅฆงจฉชซฌญฎฏฐฑฒณดตถทธนบปผฝพฟภมยรฤลฦวศษสหฬอฮฯะัาำิีึืฺุู",
+            "😀𒀀𒀁𒀂𒀃𒀄𒀅𒀆𒀇𒀈𒀉𒀊𒀋𒀌𒀍𒀎𒀏",
+        ];
+        for input in inputs.iter() {
+            let expected: Vec<u32> = input.chars().map(u32::from).collect();
+            let mut it = input.as_bytes().iter();
+            let mut reversed: Vec<u32> = Vec::new();
+            while let Ok(code_point) = validate_prev(&mut it) {
+                reversed.push(code_point);
+            }
+            assert!(it.as_slice().is_empty());
+            reversed.reverse();
+            assert_eq!(reversed, expected);
+
+            // Taking turns at both ends of one iterator.
+            let mut it = input.as_bytes().iter();
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+            while !it.as_slice().is_empty() {
+                front.push(validate_next(&mut it).unwrap());
+                if !it.as_slice().is_empty() {
+                    back.push(validate_prev(&mut it).unwrap());
+                }
+            }
+            front.extend(back.iter().rev());
+            assert_eq!(front, expected);
+        }
+    }
+
+    #[test]
+    fn test_validate_prev_errors() {
+        let cases: [(&[u8], UtfError); 8] = [
+            (b"", UtfError::InvalidLead),
+            (b"\x80", UtfError::IncompleteSequence),
+            (b"\xf0\x80\x80\x80\x80", UtfError::InvalidLead),
+            (b"a\x80", UtfError::InvalidLead),
+            (b"\xe2\x82", UtfError::IncompleteSequence),
+            (b"\xc0\xaf", UtfError::OverlongSequence),
+            (b"\xed\xa0\x80", UtfError::InvalidCodePoint),
+            (b"\xf4\x90\x80\x80", UtfError::InvalidCodePoint),
+        ];
+        for (bytes, kind) in cases.iter() {
+            let mut it = bytes.iter().copied();
+            assert_eq!(validate_prev(&mut it), Err(*kind), "{:x?}", bytes);
+        }
+        // A stray trail byte after a complete character.
+        let mut it = "é".bytes().chain(Some(0xa9));
+        assert_eq!(validate_prev(&mut it), Err(UtfError::InvalidLead));
+        let mut it = [0x61, 0xc3, 0xa9].iter();
+        assert_eq!(validate_prev(&mut it), Ok(0xe9));
+        assert_eq!(it.as_slice(), b"a");
+    }
+
+    #[test]
+    fn test_validate_next_with_profiles() {
+        use UtfError::*;
+        use ValidationProfile::*;
+
+        let profiles = [Strict, Wtf8, Cesu8, ModifiedUtf8];
+        #[rustfmt::skip]
+        let cases: [(&[u8], [_; 4]); 10] = [
+            (b"a", [Ok(0x61), Ok(0x61), Ok(0x61), Ok(0x61)]),
+            // U+0000 as in JNI.
+            (b"\xc0\x80", [Err(OverlongSequence), Err(OverlongSequence), Err(OverlongSequence), Ok(0)]),
+            (b"\xc0\x81", [Err(OverlongSequence); 4]),
+            // U+10000 as standard UTF-8 and as in JNI.
+            (b"\xf0\x90\x80\x80", [Ok(0x10000), Ok(0x10000), Err(InvalidLead), Err(InvalidLead)]),
+            (b"\xed\xa0\x80\xed\xb0\x80", [Err(InvalidCodePoint), Ok(0xd800), Ok(0x10000), Ok(0x10000)]),
+            (b"\xed\xaf\xbf\xed\xbf\xbf", [Err(InvalidCodePoint), Ok(0xdbff), Ok(0x10ffff), Ok(0x10ffff)]),
+            // Unpaired surrogates.
+            (b"\xed\xb0\x80", [Err(InvalidCodePoint), Ok(0xdc00), Err(InvalidCodePoint), Err(InvalidCodePoint)]),
+            (b"\xed\xa0\x80a", [Err(InvalidCodePoint), Ok(0xd800), Err(InvalidCodePoint), Err(InvalidCodePoint)]),
+            (b"\xed\xa0\x80\xed\xa0\x80", [Err(InvalidCodePoint), Ok(0xd800), Err(InvalidCodePoint), Err(InvalidCodePoint)]),
+            (b"\xed\xa0\x80", [Err(InvalidCodePoint), Ok(0xd800), Err(NotEnoughRoom), Err(NotEnoughRoom)]),
+        ];
+        for (bytes, expected) in cases.iter() {
+            for (profile, expected) in profiles.iter().zip(expected.iter()) {
+                let result = validate_next_with(&mut bytes.iter(), *profile);
+                assert_eq!(result, *expected, "{:x?} {:?}", bytes, profile);
+            }
+        }
+
+        // A whole JNI string.
+        let jni = b"a\xc0\x80\xed\xa0\xbd\xed\xb8\x80\xe2\x82\xac";
+        let mut it = jni.iter();
+        let mut decoded = String::new();
+        while it.len() != 0 {
+            let code_point = validate_next_with(&mut it, ModifiedUtf8).unwrap();
+            decoded.push(char::from_u32(code_point).unwrap());
+        }
+        assert_eq!(decoded, "a\0😀€");
+    }
+
+    #[test]
+    fn test_validate_next_with_strict_is_validate_next() {
+        let mut rng = XorShift::new(266);
+        for _ in 0..500 {
+            let fragments = rng.below(20);
+            let bytes = random_corrupt(&mut rng, fragments);
+            let mut expected = bytes.iter();
+            let mut it = bytes.iter();
+            while expected.len() != 0 {
+                assert_eq!(
+                    validate_next_with(&mut it, ValidationProfile::Strict),
+                    validate_next(&mut expected)
+                );
+                assert_eq!(it.as_slice(), expected.as_slice());
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_next_byte_sources() {
+        let text = "aé€😀";
+        // From a C `char` buffer.
+        let signed: Vec<i8> = text.bytes().map(|byte| byte as i8).collect();
+        let mut it = signed.iter().copied();
+        for c in text.chars() {
+            assert_eq!(next_code_point(&mut it), Some(Ok(c)));
+        }
+        assert_eq!(next_code_point(&mut it), None);
+
+        let refs: Vec<&u8> = text.as_bytes().iter().collect();
+        let mut it = refs.iter();
+        for c in text.chars() {
+            assert_eq!(validate_next(&mut it), Ok(c as u32));
+        }
+    }
+
+    #[test]
+    fn test_validate_next_fallible_io() {
+        use std::io::{self, BufReader, Cursor, Read};
+
+        /// Fails once after `ok` bytes.
+        struct Failing<'a> {
+            bytes: &'a [u8],
+            ok: usize,
+        }
+
+        impl Read for Failing<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.ok == 0 {
+                    self.ok = usize::MAX;
+                    return Err(io::Error::other("injected"));
+                }
+                let n = buf.len().min(self.bytes.len()).min(self.ok);
+                buf[..n].copy_from_slice(&self.bytes[..n]);
+                self.bytes = &self.bytes[n..];
+                self.ok -= n;
+                Ok(n)
+            }
+        }
+
+        let text = "aé€😀";
+        let mut it = Cursor::new(text).bytes();
+        let mut decoded = String::new();
+        while let Some(c) = validate_next_fallible(&mut it) {
+            decoded.push(c.unwrap());
+        }
+        assert_eq!(decoded, text);
+
+        // Failing after the second byte of '€'.
+        let mut it = BufReader::new(Failing {
+            bytes: text.as_bytes(),
+            ok: 5,
+        })
+        .bytes();
+        assert!(matches!(validate_next_fallible(&mut it), Some(Ok('a'))));
+        assert!(matches!(validate_next_fallible(&mut it), Some(Ok('é'))));
+        match validate_next_fallible(&mut it) {
+            Some(Err(DecodeError::Source(err))) => assert_eq!(err.kind(), io::ErrorKind::Other),
+            other => panic!("{:?}", other),
+        }
+        // The rest of '€' is left, and no longer valid.
+        assert!(matches!(
+            validate_next_fallible(&mut it),
+            Some(Err(DecodeError::Utf8(UtfError::InvalidLead)))
+        ));
+
+        let mut it = Cursor::new(b"\xe2\x82").bytes();
+        assert!(matches!(
+            validate_next_fallible(&mut it),
+            Some(Err(DecodeError::Utf8(UtfError::NotEnoughRoom)))
+        ));
+        assert!(validate_next_fallible(&mut it).is_none());
+
+        // Any error type works.
+        let mut it = vec![Ok(0xc3u8), Err("gone")].into_iter();
+        assert_eq!(
+            validate_next_fallible(&mut it),
+            Some(Err(DecodeError::Source("gone")))
+        );
+    }
+
+    #[test]
+    fn test_validate_next_supplementary_not_surrogate() {
+        // U+1D800 shares its low 16 bits with a lead surrogate.
+        let input = "\u{1d800}";
+        let mut it = input.as_bytes().iter();
+        assert_eq!(validate_next(&mut it), Ok(0x1d800));
+    }
+
+    fn step_err(bytes: &[u8]) -> (UtfError, Option<u8>, usize) {
+        let err = decode_step(bytes).unwrap_err();
+        assert_eq!(err.offset(), 0);
+        (err.kind(), err.error_len(), err.skip_len())
+    }
+
+    #[test]
+    fn test_decode_step() {
+        assert_eq!(decode_step(b"a"), Ok((0x61, 1)));
+        assert_eq!(decode_step("\u{20ac}!".as_bytes()), Ok((0x20ac, 3)));
+        assert_eq!(decode_step("\u{10ffff}".as_bytes()), Ok((0x10ffff, 4)));
+        assert_eq!(step_err(&[]), (UtfError::NotEnoughRoom, None, 0));
+        assert_eq!(step_err(&[0xf0, 0x9f]), (UtfError::NotEnoughRoom, None, 2));
+        assert_eq!(step_err(&[0xff]), (UtfError::InvalidLead, Some(1), 1));
+        assert_eq!(step_err(&[0x80]), (UtfError::InvalidLead, Some(1), 1));
+        assert_eq!(
+            step_err(&[0xc0, 0x80]),
+            (UtfError::OverlongSequence, Some(1), 1)
+        );
+        assert_eq!(step_err(&[0xc1]), (UtfError::OverlongSequence, Some(1), 1));
+        assert_eq!(
+            step_err(&[0xe0, 0x80]),
+            (UtfError::OverlongSequence, Some(1), 1)
+        );
+        assert_eq!(
+            step_err(&[0xed, 0xa0, 0x80]),
+            (UtfError::InvalidCodePoint, Some(1), 1)
+        );
+        assert_eq!(
+            step_err(&[0xf4, 0x90, 0x80, 0x80]),
+            (UtfError::InvalidCodePoint, Some(1), 1)
+        );
+        assert_eq!(step_err(&[0xf5]), (UtfError::InvalidCodePoint, Some(1), 1));
+        assert_eq!(
+            step_err(&[0xe2, 0x41]),
+            (UtfError::IncompleteSequence, Some(1), 1)
+        );
+        assert_eq!(
+            step_err(&[0xf0, 0x9f, 0x98, 0x41]),
+            (UtfError::IncompleteSequence, Some(3), 3)
+        );
+    }
+
+    #[test]
+    fn test_validate_next_counted_position() {
+        let cases: [(&[u8], UtfError, usize); 8] = [
+            (b"\xffab", UtfError::InvalidLead, 1),
+            (b"\x80\x80ab", UtfError::InvalidLead, 1),
+            (b"\xc0\xafab", UtfError::OverlongSequence, 1),
+            (b"\xe0\x80\x80ab", UtfError::OverlongSequence, 1),
+            (b"\xed\xa0\x80ab", UtfError::InvalidCodePoint, 1),
+            (b"\xe2\x82ab", UtfError::IncompleteSequence, 2),
+            (b"\xf0\x9f\x98ab", UtfError::IncompleteSequence, 3),
+            (b"\xf0\x9f\x98", UtfError::NotEnoughRoom, 3),
+        ];
+        for (bytes, kind, consumed) in cases.iter() {
+            let mut it = bytes.iter();
+            assert_eq!(
+                validate_next_counted(&mut it),
+                (Err(*kind), *consumed),
+                "{:x?}",
+                bytes
+            );
+            assert_eq!(it.as_slice(), &bytes[*consumed..]);
+        }
+        let mut it = [0u8; 0].iter();
+        assert_eq!(
+            validate_next_counted(&mut it),
+            (Err(UtfError::NotEnoughRoom), 0)
+        );
+        let mut it = "€a".as_bytes().iter();
+        assert_eq!(validate_next_counted(&mut it), (Ok(0x20ac), 3));
+        assert_eq!(it.as_slice(), b"a");
+    }
+
+    #[test]
+    fn test_validate_next_counted_kinds_differ_from_validate_next() {
+        let bytes = b"\xe0\x80\x41";
+        let mut it = bytes.iter();
+        assert_eq!(
+            validate_next_counted(&mut it),
+            (Err(UtfError::OverlongSequence), 1)
+        );
+        let mut it = bytes.iter();
+        assert_eq!(validate_next(&mut it), Err(UtfError::IncompleteSequence));
+        assert!(it.as_slice().is_empty());
+    }
+
+    #[test]
+    fn test_validate_next_counted_random() {
+        let mut rng = XorShift::new(253);
+        for _ in 0..2000 {
+            let fragments = rng.below(20);
+            let input = random_corrupt(&mut rng, fragments);
+            let mut it = input.iter();
+            while it.len() > 0 {
+                let rest = it.as_slice();
+                let (result, consumed) = validate_next_counted(&mut it);
+                let expected = match decode_step(rest) {
+                    Ok((code_point, length)) => (Ok(code_point), length),
+                    Err(err) => (Err(err.kind()), err.skip_len()),
+                };
+                assert_eq!((result, consumed), expected, "{:x?}", rest);
+                assert_eq!(it.as_slice(), &rest[consumed..]);
+                assert_eq!(result.ok(), validate_next(&mut rest.iter()).ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_next_counted_resyncs() {
+        let bytes = b"a\xff\xe2\x82\xe2\x82\xac\xc0b\xf0\x9f";
+        let mut it = bytes.iter();
+        let mut decoded = Vec::new();
+        while it.len() > 0 {
+            decoded.push(validate_next_counted(&mut it).0);
+        }
+        assert_eq!(
+            decoded,
+            [
+                Ok(0x61),
+                Err(UtfError::InvalidLead),
+                Err(UtfError::IncompleteSequence),
+                Ok(0x20ac),
+                Err(UtfError::IncompleteSequence),
+                Ok(0x62),
+                Err(UtfError::NotEnoughRoom),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_step_agrees_with_validate_next() {
+        for cp in 0..=0x10ffffu32 {
+            let mut buf = [0; 4];
+            let bytes = match char::from_u32(cp) {
+                Some(c) => c.encode_utf8(&mut buf).as_bytes(),
+                None => continue,
+            };
+            let mut it = bytes.iter();
+            assert_eq!(decode_step(bytes), Ok((cp, bytes.len())));
+            assert_eq!(validate_next(&mut it), Ok(cp));
+        }
+    }
+
+    fn check_word(word: u32) {
+        let expected = decode_step(&word.to_le_bytes())
+            .map(|(code_point, length)| (code_point, length as u8))
+            .map_err(|err| err.kind());
+        assert_eq!(decode_word(word), expected, "{:08x}", word);
+    }
+
+    #[test]
+    fn test_decode_word_all_2_byte_words() {
+        for word in 0..=0xffffu32 {
+            check_word(word);
+        }
+    }
+
+    #[test]
+    fn test_decode_word_random_trail_bytes() {
+        let mut rng = XorShift::new(220);
+        for lead in 0..=0xffu32 {
+            for _ in 0..2000 {
+                // Mostly trail bytes, so that long sequences get decoded.
+                let mut word = lead;
+                for shift in [8, 16, 24].iter() {
+                    let byte = if rng.below(8) == 0 {
+                        rng.below(256)
+                    } else {
+                        0x80 | rng.below(64)
+                    };
+                    word |= (byte as u32) << shift;
+                }
+                check_word(word);
+            }
+        }
+    }
 }
@@ -0,0 +1,326 @@
+use core::iter::FusedIterator;
+
+use crate::core::{decode_step, sequence_length, UtfError};
+
+/// Items of a byte iterator accepted by [`decode_utf8`].
+pub trait AsByte {
+    fn as_byte(&self) -> u8;
+}
+
+impl AsByte for u8 {
+    #[inline]
+    fn as_byte(&self) -> u8 {
+        *self
+    }
+}
+
+impl AsByte for &u8 {
+    #[inline]
+    fn as_byte(&self) -> u8 {
+        **self
+    }
+}
+
+impl AsByte for &&u8 {
+    #[inline]
+    fn as_byte(&self) -> u8 {
+        ***self
+    }
+}
+
+/// The same bit pattern, as in C `char` buffers.
+impl AsByte for i8 {
+    #[inline]
+    fn as_byte(&self) -> u8 {
+        *self as u8
+    }
+}
+
+/// Items of a fallible byte source accepted by
+/// [`validate_next_fallible`](crate::validate_next_fallible), such as
+/// [`std::io::Bytes`].
+pub trait TryAsByte {
+    type Error;
+
+    fn try_as_byte(self) -> Result<u8, Self::Error>;
+}
+
+impl<T: AsByte, E> TryAsByte for Result<T, E> {
+    type Error = E;
+
+    #[inline]
+    fn try_as_byte(self) -> Result<u8, E> {
+        self.map(|byte| byte.as_byte())
+    }
+}
+
+/// Iterator over the characters decoded from a byte iterator, created by
+/// [`decode_utf8`].
+///
+/// After the first error, including input ending in the middle of a
+/// character, the iterator is exhausted.
+#[derive(Clone, Debug)]
+pub struct DecodeUtf8<I> {
+    inner: I,
+    buf: [u8; 4],
+    /// Bytes of `buf` read from `inner` but not decoded yet.
+    len: usize,
+    offset: usize,
+    done: bool,
+}
+
+/// Decodes the bytes of `iter`, reading at most one byte past the character
+/// or error being decoded.
+pub fn decode_utf8<I>(iter: I) -> DecodeUtf8<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: AsByte,
+{
+    DecodeUtf8 {
+        inner: iter.into_iter(),
+        buf: [0; 4],
+        len: 0,
+        offset: 0,
+        done: false,
+    }
+}
+
+impl<I> DecodeUtf8<I> {
+    /// Number of bytes of the characters decoded so far; after an error, the
+    /// offset of the offending sequence.
+    #[inline]
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I> DecodeUtf8<I>
+where
+    I: Iterator,
+    I::Item: AsByte,
+{
+    /// Skips a byte order mark at the current position, if there is one.
+    ///
+    /// The bytes read to find out are kept and decoded as usual if they are
+    /// not the BOM.
+    pub fn skip_bom(&mut self) -> bool {
+        if self.done {
+            return false;
+        }
+        while self.len < 3 {
+            match self.inner.next() {
+                Some(byte) => {
+                    self.buf[self.len] = byte.as_byte();
+                    self.len += 1;
+                }
+                None => break,
+            }
+        }
+        if self.buf[..self.len] != [0xef, 0xbb, 0xbf][..] {
+            return false;
+        }
+        self.len = 0;
+        self.offset = self.offset.saturating_add(3);
+        true
+    }
+}
+
+impl<I> Iterator for DecodeUtf8<I>
+where
+    I: Iterator,
+    I::Item: AsByte,
+{
+    type Item = Result<char, UtfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.len == 0 {
+            match self.inner.next() {
+                Some(byte) => self.buf[0] = byte.as_byte(),
+                None => {
+                    self.done = true;
+                    return None;
+                }
+            }
+            self.len = 1;
+        }
+        // Read until the result is decided. The kind of error of a lead
+        // that can never start a character depends on the byte after it.
+        let needed = sequence_length(self.buf[0]);
+        let result = loop {
+            let result = decode_step(&self.buf[..self.len]);
+            let undecided = match result {
+                Ok(_) => false,
+                Err(err) => err.error_len().is_none() || self.len == 1,
+            };
+            if !undecided || self.len >= needed {
+                break result;
+            }
+            match self.inner.next() {
+                Some(byte) => {
+                    self.buf[self.len] = byte.as_byte();
+                    self.len += 1;
+                }
+                None => break result,
+            }
+        };
+        match result {
+            Ok((code_point, length)) => {
+                // More than the character was buffered by `skip_bom`.
+                self.buf.copy_within(length..self.len, 0);
+                self.len -= length;
+                self.offset = self.offset.saturating_add(length);
+                char::from_u32(code_point).map(Ok)
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err.kind()))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        let (lower, upper) = self.inner.size_hint();
+        let pending = self.len;
+        (
+            (lower.saturating_add(pending)).min(1),
+            upper.and_then(|upper| upper.checked_add(pending)),
+        )
+    }
+}
+
+impl<I> FusedIterator for DecodeUtf8<I>
+where
+    I: Iterator,
+    I::Item: AsByte,
+{
+}
+
+#[cfg(test)]
+mod test_decode_utf8 {
+    use super::*;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    #[test]
+    fn test_decode_utf8_matches_std() {
+        let inputs = [
+            "",
+            "!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            "¡¢£¤¥¦§¨©ª«¬\u{AD}®¯°±²³´µ¶·¸¹º»¼½¾¿ÀÁÂÃÄÅÆÇÈÉÊË",
+            "ขฃคฅฆงจฉชซฌญฎฏฐฑฒณดตถทธนบปผฝพฟภมยรฤลฦวศษสหฬอฮฯะ",
+            "😀𒀀𒀁𒀂𒀃𒀄𒀅𒀆𒀇𒀈𒀉𒀊𒀋𒀌𒀍𒀎𒀏",
+            "mixed é€😀ข\u{10ffff}",
+        ];
+        for input in inputs.iter() {
+            let expected: Vec<_> = input.chars().map(Ok).collect();
+            let mut it = decode_utf8(input.as_bytes());
+            let decoded: Vec<_> = it.by_ref().collect();
+            assert_eq!(decoded, expected);
+            assert_eq!(it.byte_offset(), input.len());
+            // Owned bytes work as well.
+            let owned: Vec<_> = decode_utf8(input.bytes()).collect();
+            assert_eq!(owned, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_utf8_truncated() {
+        let mut bytes = "a€".as_bytes().to_vec();
+        bytes.extend_from_slice(&[0xf0, 0x9f, 0x98]);
+        let mut it = decode_utf8(&bytes);
+        assert_eq!(it.next(), Some(Ok('a')));
+        assert_eq!(it.next(), Some(Ok('€')));
+        assert_eq!(it.next(), Some(Err(UtfError::NotEnoughRoom)));
+        assert_eq!(it.byte_offset(), 4);
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_decode_utf8_fused_after_error() {
+        let bytes = b"ab\xe0\x80cd";
+        let mut it = decode_utf8(bytes.iter());
+        assert_eq!(it.next(), Some(Ok('a')));
+        assert_eq!(it.next(), Some(Ok('b')));
+        assert_eq!(it.next(), Some(Err(UtfError::OverlongSequence)));
+        assert_eq!(it.byte_offset(), 2);
+        for _ in 0..10 {
+            assert_eq!(it.next(), None);
+        }
+        // The error was decided by its second byte.
+        assert_eq!(it.into_inner().as_slice(), b"cd");
+    }
+
+    #[test]
+    fn test_decode_utf8_skip_bom() {
+        let mut it = decode_utf8(b"\xef\xbb\xbf".iter());
+        assert!(it.skip_bom());
+        assert_eq!(it.next(), None);
+        assert_eq!(it.byte_offset(), 3);
+
+        let mut it = decode_utf8("\u{feff}\u{feff}é".bytes());
+        assert!(it.skip_bom());
+        assert_eq!(it.collect::<Vec<_>>(), [Ok('\u{feff}'), Ok('é')]);
+
+        // What was read to look for the BOM is still decoded.
+        for text in ["", "a", "ab", "abc", "é", "€", "😀"].iter() {
+            let mut it = decode_utf8(text.bytes());
+            assert!(!it.skip_bom());
+            let decoded: Vec<_> = it.by_ref().collect();
+            assert_eq!(decoded, text.chars().map(Ok).collect::<Vec<_>>());
+            assert_eq!(it.byte_offset(), text.len());
+        }
+        let mut it = decode_utf8(b"\xef\xbba".iter());
+        assert!(!it.skip_bom());
+        assert_eq!(it.next(), Some(Err(UtfError::IncompleteSequence)));
+        assert_eq!(it.byte_offset(), 0);
+    }
+
+    #[test]
+    fn test_decode_utf8_errors_match_decode_step() {
+        let inputs: [&[u8]; 6] = [
+            b"\xff",
+            b"\x80",
+            b"\xc0\xaf",
+            b"\xed\xa0\x80",
+            b"\xe2\x82a",
+            b"\xf4\x90\x80\x80",
+        ];
+        for bytes in inputs.iter() {
+            let expected = decode_step(bytes).unwrap_err().kind();
+            let decoded: Vec<_> = decode_utf8(bytes.iter()).collect();
+            assert_eq!(decoded, [Err(expected)], "{:x?}", bytes);
+        }
+        let mut rng = XorShift::new(254);
+        for _ in 0..500 {
+            let fragments = rng.below(20);
+            let bytes = random_corrupt(&mut rng, fragments);
+            let mut expected = Vec::new();
+            let mut pos = 0;
+            while pos < bytes.len() {
+                match decode_step(&bytes[pos..]) {
+                    Ok((code_point, length)) => {
+                        expected.push(Ok(char::from_u32(code_point).unwrap()));
+                        pos += length;
+                    }
+                    Err(err) => {
+                        expected.push(Err(err.kind()));
+                        break;
+                    }
+                }
+            }
+            let mut it = decode_utf8(bytes.iter());
+            assert_eq!(it.by_ref().collect::<Vec<_>>(), expected, "{:x?}", bytes);
+            assert_eq!(it.byte_offset(), pos);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use crate::core::decode_step;
+use crate::error::Utf8ErrorAt;
+use crate::stream::{tail_error, validate_chunk};
+
+/// What [`Utf8Decoder::decode`] made of one chunk.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DecodeStep<'a> {
+    /// A character split across chunks, completed by the start of this one.
+    /// It comes before `text`.
+    pub stitched: Option<char>,
+    /// Valid text borrowed from the chunk.
+    pub text: &'a str,
+    /// The error that stopped decoding, with its offset from the start of
+    /// the stream.
+    pub error: Option<Utf8ErrorAt>,
+}
+
+impl<'a> DecodeStep<'a> {
+    #[inline]
+    fn text(text: &'a str) -> Self {
+        DecodeStep {
+            stitched: None,
+            text,
+            error: None,
+        }
+    }
+
+    #[inline]
+    fn error(err: Utf8ErrorAt) -> Self {
+        DecodeStep {
+            error: Some(err),
+            ..DecodeStep::text("")
+        }
+    }
+}
+
+/// Incremental decoder that hands out the text of each chunk as it arrives.
+///
+/// Text is borrowed from the chunk; only the up to 3 bytes of a character
+/// split across chunks are copied.
+#[derive(Clone, Debug, Default)]
+pub struct Utf8Decoder {
+    pending: [u8; 4],
+    pending_len: usize,
+    consumed: u64,
+    error: Option<Utf8ErrorAt>,
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A decoder that has already seen `consumed` bytes of valid text.
+    #[cfg(all(test, feature = "alloc"))]
+    pub(crate) fn with_consumed(consumed: u64) -> Self {
+        Utf8Decoder {
+            consumed,
+            ..Self::default()
+        }
+    }
+
+    /// Decodes the next chunk of the stream.
+    ///
+    /// Once an error is returned, every later call returns it again with no
+    /// text.
+    pub fn decode<'a>(&mut self, chunk: &'a [u8]) -> DecodeStep<'a> {
+        if let Some(err) = self.error {
+            return DecodeStep::error(err);
+        }
+        let step = self.decode_inner(chunk);
+        self.error = step.error;
+        step
+    }
+
+    fn decode_inner<'a>(&mut self, chunk: &'a [u8]) -> DecodeStep<'a> {
+        if chunk.is_empty() {
+            return DecodeStep::text("");
+        }
+        let base = self.consumed;
+        self.consumed = self.consumed.saturating_add(chunk.len() as u64);
+        let whole = chunk.len();
+        let mut chunk = chunk;
+        let mut stitched = None;
+        if self.pending_len != 0 {
+            let start = base - self.pending_len as u64;
+            let take = chunk.len().min(4 - self.pending_len);
+            let mut joined = self.pending;
+            joined[self.pending_len..self.pending_len + take].copy_from_slice(&chunk[..take]);
+            match decode_step(&joined[..self.pending_len + take]) {
+                Ok((code_point, length)) => {
+                    chunk = &chunk[length - self.pending_len..];
+                    self.pending_len = 0;
+                    stitched = char::from_u32(code_point);
+                }
+                Err(err) if err.error_len().is_none() => {
+                    self.pending = joined;
+                    self.pending_len += take;
+                    return DecodeStep::text("");
+                }
+                Err(err) => return DecodeStep::error(err.at_stream(start)),
+            }
+        }
+        let chunk_start = base.saturating_add((whole - chunk.len()) as u64);
+        let (valid_len, error) = match validate_chunk(chunk) {
+            Ok(tail) => {
+                self.pending[..tail].copy_from_slice(&chunk[chunk.len() - tail..]);
+                self.pending_len = tail;
+                (chunk.len() - tail, None)
+            }
+            Err(err) => (err.offset(), Some(err.at_stream(chunk_start))),
+        };
+        let text = &chunk[..valid_len];
+        debug_assert!(core::str::from_utf8(text).is_ok());
+        DecodeStep {
+            stitched,
+            // SAFETY: `validate_chunk` accepted every byte before `valid_len`.
+            text: unsafe { core::str::from_utf8_unchecked(text) },
+            error,
+        }
+    }
+
+    /// Checks that the stream did not end in the middle of a character.
+    pub fn finish(self) -> Result<(), Utf8ErrorAt> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        if self.pending_len != 0 {
+            let start = self.consumed - self.pending_len as u64;
+            return Err(tail_error(&self.pending[..self.pending_len]).at_stream(start));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_decoder {
+    use super::*;
+    use crate::core::UtfError;
+
+    fn decode_all(chunks: &[&[u8]]) -> (String, Result<(), Utf8ErrorAt>) {
+        let mut decoder = Utf8Decoder::new();
+        let mut out = String::new();
+        for chunk in chunks {
+            let step = decoder.decode(chunk);
+            out.extend(step.stitched);
+            out.push_str(step.text);
+            if let Some(err) = step.error {
+                return (out, Err(err));
+            }
+        }
+        (out, decoder.finish())
+    }
+
+    #[test]
+    fn test_decoder_split_characters() {
+        let text = "aé€😀ข";
+        let input = text.as_bytes();
+        for split in 0..=input.len() {
+            let (a, b) = input.split_at(split);
+            assert_eq!(decode_all(&[a, b]), (text.to_string(), Ok(())));
+        }
+        let bytes: Vec<&[u8]> = input.chunks(1).collect();
+        assert_eq!(decode_all(&bytes), (text.to_string(), Ok(())));
+    }
+
+    #[test]
+    fn test_decoder_borrows_from_chunk() {
+        let mut decoder = Utf8Decoder::new();
+        let chunk = "ab😀".as_bytes();
+        let step = decoder.decode(&chunk[..4]);
+        assert_eq!(step.text, "ab");
+        assert_eq!(step.text.as_ptr(), chunk.as_ptr());
+        let step = decoder.decode(&[0x98, 0x80, 0x63]);
+        assert_eq!((step.stitched, step.text), (Some('😀'), "c"));
+        assert_eq!(decoder.finish(), Ok(()));
+    }
+
+    #[test]
+    fn test_decoder_error_at_seam() {
+        // The carried bytes are rejected by the next chunk.
+        let (text, result) = decode_all(&[b"ab\xf0\x9f", b"Acd"]);
+        assert_eq!(text, "ab");
+        let err = result.unwrap_err();
+        assert_eq!(
+            (err.offset(), err.kind()),
+            (2, UtfError::IncompleteSequence)
+        );
+
+        // A stitched character followed by an error in the same chunk.
+        let (text, result) = decode_all(&[b"a\xc3", b"\xa9b\xff"]);
+        assert_eq!(text, "aéb");
+        assert_eq!(result.unwrap_err().offset(), 4);
+
+        let (text, result) = decode_all(&[b"ab\xf0", b"\x9f\x98"]);
+        assert_eq!(text, "ab");
+        let err = result.unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (2, UtfError::NotEnoughRoom));
+    }
+
+    #[test]
+    fn test_decoder_sticky_error() {
+        let mut decoder = Utf8Decoder::new();
+        assert_eq!(decoder.decode(b"ok\xc0").text, "ok");
+        let step = decoder.decode(b"\x80fine");
+        assert_eq!((step.text, step.error.map(|e| e.offset())), ("", Some(2)));
+        let step = decoder.decode(b"more");
+        assert_eq!((step.text, step.error.map(|e| e.offset())), ("", Some(2)));
+        assert_eq!(decoder.finish().unwrap_err().offset(), 2);
+    }
+}
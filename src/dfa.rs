@@ -0,0 +1,138 @@
+//! Validation with a table-driven DFA, after Björn Höhrmann's "Flexible and
+//! Economical UTF-8 Decoder".
+
+use crate::error::Utf8ErrorAt;
+use crate::validate::validate_exact;
+
+const ACCEPT: u8 = 0;
+const REJECT: u8 = 12;
+
+/// Class of every byte value: what the transitions distinguish.
+#[rustfmt::skip]
+const CLASSES: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // 00..0F
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // 10..1F
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // 20..2F
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // 30..3F
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // 40..4F
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // 50..5F
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // 60..6F
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // 70..7F
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, // 80..8F
+    9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, // 90..9F
+    7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, // A0..AF
+    7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, // B0..BF
+    8, 8, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, // C0..CF
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, // D0..DF
+    10, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 4, 3, 3, // E0..EF
+    11, 6, 6, 6, 5, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, 8, // F0..FF
+];
+
+/// Next state, indexed by state plus class. States are multiples of 12:
+/// accept, reject, then the states expecting more continuation bytes.
+#[rustfmt::skip]
+const TRANSITIONS: [u8; 108] = [
+    // 0: accept
+    0, 12, 24, 36, 60, 96, 84, 12, 12, 12, 48, 72,
+    // 12: reject
+    12, 12, 12, 12, 12, 12, 12, 12, 12, 12, 12, 12,
+    // 24: one more continuation byte
+    12, 0, 12, 12, 12, 12, 12, 0, 12, 0, 12, 12,
+    // 36: two more
+    12, 24, 12, 12, 12, 12, 12, 24, 12, 24, 12, 12,
+    // 48: after E0, A0..BF
+    12, 12, 12, 12, 12, 12, 12, 24, 12, 12, 12, 12,
+    // 60: after ED, 80..9F
+    12, 24, 12, 12, 12, 12, 12, 12, 12, 24, 12, 12,
+    // 72: after F0, 90..BF
+    12, 12, 12, 12, 12, 12, 12, 36, 12, 36, 12, 12,
+    // 84: after F1..F3, three more
+    12, 36, 12, 12, 12, 12, 12, 36, 12, 36, 12, 12,
+    // 96: after F4, 80..8F
+    12, 36, 12, 12, 12, 12, 12, 12, 12, 12, 12, 12,
+];
+
+/// Checks that all of `bytes` is valid UTF-8 with one table lookup pair per
+/// byte, and without decoding code points.
+///
+/// Errors are the same as those of [`validate`](crate::validate): the DFA
+/// only finds the character that fails, which is then decoded exactly.
+pub fn validate_dfa(bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+    let mut state = ACCEPT;
+    let mut char_start = 0;
+    for (pos, &byte) in bytes.iter().enumerate() {
+        if state == ACCEPT {
+            char_start = pos;
+        }
+        state = TRANSITIONS[(state + CLASSES[byte as usize]) as usize];
+        if state == REJECT {
+            break;
+        }
+    }
+    if state == ACCEPT {
+        return Ok(());
+    }
+    validate_exact(&bytes[char_start..]).map_err(|err| err.at(char_start))
+}
+
+#[cfg(test)]
+mod test_dfa {
+    use super::*;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    fn check(bytes: &[u8]) {
+        assert_eq!(validate_dfa(bytes), validate_exact(bytes), "{:x?}", bytes);
+    }
+
+    #[test]
+    fn test_dfa_exhaustive_up_to_3_bytes() {
+        check(b"");
+        for a in 0..=0xffu8 {
+            check(&[a]);
+            for b in 0..=0xffu8 {
+                check(&[a, b]);
+            }
+        }
+        for a in 0xe0..=0xffu8 {
+            for b in 0..=0xffu8 {
+                for c in 0x70..=0xc0u8 {
+                    check(&[a, b, c]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dfa_every_code_point() {
+        let mut buf = [0; 4];
+        for c in (0..=0x10ffffu32).filter_map(char::from_u32) {
+            let bytes = c.encode_utf8(&mut buf).as_bytes();
+            assert_eq!(validate_dfa(bytes), Ok(()));
+            check(&bytes[..bytes.len() - 1]);
+        }
+        // Surrogates, overlong forms and code points above U+10FFFF.
+        for bytes in [
+            &[0xed, 0xa0, 0x80][..],
+            &[0xed, 0xbf, 0xbf],
+            &[0xc0, 0x80],
+            &[0xe0, 0x9f, 0xbf],
+            &[0xf0, 0x8f, 0xbf, 0xbf],
+            &[0xf4, 0x90, 0x80, 0x80],
+            &[0xf5, 0x80, 0x80, 0x80],
+        ]
+        .iter()
+        {
+            assert!(validate_dfa(bytes).is_err());
+            check(bytes);
+        }
+    }
+
+    #[test]
+    fn test_dfa_random() {
+        let mut rng = XorShift::new(260);
+        for _ in 0..2000 {
+            let fragments = rng.below(60);
+            check(&random_corrupt(&mut rng, fragments));
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use core::iter::FusedIterator;
+
+use crate::core::UtfError;
+
+/// Writes the shortest encoding of `code_point` to the start of `buf`,
+/// returning its length.
+///
+/// Surrogates and values above U+10FFFF are an
+/// [`InvalidCodePoint`](UtfError::InvalidCodePoint), a `buf` too short for
+/// the encoding is a [`NotEnoughRoom`](UtfError::NotEnoughRoom); in both
+/// cases `buf` is left as it is.
+pub fn encode_utf8(code_point: u32, buf: &mut [u8]) -> Result<usize, UtfError> {
+    let c = char::from_u32(code_point).ok_or(UtfError::InvalidCodePoint)?;
+    let len = c.len_utf8();
+    let buf = buf.get_mut(..len).ok_or(UtfError::NotEnoughRoom)?;
+    c.encode_utf8(buf);
+    Ok(len)
+}
+
+/// Iterator over the bytes of an encoded code point, created by
+/// [`encode_utf8_iter`].
+#[derive(Clone, Debug)]
+pub struct EncodeUtf8 {
+    buf: [u8; 4],
+    pos: usize,
+    len: usize,
+}
+
+/// Encodes `code_point` like [`encode_utf8`], returning the bytes as an
+/// iterator.
+pub fn encode_utf8_iter(code_point: u32) -> Result<EncodeUtf8, UtfError> {
+    let mut buf = [0; 4];
+    let len = encode_utf8(code_point, &mut buf)?;
+    Ok(EncodeUtf8 { buf, pos: 0, len })
+}
+
+impl EncodeUtf8 {
+    /// The bytes not yielded yet.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[self.pos..self.len]
+    }
+}
+
+impl Iterator for EncodeUtf8 {
+    type Item = u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<u8> {
+        let byte = *self.as_bytes().first()?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len - self.pos;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for EncodeUtf8 {}
+
+impl FusedIterator for EncodeUtf8 {}
+
+#[cfg(test)]
+mod test_encode {
+    use super::*;
+    use crate::core::validate_next;
+
+    /// The UTF-8 bit layout of any value up to 21 bits, valid or not.
+    fn encode_shape(code_point: u32) -> Vec<u8> {
+        let trail = |shift: u32| 0x80 | (code_point >> shift & 0x3f) as u8;
+        match code_point {
+            0..=0x7f => vec![code_point as u8],
+            0x80..=0x7ff => vec![0xc0 | (code_point >> 6) as u8, trail(0)],
+            0x800..=0xffff => vec![0xe0 | (code_point >> 12) as u8, trail(6), trail(0)],
+            _ => vec![
+                0xf0 | (code_point >> 18) as u8,
+                trail(12),
+                trail(6),
+                trail(0),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let mut buf = [0; 4];
+        for code_point in 0..=0x1f_ffff {
+            let shape = encode_shape(code_point);
+            match char::from_u32(code_point) {
+                Some(c) => {
+                    assert_eq!(encode_utf8(code_point, &mut buf), Ok(c.len_utf8()));
+                    assert_eq!(&buf[..c.len_utf8()], &shape[..]);
+                    assert_eq!(
+                        validate_next(&mut buf[..c.len_utf8()].iter()),
+                        Ok(code_point)
+                    );
+                    let bytes: Vec<u8> = encode_utf8_iter(code_point).unwrap().collect();
+                    assert_eq!(bytes, shape);
+                }
+                None => {
+                    let err = UtfError::InvalidCodePoint;
+                    assert_eq!(encode_utf8(code_point, &mut buf), Err(err));
+                    assert!(encode_utf8_iter(code_point).is_err());
+                    let decoded = validate_next(&mut shape.iter());
+                    assert_eq!(decoded, Err(err), "{:#x}", code_point);
+                }
+            }
+        }
+        for code_point in [0x20_0000, 0xffff_ffff].iter() {
+            assert_eq!(
+                encode_utf8(*code_point, &mut buf),
+                Err(UtfError::InvalidCodePoint)
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode_not_enough_room() {
+        for (code_point, len) in [(0x61, 1), (0xe9, 2), (0x20ac, 3), (0x1f600, 4)].iter() {
+            for short in 0..*len {
+                let mut buf = [0xaa; 4];
+                assert_eq!(
+                    encode_utf8(*code_point, &mut buf[..short]),
+                    Err(UtfError::NotEnoughRoom)
+                );
+                assert_eq!(buf, [0xaa; 4]);
+            }
+            let mut buf = [0xaa; 8];
+            assert_eq!(encode_utf8(*code_point, &mut buf), Ok(*len));
+            assert_eq!(buf[*len..], [0xaa; 8][*len..]);
+        }
+        let mut it = encode_utf8_iter(0x1f600).unwrap();
+        assert_eq!(it.len(), 4);
+        it.next();
+        assert_eq!(it.as_bytes(), [0x9f, 0x98, 0x80]);
+        assert_eq!(it.len(), 3);
+    }
+}
@@ -0,0 +1,379 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::core::{is_continuation, sequence_length, UtfError};
+
+impl fmt::Display for UtfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            UtfError::NotEnoughRoom => "input ended in the middle of a sequence",
+            UtfError::InvalidLead => "invalid lead byte",
+            UtfError::IncompleteSequence => "expected a continuation byte",
+            UtfError::OverlongSequence => "overlong sequence",
+            UtfError::InvalidCodePoint => "invalid code point",
+        };
+        f.write_str(msg)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for UtfError {}
+
+impl UtfError {
+    /// All variants, in code order.
+    pub const ALL: [UtfError; 5] = [
+        UtfError::InvalidLead,
+        UtfError::IncompleteSequence,
+        UtfError::NotEnoughRoom,
+        UtfError::OverlongSequence,
+        UtfError::InvalidCodePoint,
+    ];
+
+    /// Stable numeric code for FFI and logs.
+    ///
+    /// These assignments are frozen:
+    ///
+    /// | code | variant |
+    /// |------|---------|
+    /// | 1 | `InvalidLead` |
+    /// | 2 | `IncompleteSequence` (invalid trail byte) |
+    /// | 3 | `NotEnoughRoom` (unexpected end of input) |
+    /// | 4 | `OverlongSequence` |
+    /// | 5 | `InvalidCodePoint` (surrogate or above U+10FFFF) |
+    /// | 6 | reserved, never assigned |
+    /// | 100.. | policy violations, see `PolicyError::code` |
+    ///
+    /// Surrogates and values above U+10FFFF stay merged under code 5: the
+    /// decoder rejects both at the same point, as a second byte out of range
+    /// for the lead (`ED A0..BF`, `F4 90..BF`) or a lead past `F4`, and
+    /// resynchronizes the same way after them, so `UtfError` has one variant
+    /// for the two. Code 6 is kept free rather than given to another error so
+    /// that it can never be mistaken for "above U+10FFFF".
+    #[inline]
+    pub const fn code(&self) -> u16 {
+        match self {
+            UtfError::InvalidLead => 1,
+            UtfError::IncompleteSequence => 2,
+            UtfError::NotEnoughRoom => 3,
+            UtfError::OverlongSequence => 4,
+            UtfError::InvalidCodePoint => 5,
+        }
+    }
+
+    /// Inverse of [`code`](Self::code).
+    #[inline]
+    pub const fn from_code(code: u16) -> Option<Self> {
+        match code {
+            1 => Some(UtfError::InvalidLead),
+            2 => Some(UtfError::IncompleteSequence),
+            3 => Some(UtfError::NotEnoughRoom),
+            4 => Some(UtfError::OverlongSequence),
+            5 => Some(UtfError::InvalidCodePoint),
+            _ => None,
+        }
+    }
+}
+
+/// A [`UtfError`] together with the byte offset of the sequence that caused it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Utf8ErrorAt {
+    /// Wider than `usize` so that streams past 4 GiB report exact offsets on
+    /// 32-bit targets.
+    offset: u64,
+    kind: UtfError,
+    len: u8,
+}
+
+impl Utf8ErrorAt {
+    /// An ill-formed subsequence of `len` bytes starting at `offset`.
+    #[inline]
+    pub(crate) const fn new(offset: usize, kind: UtfError, len: u8) -> Self {
+        Utf8ErrorAt {
+            offset: offset as u64,
+            kind,
+            len,
+        }
+    }
+
+    /// The input ended `len` bytes into a sequence starting at `offset`.
+    #[inline]
+    pub(crate) const fn truncated(offset: usize, len: u8) -> Self {
+        Utf8ErrorAt {
+            offset: offset as u64,
+            kind: UtfError::NotEnoughRoom,
+            len,
+        }
+    }
+
+    /// Moves the error `base` bytes further into the input.
+    #[inline]
+    pub(crate) const fn at(self, base: usize) -> Self {
+        self.at_stream(base as u64)
+    }
+
+    /// Moves the error `base` bytes further into a stream.
+    #[inline]
+    pub(crate) const fn at_stream(self, base: u64) -> Self {
+        Utf8ErrorAt {
+            offset: base.saturating_add(self.offset),
+            ..self
+        }
+    }
+
+    /// Byte offset of the start of the offending sequence, saturating at
+    /// `usize::MAX`; see [`stream_offset`](Self::stream_offset).
+    #[inline]
+    pub const fn offset(&self) -> usize {
+        if self.offset > usize::MAX as u64 {
+            usize::MAX
+        } else {
+            self.offset as usize
+        }
+    }
+
+    /// Byte offset of the start of the offending sequence. Errors of the
+    /// streaming APIs count from the start of the stream, which may be more
+    /// than `usize::MAX` bytes on 32-bit targets.
+    #[inline]
+    pub const fn stream_offset(&self) -> u64 {
+        self.offset
+    }
+
+    #[inline]
+    pub const fn kind(&self) -> UtfError {
+        self.kind
+    }
+
+    /// Stable numeric code of the error kind, see [`UtfError::code`].
+    #[inline]
+    pub const fn code(&self) -> u16 {
+        self.kind.code()
+    }
+
+    /// Same as [`offset`](Self::offset): the length of the valid prefix, as
+    /// in [`core::str::Utf8Error::valid_up_to`].
+    #[inline]
+    pub const fn valid_up_to(&self) -> usize {
+        self.offset()
+    }
+
+    /// Length of the maximal ill-formed subsequence, or `None` if the input
+    /// ended in the middle of a sequence, as in
+    /// [`core::str::Utf8Error::error_len`].
+    #[inline]
+    pub const fn error_len(&self) -> Option<u8> {
+        match self.kind {
+            UtfError::NotEnoughRoom => None,
+            _ => Some(self.len),
+        }
+    }
+
+    /// Number of bytes to skip from [`offset`](Self::offset) before resuming,
+    /// following the maximal-subpart rule.
+    ///
+    /// This is 1 to 3: the lead byte plus any continuation bytes that were
+    /// still valid for it, so no valid character start is ever skipped. For
+    /// a sequence cut short by the end of the input it covers the rest of
+    /// the input.
+    #[inline]
+    pub const fn skip_len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// The byte at [`offset`](Self::offset) of `input`, the slice the error
+    /// was reported for: the lead byte that failed, or a stray continuation
+    /// byte for [`InvalidLead`](UtfError::InvalidLead).
+    ///
+    /// The bytes are read from `input` rather than stored in the error, so
+    /// that it stays the same however the input was split into chunks.
+    #[inline]
+    pub fn lead_byte(&self, input: &[u8]) -> Option<u8> {
+        input.get(self.offset()).copied()
+    }
+
+    /// The value decoded from the lead byte of `input` and the continuation
+    /// bytes after it, up to the length the lead announces.
+    ///
+    /// That is the rejected value of an
+    /// [`OverlongSequence`](UtfError::OverlongSequence) or
+    /// [`InvalidCodePoint`](UtfError::InvalidCodePoint), e.g. `0xD800` for
+    /// `ED A0 80`, and the code point decoded so far for a sequence cut
+    /// short. `None` for an [`InvalidLead`](UtfError::InvalidLead).
+    pub fn code_point(&self, input: &[u8]) -> Option<u32> {
+        let bytes = input.get(self.offset()..)?;
+        let lead = *bytes.first()?;
+        let length = sequence_length(lead);
+        if self.kind == UtfError::InvalidLead || length < 2 {
+            return None;
+        }
+        let mut code_point = (lead as u32) & (0x7f >> length);
+        for byte in bytes[1..].iter().take(length - 1) {
+            if !is_continuation(*byte) {
+                break;
+            }
+            code_point = (code_point << 6) | (*byte & 0x3f) as u32;
+        }
+        Some(code_point)
+    }
+}
+
+impl fmt::Display for Utf8ErrorAt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for Utf8ErrorAt {}
+
+/// Error of [`validate_next_fallible`](crate::validate_next_fallible): either
+/// the bytes are not valid UTF-8 or the source failed to produce them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError<E> {
+    Utf8(UtfError),
+    Source(E),
+}
+
+impl<E> From<UtfError> for DecodeError<E> {
+    #[inline]
+    fn from(kind: UtfError) -> Self {
+        DecodeError::Utf8(kind)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for DecodeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Utf8(kind) => kind.fmt(f),
+            DecodeError::Source(err) => err.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Error + 'static> Error for DecodeError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Utf8(kind) => Some(kind),
+            DecodeError::Source(err) => Some(err),
+        }
+    }
+}
+
+/// Invalid UTF-8 becomes [`io::ErrorKind::InvalidData`].
+#[cfg(feature = "std")]
+impl From<DecodeError<io::Error>> for io::Error {
+    fn from(err: DecodeError<io::Error>) -> Self {
+        match err {
+            DecodeError::Utf8(kind) => io::Error::new(io::ErrorKind::InvalidData, kind),
+            DecodeError::Source(err) => err,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_error {
+    use super::*;
+
+    #[test]
+    fn test_codes_unique_and_round_trip() {
+        let mut codes: Vec<u16> = UtfError::ALL.iter().map(UtfError::code).collect();
+        for kind in UtfError::ALL.iter() {
+            assert_eq!(UtfError::from_code(kind.code()), Some(*kind));
+        }
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), UtfError::ALL.len());
+        assert_eq!(codes, [1, 2, 3, 4, 5]);
+        assert_eq!(UtfError::from_code(0), None);
+        assert_eq!(UtfError::from_code(6), None);
+        assert_eq!(UtfError::from_code(100), None);
+    }
+
+    #[test]
+    fn test_code_frozen() {
+        assert_eq!(UtfError::InvalidLead.code(), 1);
+        assert_eq!(UtfError::IncompleteSequence.code(), 2);
+        assert_eq!(UtfError::NotEnoughRoom.code(), 3);
+        assert_eq!(UtfError::OverlongSequence.code(), 4);
+        assert_eq!(UtfError::InvalidCodePoint.code(), 5);
+        let err = Utf8ErrorAt::new(3, UtfError::OverlongSequence, 1);
+        assert_eq!((err.error_len(), err.skip_len()), (Some(1), 1));
+        let err = Utf8ErrorAt::truncated(3, 2);
+        assert_eq!(
+            (err.kind(), err.error_len(), err.skip_len()),
+            (UtfError::NotEnoughRoom, None, 2)
+        );
+        let err = Utf8ErrorAt::new(3, UtfError::OverlongSequence, 1);
+        assert_eq!(err.code(), 4);
+    }
+
+    fn payload(bytes: &[u8]) -> (UtfError, Option<u8>, Option<u32>) {
+        let err = crate::validate::validate(bytes).unwrap_err();
+        // The codes do not depend on the payload.
+        assert_eq!(UtfError::from_code(err.code()), Some(err.kind()));
+        (err.kind(), err.lead_byte(bytes), err.code_point(bytes))
+    }
+
+    #[test]
+    fn test_error_payloads() {
+        use UtfError::*;
+        assert_eq!(payload(b"\xff"), (InvalidLead, Some(0xff), None));
+        assert_eq!(payload(b"a\x80"), (InvalidLead, Some(0x80), None));
+        assert_eq!(
+            payload(b"\xc0\xaf"),
+            (OverlongSequence, Some(0xc0), Some(0x2f))
+        );
+        assert_eq!(
+            payload(b"\xe0\x80\xaf"),
+            (OverlongSequence, Some(0xe0), Some(0x2f))
+        );
+        assert_eq!(
+            payload(b"\xed\xa0\x80"),
+            (InvalidCodePoint, Some(0xed), Some(0xd800))
+        );
+        assert_eq!(
+            payload(b"\xf4\x90\x80\x80"),
+            (InvalidCodePoint, Some(0xf4), Some(0x110000))
+        );
+        assert_eq!(
+            payload(b"ab\xe2\x82x"),
+            (IncompleteSequence, Some(0xe2), Some(0x82))
+        );
+        assert_eq!(
+            payload(b"\xf0\x9f\x98"),
+            (NotEnoughRoom, Some(0xf0), Some(0x7d8))
+        );
+        let err = Utf8ErrorAt::new(5, InvalidLead, 1);
+        assert_eq!((err.lead_byte(b"ab"), err.code_point(b"ab")), (None, None));
+    }
+
+    #[test]
+    fn test_matches_std_utf8_error() {
+        let inputs: [&[u8]; 9] = [
+            b"\xff",
+            b"\x80abc",
+            b"\xe0\x80\x80",
+            b"ab\xed\xa0\x80cd",
+            b"ab\xe2\x28\xa1",
+            b"abc\xf4\x90\x80\x80",
+            b"abc\xc3",
+            b"abc\xe2\x82",
+            b"abc\xf0\x9f\x98",
+        ];
+        for bytes in inputs.iter() {
+            let expected = std::str::from_utf8(bytes).unwrap_err();
+            let err = crate::validate::validate(bytes).unwrap_err();
+            assert_eq!(err.valid_up_to(), expected.valid_up_to(), "{:x?}", bytes);
+            assert_eq!(
+                err.error_len(),
+                expected.error_len().map(|len| len as u8),
+                "{:x?}",
+                bytes
+            );
+        }
+    }
+}
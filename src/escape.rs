@@ -0,0 +1,208 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use crate::error::Utf8ErrorAt;
+use crate::utf8_chunks::utf8_chunks;
+use crate::validate::validate;
+
+/// Writes valid text as is and every byte of an invalid sequence as `\xNN`;
+/// backslashes become `\\`, so [`parse_hex_escaped`] gives back `bytes`.
+pub fn escape_invalid(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for chunk in utf8_chunks(bytes) {
+        for piece in chunk.valid().split_inclusive('\\') {
+            out.push_str(piece);
+            if piece.ends_with('\\') {
+                out.push('\\');
+            }
+        }
+        for byte in chunk.invalid() {
+            // Writing to a `String` does not fail.
+            let _ = write!(out, "\\x{:02X}", byte);
+        }
+    }
+    out
+}
+
+/// What is wrong with an escape sequence.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EscapeErrorKind {
+    /// `\x` not followed by two hex digits.
+    InvalidHex,
+    /// A backslash at the end of the text.
+    Trailing,
+    /// A backslash followed by something other than `x` or `\`.
+    UnknownEscape,
+}
+
+/// A malformed escape in text given to [`parse_hex_escaped`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseEscapeError {
+    offset: usize,
+    kind: EscapeErrorKind,
+}
+
+impl ParseEscapeError {
+    /// Byte offset of the backslash starting the escape, in the escaped text.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    pub fn kind(&self) -> EscapeErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for ParseEscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            EscapeErrorKind::InvalidHex => "`\\x` without two hex digits",
+            EscapeErrorKind::Trailing => "trailing backslash",
+            EscapeErrorKind::UnknownEscape => "unknown escape",
+        };
+        write!(f, "{} at byte {}", what, self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ParseEscapeError {}
+
+/// Turns `\xNN` escapes and `\\` back into bytes; everything else is kept
+/// as its UTF-8 encoding.
+pub fn parse_hex_escaped(s: &str) -> Result<Vec<u8>, ParseEscapeError> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let found = match bytes[pos..].iter().position(|&b| b == b'\\') {
+            Some(found) => pos + found,
+            None => {
+                out.extend_from_slice(&bytes[pos..]);
+                break;
+            }
+        };
+        out.extend_from_slice(&bytes[pos..found]);
+        let fail = |kind| ParseEscapeError {
+            offset: found,
+            kind,
+        };
+        match bytes.get(found + 1) {
+            Some(b'\\') => {
+                out.push(b'\\');
+                pos = found + 2;
+            }
+            Some(b'x') => {
+                let digits = bytes.get(found + 2..found + 4);
+                let digits = digits.and_then(|digits| core::str::from_utf8(digits).ok());
+                match digits.and_then(|digits| u8::from_str_radix(digits, 16).ok()) {
+                    // `from_str_radix` accepts a sign.
+                    Some(byte) if bytes[found + 2] != b'+' => out.push(byte),
+                    _ => return Err(fail(EscapeErrorKind::InvalidHex)),
+                }
+                pos = found + 4;
+            }
+            Some(_) => return Err(fail(EscapeErrorKind::UnknownEscape)),
+            None => return Err(fail(EscapeErrorKind::Trailing)),
+        }
+    }
+    Ok(out)
+}
+
+/// Error of [`validate_hex_escaped`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EscapedError {
+    Escape(ParseEscapeError),
+    /// The reconstructed bytes are not UTF-8; the offset is into them.
+    Utf8(Utf8ErrorAt),
+}
+
+impl fmt::Display for EscapedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscapedError::Escape(err) => fmt::Display::fmt(err, f),
+            EscapedError::Utf8(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for EscapedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EscapedError::Escape(err) => Some(err),
+            EscapedError::Utf8(err) => Some(err),
+        }
+    }
+}
+
+/// Parses `s` like [`parse_hex_escaped`] and validates the result.
+pub fn validate_hex_escaped(s: &str) -> Result<(), EscapedError> {
+    let bytes = parse_hex_escaped(s).map_err(EscapedError::Escape)?;
+    validate(&bytes).map_err(EscapedError::Utf8)
+}
+
+#[cfg(test)]
+mod test_escape {
+    use super::*;
+    use crate::core::UtfError;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    #[test]
+    fn test_escape_round_trip() {
+        let mut rng = XorShift::new(233);
+        for _ in 0..1000 {
+            let fragments = rng.below(40);
+            let mut input = random_corrupt(&mut rng, fragments);
+            input.extend_from_slice(b"\\x41\\");
+            let escaped = escape_invalid(&input);
+            assert_eq!(parse_hex_escaped(&escaped).unwrap(), input, "{}", escaped);
+        }
+        assert_eq!(escape_invalid(b"a\\b\xe2\x82"), "a\\\\b\\xE2\\x82");
+    }
+
+    #[test]
+    fn test_parse_hex_escaped() {
+        assert_eq!(
+            parse_hex_escaped("é\\xe2\\x82\\xAC").unwrap(),
+            "é€".as_bytes()
+        );
+        assert_eq!(parse_hex_escaped("a\\\\xff").unwrap(), b"a\\xff");
+        let cases = [
+            ("ab\\xZ1", 2, EscapeErrorKind::InvalidHex),
+            ("ab\\x+1", 2, EscapeErrorKind::InvalidHex),
+            ("ab\\x4", 2, EscapeErrorKind::InvalidHex),
+            ("ab\\x", 2, EscapeErrorKind::InvalidHex),
+            ("\\xé", 0, EscapeErrorKind::InvalidHex),
+            ("ab\\", 2, EscapeErrorKind::Trailing),
+            ("é\\n", 2, EscapeErrorKind::UnknownEscape),
+        ];
+        for (input, offset, kind) in cases.iter() {
+            let err = parse_hex_escaped(input).unwrap_err();
+            assert_eq!((err.offset(), err.kind()), (*offset, *kind), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_validate_hex_escaped() {
+        assert_eq!(validate_hex_escaped("a\\xE2\\x82\\xACb"), Ok(()));
+        match validate_hex_escaped("ab\\xE2\\x82c") {
+            Err(EscapedError::Utf8(err)) => {
+                assert_eq!(
+                    (err.offset(), err.kind()),
+                    (2, UtfError::IncompleteSequence)
+                )
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(
+            validate_hex_escaped("\\x"),
+            Err(EscapedError::Escape(_))
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::io::AsyncReadExt;
+
+use crate::stats::{analyze_block, TextStats};
+
+/// Files at least this large are validated on the blocking thread pool.
+pub const BLOCKING_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// Result of [`validate_file`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileReport {
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// Statistics over the whole file, as [`analyze`](crate::analyze) gives.
+    pub stats: TextStats,
+}
+
+impl FileReport {
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.stats.is_valid()
+    }
+
+    /// Offset of the first invalid sequence, if any.
+    #[inline]
+    pub fn first_error(&self) -> Option<usize> {
+        self.stats.errors.first().map(|err| err.offset())
+    }
+}
+
+/// Error of [`validate_file`]: only IO fails, invalid UTF-8 is reported in
+/// the [`FileReport`].
+#[derive(Debug)]
+pub enum FileError {
+    Io(io::Error),
+    /// The blocking validation task panicked or the runtime shut down.
+    Task(tokio::task::JoinError),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io(err) => write!(f, "io error: {}", err),
+            FileError::Task(err) => write!(f, "validation task failed: {}", err),
+        }
+    }
+}
+
+impl Error for FileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FileError::Io(err) => Some(err),
+            FileError::Task(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(err: io::Error) -> Self {
+        FileError::Io(err)
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static BLOCKING: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Validates the file at `path`, reading 64 KiB at a time; see
+/// [`validate_file_with_capacity`].
+pub async fn validate_file(path: impl AsRef<Path>) -> Result<FileReport, FileError> {
+    validate_file_with_capacity(path, 64 * 1024).await
+}
+
+/// Validates the file at `path` through a buffer of `capacity` bytes (at
+/// least 8).
+///
+/// Smaller files are read with `tokio::fs`, yielding to the runtime after
+/// every buffer. Files of [`BLOCKING_THRESHOLD`] bytes or more are read on
+/// the blocking thread pool; dropping the future stops that task at the next
+/// buffer and closes the file.
+pub async fn validate_file_with_capacity(
+    path: impl AsRef<Path>,
+    capacity: usize,
+) -> Result<FileReport, FileError> {
+    let capacity = capacity.max(8);
+    let mut file = tokio::fs::File::open(path).await?;
+    let size = file.metadata().await?.len();
+    let stats = if size >= BLOCKING_THRESHOLD {
+        #[cfg(test)]
+        BLOCKING.with(|count| count.set(count.get() + 1));
+        let file = file.into_std().await;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let _guard = CancelOnDrop(cancelled.clone());
+        tokio::task::spawn_blocking(move || analyze_reader(file, capacity, &cancelled))
+            .await
+            .map_err(FileError::Task)??
+    } else {
+        let mut stats = TextStats {
+            ascii_only: true,
+            ..TextStats::default()
+        };
+        let mut buf = vec![0; capacity];
+        let mut tail = 0;
+        loop {
+            let n = file.read(&mut buf[tail..]).await?;
+            match advance(&mut stats, &mut buf, tail, n) {
+                Some(new_tail) => tail = new_tail,
+                None => break,
+            }
+            tokio::task::yield_now().await;
+        }
+        stats
+    };
+    Ok(FileReport { size, stats })
+}
+
+/// Sets the flag when the future owning it is dropped.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+fn analyze_reader<R: Read>(
+    mut reader: R,
+    capacity: usize,
+    cancelled: &AtomicBool,
+) -> io::Result<TextStats> {
+    let mut stats = TextStats {
+        ascii_only: true,
+        ..TextStats::default()
+    };
+    let mut buf = vec![0; capacity];
+    let mut tail = 0;
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        let n = match reader.read(&mut buf[tail..]) {
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        match advance(&mut stats, &mut buf, tail, n) {
+            Some(new_tail) => tail = new_tail,
+            None => return Ok(stats),
+        }
+    }
+}
+
+/// Analyzes the `tail` bytes carried over plus `n` bytes just read into
+/// `buf`, and moves the new tail to the front. Returns `None` at EOF.
+#[inline]
+fn advance(stats: &mut TextStats, buf: &mut [u8], tail: usize, n: usize) -> Option<usize> {
+    let len = tail + n;
+    let new_tail = analyze_block(stats, &buf[..len], n == 0);
+    if n == 0 {
+        return None;
+    }
+    buf.copy_within(len - new_tail..len, 0);
+    Some(new_tail)
+}
+
+#[cfg(test)]
+mod test_file {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::core::UtfError;
+    use crate::stats::analyze;
+
+    /// A file in the temp directory, removed on drop.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("valid_utf8_{}_{}", std::process::id(), name));
+            std::fs::write(&path, contents).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate_file_small() {
+        let mut contents = "text é€😀ข ".repeat(1000).into_bytes();
+        contents.extend_from_slice(&[0xe0, 0x80, 0x61, 0xf0, 0x9f]);
+        let file = TempFile::new("small", &contents);
+        for capacity in [0, 5, 4096].iter() {
+            let report = validate_file_with_capacity(&file.0, *capacity)
+                .await
+                .unwrap();
+            assert_eq!(report.size, contents.len() as u64);
+            assert_eq!(report.stats, analyze(&contents));
+            assert!(!report.is_valid());
+            assert_eq!(report.first_error(), Some(contents.len() - 5));
+        }
+        assert_eq!(BLOCKING.with(|count| count.get()), 0);
+
+        let empty = TempFile::new("empty", b"");
+        let report = validate_file(&empty.0).await.unwrap();
+        assert!(report.is_valid() && report.stats.ascii_only);
+        assert!(matches!(
+            validate_file(empty.0.join("missing")).await,
+            Err(FileError::Io(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_validate_file_blocking() {
+        let mut contents = "😀 some text "
+            .repeat(BLOCKING_THRESHOLD as usize / 10)
+            .into_bytes();
+        let corrupt = contents.len() - 1000;
+        contents[corrupt] = 0x80;
+        let file = TempFile::new("large", &contents);
+        let report = validate_file(&file.0).await.unwrap();
+        assert_eq!(BLOCKING.with(|count| count.get()), 1);
+        assert_eq!(report.size, contents.len() as u64);
+        assert_eq!(report.first_error(), Some(corrupt));
+        assert_eq!(report.stats.errors[0].kind(), UtfError::InvalidLead);
+        assert_eq!(report.stats.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_analyze_reader_cancelled() {
+        let cancelled = AtomicBool::new(true);
+        let err = analyze_reader(&b"abc"[..], 4, &cancelled).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+    }
+}
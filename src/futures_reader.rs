@@ -0,0 +1,141 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_io::AsyncRead;
+
+use crate::io::ReadState;
+
+/// [`futures_io::AsyncRead`] counterpart of [`Utf8Reader`](crate::Utf8Reader),
+/// with the same semantics.
+#[derive(Debug)]
+pub struct FuturesUtf8Reader<R> {
+    inner: R,
+    state: ReadState,
+}
+
+impl<R: AsyncRead + Unpin> FuturesUtf8Reader<R> {
+    pub fn new(inner: R) -> Self {
+        FuturesUtf8Reader {
+            inner,
+            state: ReadState::default(),
+        }
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for FuturesUtf8Reader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let inner = &mut this.inner;
+        this.state
+            .poll_read(buf, |buf| Pin::new(&mut *inner).poll_read(cx, buf))
+    }
+}
+
+#[cfg(test)]
+mod test_futures_reader {
+    use futures_executor::block_on;
+    use futures_util::io::AsyncReadExt;
+
+    use super::*;
+    use crate::error::Utf8ErrorAt;
+    use crate::io::StreamError;
+
+    /// Yields `data` in chunks of cycling sizes, returning `Pending` before
+    /// every other chunk.
+    struct Pathological {
+        data: Vec<u8>,
+        pos: usize,
+        sizes: &'static [usize],
+        calls: usize,
+    }
+
+    impl Pathological {
+        fn new(data: &[u8]) -> Self {
+            Pathological {
+                data: data.to_vec(),
+                pos: 0,
+                sizes: &[1, 3, 2, 7, 1, 1, 5],
+                calls: 0,
+            }
+        }
+    }
+
+    impl AsyncRead for Pathological {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.calls += 1;
+            if self.calls.is_multiple_of(2) {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let size = self.sizes[self.calls / 2 % self.sizes.len()];
+            let n = size.min(buf.len()).min(self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    fn read_all(data: &[u8]) -> (Vec<u8>, io::Result<usize>) {
+        let mut reader = FuturesUtf8Reader::new(Pathological::new(data));
+        let mut out = Vec::new();
+        let result = block_on(reader.read_to_end(&mut out));
+        (out, result)
+    }
+
+    #[test]
+    fn test_futures_reader_valid() {
+        let input = "aé€😀ข".repeat(50);
+        let (out, result) = read_all(input.as_bytes());
+        assert_eq!(result.unwrap(), input.len());
+        assert_eq!(out, input.as_bytes());
+    }
+
+    #[test]
+    fn test_futures_reader_invalid() {
+        let mut input = "😀€".repeat(20).into_bytes();
+        input.extend_from_slice(&[0xed, 0xa0, 0x80]);
+        let (out, result) = read_all(&input);
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let inner = err.get_ref().unwrap().downcast_ref::<Utf8ErrorAt>();
+        assert_eq!(inner.unwrap().offset(), 140);
+        // The first byte of the bad sequence arrived in an earlier chunk but
+        // was held back.
+        assert_eq!(out.len(), 140);
+    }
+
+    #[test]
+    fn test_futures_reader_eof_mid_sequence() {
+        let mut input = "ab".as_bytes().to_vec();
+        input.extend_from_slice(&[0xf0, 0x9f, 0x98]);
+        let (_, result) = read_all(&input);
+        assert_eq!(
+            StreamError::from(result.unwrap_err()).utf8_offset(),
+            Some(2)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use rand::Rng;
+
+/// Distribution of sequence lengths produced by [`generate`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GenProfile {
+    /// Mostly printable ASCII with occasional multi-byte characters.
+    AsciiHeavy,
+    /// ASCII mixed with a large share of 2-byte (Latin, Greek, Cyrillic) characters.
+    EuropeanMix,
+    /// Mostly 3-byte characters.
+    CjkHeavy,
+    /// Mostly 4-byte characters.
+    EmojiHeavy,
+    /// Every scalar value is equally likely, so 4-byte characters dominate.
+    UniformScalars,
+}
+
+impl GenProfile {
+    /// Relative weights of 1, 2, 3 and 4 byte characters.
+    const fn weights(self) -> [u32; 4] {
+        match self {
+            GenProfile::AsciiHeavy => [90, 5, 4, 1],
+            GenProfile::EuropeanMix => [60, 35, 4, 1],
+            GenProfile::CjkHeavy => [15, 2, 80, 3],
+            GenProfile::EmojiHeavy => [30, 5, 15, 50],
+            // Number of scalar values of each length.
+            GenProfile::UniformScalars => [0x80, 0x780, 0xf800 - 0x800, 0x100000],
+        }
+    }
+}
+
+#[inline]
+const fn is_noncharacter(cp: u32) -> bool {
+    (cp >= 0xfdd0 && cp <= 0xfdef) || (cp & 0xfffe) == 0xfffe
+}
+
+fn sample_length<R: Rng + ?Sized>(rng: &mut R, profile: GenProfile) -> usize {
+    let weights = profile.weights();
+    let mut pick = rng.gen_range(0..weights.iter().sum::<u32>());
+    for (i, weight) in weights.iter().enumerate() {
+        if pick < *weight {
+            return i + 1;
+        }
+        pick -= weight;
+    }
+    unreachable!()
+}
+
+fn sample_code_point<R: Rng + ?Sized>(
+    rng: &mut R,
+    length: usize,
+    profile: GenProfile,
+    noncharacters: bool,
+) -> char {
+    loop {
+        let cp = match length {
+            1 if profile == GenProfile::UniformScalars => rng.gen_range(0..0x80),
+            1 => rng.gen_range(0x20..0x7f),
+            2 => rng.gen_range(0x80..0x800),
+            3 => rng.gen_range(0x800..0x10000),
+            4 => rng.gen_range(0x10000..=0x10ffff),
+            _ => unreachable!(),
+        };
+        if !noncharacters && is_noncharacter(cp) {
+            continue;
+        }
+        // Surrogates are rejected here.
+        if let Some(c) = char::from_u32(cp) {
+            return c;
+        }
+    }
+}
+
+/// Generates valid UTF-8 of exactly `len_bytes` bytes with character lengths
+/// distributed according to `profile`.
+///
+/// Noncharacters (U+FDD0..U+FDEF and U+xFFFE/U+xFFFF) are never emitted.
+pub fn generate<R: Rng + ?Sized>(rng: &mut R, len_bytes: usize, profile: GenProfile) -> Vec<u8> {
+    generate_with(rng, len_bytes, profile, false)
+}
+
+/// Like [`generate`], but emits noncharacters as well when `noncharacters` is set.
+pub fn generate_with<R: Rng + ?Sized>(
+    rng: &mut R,
+    len_bytes: usize,
+    profile: GenProfile,
+    noncharacters: bool,
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len_bytes);
+    let mut buf = [0; 4];
+    while out.len() < len_bytes {
+        // Near the end, shrink the character so the output fits exactly.
+        let length = sample_length(rng, profile).min(len_bytes - out.len());
+        let c = sample_code_point(rng, length, profile, noncharacters);
+        out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod test_generate {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::core::decode_step;
+
+    const PROFILES: [GenProfile; 5] = [
+        GenProfile::AsciiHeavy,
+        GenProfile::EuropeanMix,
+        GenProfile::CjkHeavy,
+        GenProfile::EmojiHeavy,
+        GenProfile::UniformScalars,
+    ];
+
+    /// Validates `bytes` and counts the characters of each length.
+    fn histogram(bytes: &[u8]) -> ([usize; 4], Vec<u32>) {
+        let mut counts = [0; 4];
+        let mut code_points = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let (cp, length) = decode_step(&bytes[pos..]).unwrap();
+            counts[length - 1] += 1;
+            code_points.push(cp);
+            pos += length;
+        }
+        (counts, code_points)
+    }
+
+    #[test]
+    fn test_generate_valid_exact_length() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for profile in PROFILES {
+            for len in [0, 1, 2, 3, 4, 5, 100, 4096] {
+                let bytes = generate(&mut rng, len, profile);
+                assert_eq!(bytes.len(), len);
+                assert!(std::str::from_utf8(&bytes).is_ok());
+                histogram(&bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_distribution() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for profile in PROFILES {
+            let (counts, _) = histogram(&generate(&mut rng, 200_000, profile));
+            let total = counts.iter().sum::<usize>() as f64;
+            let weights = profile.weights();
+            let weight_total = weights.iter().sum::<u32>() as f64;
+            for (count, weight) in counts.iter().zip(weights.iter()) {
+                let realized = *count as f64 / total;
+                let expected = *weight as f64 / weight_total;
+                assert!(
+                    (realized - expected).abs() < 0.02,
+                    "{:?}: realized {} expected {}",
+                    profile,
+                    realized,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_noncharacters() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (_, code_points) = histogram(&generate(&mut rng, 1 << 20, GenProfile::UniformScalars));
+        assert!(!code_points.iter().any(|cp| is_noncharacter(*cp)));
+
+        let bytes = generate_with(&mut rng, 1 << 20, GenProfile::UniformScalars, true);
+        let (_, code_points) = histogram(&bytes);
+        assert!(code_points.iter().any(|cp| is_noncharacter(*cp)));
+    }
+}
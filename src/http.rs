@@ -0,0 +1,209 @@
+use std::error::Error;
+use std::fmt;
+
+use axum::extract::{FromRequest, Request};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use http_body_util::BodyExt;
+
+use crate::error::Utf8ErrorAt;
+use crate::stream::Utf8Validator;
+
+/// Body size limit used when no [`Utf8BodyLimit`] extension is present.
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Maximum body size accepted by [`ValidUtf8Body`].
+///
+/// Add it to the request extensions (e.g. with `axum::Extension` as a layer)
+/// to override [`DEFAULT_BODY_LIMIT`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Utf8BodyLimit(pub usize);
+
+/// Extractor for a request body that must be valid UTF-8.
+///
+/// The body is validated frame by frame as it streams in, so characters
+/// split across chunks are handled and oversized bodies are rejected without
+/// being buffered in full.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidUtf8Body(pub String);
+
+/// Rejection of [`ValidUtf8Body`], answered with a JSON error document.
+#[derive(Debug)]
+pub enum Utf8BodyRejection {
+    /// 400, with the byte offset of the invalid sequence.
+    Invalid(Utf8ErrorAt),
+    /// 413, the body exceeds the configured limit.
+    TooLarge { limit: usize },
+    /// 400, the body stream itself failed.
+    Body(axum::Error),
+}
+
+impl fmt::Display for Utf8BodyRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Utf8BodyRejection::Invalid(err) => {
+                write!(f, "request body is not valid utf-8: {}", err)
+            }
+            Utf8BodyRejection::TooLarge { limit } => {
+                write!(f, "request body exceeds {} bytes", limit)
+            }
+            Utf8BodyRejection::Body(err) => write!(f, "failed to read request body: {}", err),
+        }
+    }
+}
+
+impl Error for Utf8BodyRejection {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Utf8BodyRejection::Invalid(err) => Some(err),
+            Utf8BodyRejection::TooLarge { .. } => None,
+            Utf8BodyRejection::Body(err) => Some(err),
+        }
+    }
+}
+
+impl IntoResponse for Utf8BodyRejection {
+    fn into_response(self) -> Response {
+        let message = self.to_string();
+        let (status, body) = match self {
+            Utf8BodyRejection::Invalid(err) => (
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({
+                    "error": "invalid_utf8",
+                    "code": err.code(),
+                    "offset": err.offset(),
+                    "message": message,
+                }),
+            ),
+            Utf8BodyRejection::TooLarge { limit } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                serde_json::json!({
+                    "error": "body_too_large",
+                    "limit": limit,
+                    "message": message,
+                }),
+            ),
+            Utf8BodyRejection::Body(_) => (
+                StatusCode::BAD_REQUEST,
+                serde_json::json!({
+                    "error": "body",
+                    "message": message,
+                }),
+            ),
+        };
+        (
+            status,
+            [(header::CONTENT_TYPE, "application/json")],
+            body.to_string(),
+        )
+            .into_response()
+    }
+}
+
+impl<S: Send + Sync> FromRequest<S> for ValidUtf8Body {
+    type Rejection = Utf8BodyRejection;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        let limit = req
+            .extensions()
+            .get::<Utf8BodyLimit>()
+            .map_or(DEFAULT_BODY_LIMIT, |limit| limit.0);
+        let mut body = req.into_body();
+        let mut validator = Utf8Validator::new();
+        let mut out = Vec::new();
+        while let Some(frame) = body.frame().await {
+            let frame = frame.map_err(Utf8BodyRejection::Body)?;
+            if let Ok(data) = frame.into_data() {
+                if data.len() > limit - out.len() {
+                    return Err(Utf8BodyRejection::TooLarge { limit });
+                }
+                validator.push(&data).map_err(Utf8BodyRejection::Invalid)?;
+                out.extend_from_slice(&data);
+            }
+        }
+        validator.finish().map_err(Utf8BodyRejection::Invalid)?;
+        debug_assert!(std::str::from_utf8(&out).is_ok());
+        // SAFETY: every byte went through the validator, which finished cleanly.
+        Ok(ValidUtf8Body(unsafe { String::from_utf8_unchecked(out) }))
+    }
+}
+
+#[cfg(test)]
+mod test_http {
+    use std::io;
+
+    use axum::body::{to_bytes, Body};
+    use axum::routing::post;
+    use axum::{Extension, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::core::UtfError;
+
+    fn app() -> Router {
+        Router::new().route(
+            "/",
+            post(|ValidUtf8Body(text): ValidUtf8Body| async move { text }),
+        )
+    }
+
+    fn chunked(chunks: Vec<Vec<u8>>) -> Request {
+        let stream = futures_util::stream::iter(chunks.into_iter().map(Ok::<_, io::Error>));
+        Request::post("/").body(Body::from_stream(stream)).unwrap()
+    }
+
+    async fn send(app: Router, req: Request) -> (StatusCode, String) {
+        let resp = app.oneshot(req).await.unwrap();
+        let status = resp.status();
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_split_character_accepted() {
+        let bytes = "ab😀".as_bytes();
+        let chunks = vec![
+            bytes[..3].to_vec(),
+            bytes[3..4].to_vec(),
+            bytes[4..].to_vec(),
+        ];
+        let (status, body) = send(app(), chunked(chunks)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "ab😀");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_rejected_with_offset() {
+        let chunks = vec![b"abc".to_vec(), vec![0xf0, 0x9f], vec![0x41]];
+        let (status, body) = send(app(), chunked(chunks)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"], "invalid_utf8");
+        assert_eq!(json["code"], UtfError::IncompleteSequence.code());
+        assert_eq!(json["offset"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_body_rejected() {
+        let (status, body) = send(app(), chunked(vec![b"a".to_vec(), vec![0xe2, 0x82]])).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["offset"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_size_limit() {
+        let app = app().layer(Extension(Utf8BodyLimit(8)));
+        let (status, _) = send(
+            app.clone(),
+            chunked(vec![b"1234".to_vec(), b"5678".to_vec()]),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let chunks = vec![b"1234".to_vec(), b"5678".to_vec(), b"9".to_vec()];
+        let (status, body) = send(app, chunked(chunks)).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["limit"], 8);
+    }
+}
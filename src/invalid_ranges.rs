@@ -0,0 +1,150 @@
+use core::iter::FusedIterator;
+use core::ops::Range;
+
+use crate::core::UtfError;
+use crate::validate::validate;
+
+/// Iterator over every maximal ill-formed subsequence of a byte slice,
+/// created by [`invalid_ranges`].
+///
+/// Unlike the character iterators it does not stop at the first error: each
+/// range ends where decoding resynchronizes, after one byte for a byte that
+/// cannot start a sequence and after the valid prefix of a sequence that is
+/// cut short, including one cut short by the end of the input.
+#[derive(Clone, Debug)]
+pub struct InvalidRanges<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+/// Finds the invalid byte ranges of `bytes`, in order, with the kind of
+/// error of each.
+pub fn invalid_ranges(bytes: &[u8]) -> InvalidRanges<'_> {
+    InvalidRanges { bytes, pos: 0 }
+}
+
+impl InvalidRanges<'_> {
+    /// Offset of the first byte not scanned yet.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+}
+
+impl Iterator for InvalidRanges<'_> {
+    type Item = (Range<usize>, UtfError);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.bytes.get(self.pos..)?;
+        match validate(rest) {
+            Ok(()) => {
+                self.pos = self.bytes.len();
+                None
+            }
+            Err(err) => {
+                let start = self.pos + err.offset();
+                let end = start + err.skip_len();
+                self.pos = end;
+                Some((start..end, err.kind()))
+            }
+        }
+    }
+}
+
+impl FusedIterator for InvalidRanges<'_> {}
+
+#[cfg(test)]
+mod test_invalid_ranges {
+    use super::*;
+
+    type Invalid = (Range<usize>, UtfError);
+
+    const EMOJI: &[u8] = "😀".as_bytes();
+
+    fn ranges(bytes: &[u8]) -> Vec<Invalid> {
+        invalid_ranges(bytes).collect()
+    }
+
+    #[test]
+    fn test_invalid_ranges_valid() {
+        assert_eq!(ranges(b""), []);
+        assert_eq!(ranges("aé€😀".as_bytes()), []);
+        let mut it = invalid_ranges(b"abc");
+        assert_eq!(it.next(), None);
+        assert_eq!(it.offset(), 3);
+    }
+
+    #[test]
+    fn test_invalid_ranges_resync_before_emoji() {
+        let cases: [(&[u8], &[Invalid]); 6] = [
+            (b"\x80", &[(0..1, UtfError::InvalidLead)]),
+            (
+                b"\x80\xbf",
+                &[(0..1, UtfError::InvalidLead), (1..2, UtfError::InvalidLead)],
+            ),
+            (
+                b"\xc0\x80",
+                &[
+                    (0..1, UtfError::OverlongSequence),
+                    (1..2, UtfError::InvalidLead),
+                ],
+            ),
+            (
+                b"\xed\xa0\x80",
+                &[
+                    (0..1, UtfError::InvalidCodePoint),
+                    (1..2, UtfError::InvalidLead),
+                    (2..3, UtfError::InvalidLead),
+                ],
+            ),
+            (b"\xe2\x82", &[(0..2, UtfError::IncompleteSequence)]),
+            (b"\xf0\x9f\x98", &[(0..3, UtfError::IncompleteSequence)]),
+        ];
+        for (bad, expected) in cases.iter() {
+            let mut bytes = b"a".to_vec();
+            bytes.extend_from_slice(bad);
+            bytes.extend_from_slice(EMOJI);
+            let shifted: Vec<_> = expected
+                .iter()
+                .map(|(range, kind)| (range.start + 1..range.end + 1, *kind))
+                .collect();
+            assert_eq!(ranges(&bytes), shifted, "{:x?}", bad);
+            // Resynchronized on the emoji, which is valid.
+            let last = shifted.last().unwrap().0.end;
+            assert_eq!(&bytes[last..], EMOJI);
+        }
+    }
+
+    #[test]
+    fn test_invalid_ranges_truncated_at_end() {
+        let mut bytes = EMOJI.to_vec();
+        bytes.extend_from_slice(b"\xff");
+        bytes.extend_from_slice(&EMOJI[..3]);
+        assert_eq!(
+            ranges(&bytes),
+            [
+                (4..5, UtfError::InvalidLead),
+                (5..8, UtfError::NotEnoughRoom)
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_invalid_ranges_matches_analyze() {
+        use crate::stats::analyze;
+        use crate::test_util::{random_corrupt, XorShift};
+
+        let mut rng = XorShift::new(265);
+        for _ in 0..1000 {
+            let fragments = rng.below(40);
+            let input = random_corrupt(&mut rng, fragments);
+            let expected: Vec<_> = analyze(&input)
+                .errors
+                .iter()
+                .map(|err| (err.offset()..err.offset() + err.skip_len(), err.kind()))
+                .collect();
+            assert_eq!(ranges(&input), expected, "{:x?}", input);
+        }
+    }
+}
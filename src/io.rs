@@ -0,0 +1,860 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+use std::task::Poll;
+
+use crate::core::{is_continuation, sequence_length};
+use crate::error::Utf8ErrorAt;
+use crate::stats::TextStats;
+use crate::stream::{tail_error, validate_chunk, Utf8Validator};
+
+/// Error of the streaming APIs: either the underlying IO failed or the data
+/// is not valid UTF-8.
+///
+/// `Read`/`Write` implementations report the UTF-8 case as an [`io::Error`]
+/// of kind [`io::ErrorKind::InvalidData`] wrapping the [`Utf8ErrorAt`];
+/// converting such an error back with `StreamError::from` recovers it.
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Utf8(Utf8ErrorAt),
+}
+
+impl StreamError {
+    #[inline]
+    pub fn is_utf8(&self) -> bool {
+        matches!(self, StreamError::Utf8(_))
+    }
+
+    /// Stream offset of the invalid sequence, if this is a UTF-8 error.
+    #[inline]
+    pub fn utf8_offset(&self) -> Option<u64> {
+        match self {
+            StreamError::Utf8(err) => Some(err.stream_offset()),
+            StreamError::Io(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Io(err) => write!(f, "io error: {}", err),
+            StreamError::Utf8(err) => write!(f, "invalid utf-8: {}", err),
+        }
+    }
+}
+
+impl Error for StreamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StreamError::Io(err) => Some(err),
+            StreamError::Utf8(err) => Some(err),
+        }
+    }
+}
+
+impl From<Utf8ErrorAt> for StreamError {
+    fn from(err: Utf8ErrorAt) -> Self {
+        StreamError::Utf8(err)
+    }
+}
+
+impl From<io::Error> for StreamError {
+    /// Unwraps UTF-8 errors that were reported through `io::Error`.
+    fn from(err: io::Error) -> Self {
+        let utf8 = err
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<Utf8ErrorAt>())
+            .copied();
+        match utf8 {
+            Some(utf8) if err.kind() == io::ErrorKind::InvalidData => StreamError::Utf8(utf8),
+            _ => StreamError::Io(err),
+        }
+    }
+}
+
+impl From<StreamError> for io::Error {
+    fn from(err: StreamError) -> Self {
+        match err {
+            StreamError::Io(err) => err,
+            StreamError::Utf8(err) => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+impl From<Utf8ErrorAt> for io::Error {
+    fn from(err: Utf8ErrorAt) -> Self {
+        StreamError::Utf8(err).into()
+    }
+}
+
+/// Validation state shared by the reader adapters.
+///
+/// Bytes of a sequence split across inner reads are held back until the
+/// sequence is complete, so callers never receive bytes of an invalid or
+/// truncated sequence.
+#[derive(Debug, Default)]
+pub(crate) struct ReadState {
+    validator: Utf8Validator,
+    /// Validated bytes that did not fit into a caller's small buffer.
+    ready: [u8; 4],
+    ready_start: usize,
+    ready_end: usize,
+}
+
+impl ReadState {
+    /// Fills `buf` through `read`, which reads from the inner reader.
+    pub(crate) fn poll_read<F>(&mut self, buf: &mut [u8], mut read: F) -> Poll<io::Result<usize>>
+    where
+        F: FnMut(&mut [u8]) -> Poll<io::Result<usize>>,
+    {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if self.ready_start < self.ready_end {
+            let n = (self.ready_end - self.ready_start).min(buf.len());
+            buf[..n].copy_from_slice(&self.ready[self.ready_start..self.ready_start + n]);
+            self.ready_start += n;
+            return Poll::Ready(Ok(n));
+        }
+        let small = buf.len() < 4;
+        let mut scratch = [0; 4];
+        loop {
+            if let Some(err) = self.validator.error() {
+                return Poll::Ready(Err(err.into()));
+            }
+            // A buffer shorter than one character reads through `scratch`.
+            let target: &mut [u8] = if small { &mut scratch } else { &mut *buf };
+            let pending = self.validator.pending();
+            let carried = pending.len();
+            target[..carried].copy_from_slice(pending);
+            let n = match read(&mut target[carried..]) {
+                Poll::Ready(result) => result?,
+                Poll::Pending => return Poll::Pending,
+            };
+            if n == 0 {
+                self.validator.finish()?;
+                return Poll::Ready(Ok(0));
+            }
+            let start = self.validator.bytes_consumed() - carried as u64;
+            let len = match self.validator.push(&target[carried..carried + n]) {
+                Ok(()) => carried + n - self.validator.pending().len(),
+                Err(err) => (err.stream_offset() - start) as usize,
+            };
+            if len == 0 {
+                continue;
+            }
+            if !small {
+                return Poll::Ready(Ok(len));
+            }
+            let n = len.min(buf.len());
+            buf[..n].copy_from_slice(&scratch[..n]);
+            self.ready = scratch;
+            self.ready_start = n;
+            self.ready_end = len;
+            return Poll::Ready(Ok(n));
+        }
+    }
+}
+
+/// A reader that validates the data read through it as UTF-8.
+///
+/// Reads only ever return complete characters (except into buffers shorter
+/// than one character). On invalid data the valid part of the last read is
+/// returned first, then every following read fails with the error. Reaching
+/// EOF in the middle of a sequence is an error as well.
+#[derive(Debug)]
+pub struct Utf8Reader<R> {
+    inner: R,
+    state: ReadState,
+}
+
+impl<R: Read> Utf8Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Utf8Reader {
+            inner,
+            state: ReadState::default(),
+        }
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Stream offset of the invalid sequence once a read has failed with
+    /// one.
+    #[inline]
+    pub fn error_offset(&self) -> Option<u64> {
+        self.state.validator.error().map(|err| err.stream_offset())
+    }
+}
+
+impl<R: Read> Read for Utf8Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let inner = &mut self.inner;
+        match self
+            .state
+            .poll_read(buf, |buf| Poll::Ready(inner.read(buf)))
+        {
+            Poll::Ready(result) => result,
+            Poll::Pending => unreachable!(),
+        }
+    }
+}
+
+/// A buffered reader that validates the data read through it as UTF-8.
+///
+/// [`fill_buf`](BufRead::fill_buf) only ever returns validated text that
+/// ends on a character boundary; the bytes of a character split across
+/// inner reads stay hidden until it is complete. Errors are reported like
+/// [`Utf8Reader`] does, after the valid data before them.
+#[derive(Debug)]
+pub struct BufUtf8Reader<R> {
+    inner: R,
+    buf: Box<[u8]>,
+    /// Next byte to hand out.
+    pos: usize,
+    /// End of the validated bytes.
+    valid_end: usize,
+    /// End of the bytes read.
+    end: usize,
+    /// Stream offset of `buf[0]`.
+    offset: u64,
+    /// Error right after `valid_end`.
+    error: Option<Utf8ErrorAt>,
+}
+
+impl<R: Read> BufUtf8Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(8 * 1024, inner)
+    }
+
+    /// A reader with a buffer of `capacity` bytes, but at least 4 so that a
+    /// character always fits.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        BufUtf8Reader {
+            inner,
+            buf: vec![0; capacity.max(4)].into_boxed_slice(),
+            pos: 0,
+            valid_end: 0,
+            end: 0,
+            offset: 0,
+            error: None,
+        }
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for BufUtf8Reader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for BufUtf8Reader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.valid_end {
+            if let Some(err) = self.error {
+                return Err(err.into());
+            }
+            // Keep the start of a split character and read the rest of it.
+            self.buf.copy_within(self.valid_end..self.end, 0);
+            self.offset = self.offset.saturating_add(self.valid_end as u64);
+            self.end -= self.valid_end;
+            self.pos = 0;
+            self.valid_end = 0;
+            let n = match self.inner.read(&mut self.buf[self.end..]) {
+                Ok(n) => n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            if n == 0 {
+                if self.end == 0 {
+                    return Ok(&[]);
+                }
+                let err = tail_error(&self.buf[..self.end]).at_stream(self.offset);
+                self.error = Some(err);
+                continue;
+            }
+            self.end += n;
+            match validate_chunk(&self.buf[..self.end]) {
+                Ok(tail) => self.valid_end = self.end - tail,
+                Err(err) => {
+                    self.valid_end = err.offset();
+                    self.error = Some(err.at_stream(self.offset));
+                }
+            }
+        }
+        Ok(&self.buf[self.pos..self.valid_end])
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.valid_end);
+    }
+}
+
+/// A writer that validates the data written through it as UTF-8.
+///
+/// The bytes of a character split across writes are held back until a later
+/// write completes it, so the inner writer only ever receives whole
+/// characters. A write that finds invalid data passes on the valid bytes
+/// before it and returns their count, like [`Utf8Reader`] does; the next
+/// write fails with [`io::ErrorKind::InvalidData`] wrapping the
+/// [`Utf8ErrorAt`], and so does every one after it. [`flush`](Write::flush) keeps
+/// the held-back bytes; call [`finish`](Self::finish) at the end of the text.
+///
+/// A write that the inner writer fails is not taken in, so it can be
+/// retried, except that a character completed by it may already have gone
+/// through, which makes it a partial write instead.
+#[derive(Debug)]
+pub struct Utf8Writer<W> {
+    inner: W,
+    validator: Utf8Validator,
+}
+
+impl<W: Write> Utf8Writer<W> {
+    pub fn new(inner: W) -> Self {
+        Utf8Writer {
+            inner,
+            validator: Utf8Validator::new(),
+        }
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the inner writer; bytes still held back are dropped.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Stream offset of the invalid sequence once a write has failed with
+    /// one.
+    #[inline]
+    pub fn error_offset(&self) -> Option<u64> {
+        self.validator.error().map(|err| err.stream_offset())
+    }
+
+    /// Fails if the text ended in the middle of a character, and flushes the
+    /// inner writer otherwise.
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.validator.finish()?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for Utf8Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(err) = self.validator.error() {
+            return Err(err.into());
+        }
+        let mut carried = [0; 4];
+        let carried_len = self.validator.pending().len();
+        carried[..carried_len].copy_from_slice(self.validator.pending());
+        let start = self.validator.bytes_consumed() - carried_len as u64;
+        // Only take the input once the inner writer has it, so that a write
+        // that fails can be retried.
+        let mut validator = self.validator.snapshot();
+        let result = validator.push(buf);
+        // Everything validated before what is held back or invalid.
+        let valid = match result {
+            Ok(()) => carried_len + buf.len() - validator.pending().len(),
+            Err(err) => (err.stream_offset() - start) as usize,
+        };
+        // The carried bytes go out with the rest of their character, which
+        // is valid if anything is.
+        let from_carried = valid.min(carried_len);
+        let completed = match from_carried {
+            0 => 0,
+            _ => sequence_length(carried[0]) - carried_len,
+        };
+        carried[carried_len..carried_len + completed].copy_from_slice(&buf[..completed]);
+        self.inner.write_all(&carried[..from_carried + completed])?;
+        if let Err(err) = self.inner.write_all(&buf[completed..valid - from_carried]) {
+            if completed == 0 {
+                return Err(err);
+            }
+            // The completed character went through: a partial write.
+            self.validator.push(&buf[..completed])?;
+            return Ok(completed);
+        }
+        self.validator = validator;
+        match result {
+            Ok(()) => Ok(buf.len()),
+            // Report the valid bytes first and the error on the next write.
+            Err(_) if valid > from_carried => Ok(valid - from_carried),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Validates everything `reader` produces, reading 64 KiB at a time; see
+/// [`validate_reader_with_capacity`].
+pub fn validate_reader<R: Read>(reader: R) -> Result<TextStats, StreamError> {
+    validate_reader_with_capacity(reader, 64 * 1024)
+}
+
+/// Validates everything `reader` produces through a buffer of `capacity`
+/// bytes (at least 1), returning the same stats as
+/// [`analyze`](crate::analyze).
+///
+/// Memory use does not depend on the length of the input. Interrupted reads
+/// are retried, and UTF-8 error offsets count from the start of the stream.
+pub fn validate_reader_with_capacity<R: Read>(
+    mut reader: R,
+    capacity: usize,
+) -> Result<TextStats, StreamError> {
+    let mut buf = vec![0; capacity.max(1)];
+    let mut validator = Utf8Validator::new();
+    let mut stats = TextStats {
+        ascii_only: true,
+        ..TextStats::default()
+    };
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let chunk = &buf[..n];
+        validator.push(chunk)?;
+        stats.bytes = stats.bytes.saturating_add(n as u64);
+        stats.ascii_only &= chunk.is_ascii();
+        // Valid input: every byte that is not a continuation starts a char.
+        stats.chars += chunk.iter().filter(|&&b| !is_continuation(b)).count() as u64;
+    }
+    validator.finish()?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test_io {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::core::UtfError;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    #[test]
+    fn test_stream_error_conversions() {
+        let utf8 = Utf8ErrorAt::new(7, UtfError::InvalidLead, 1);
+        let err: io::Error = StreamError::from(utf8).into();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let back = StreamError::from(err);
+        assert!(back.is_utf8());
+        assert_eq!(back.utf8_offset(), Some(7));
+        assert!(back
+            .source()
+            .unwrap()
+            .downcast_ref::<Utf8ErrorAt>()
+            .is_some());
+
+        let io_err = StreamError::from(io::Error::other("boom"));
+        assert!(!io_err.is_utf8());
+        assert_eq!(io_err.utf8_offset(), None);
+        assert!(io_err
+            .source()
+            .unwrap()
+            .downcast_ref::<io::Error>()
+            .is_some());
+    }
+
+    #[test]
+    fn test_utf8_reader_copy_error() {
+        let mut input = "😀".repeat(3000).into_bytes();
+        input.extend_from_slice(&[0xe0, 0x80]);
+        input.extend_from_slice(b"tail");
+        let mut reader = Utf8Reader::new(Cursor::new(input));
+        let mut out = Vec::new();
+        let err = io::copy(&mut reader, &mut out).unwrap_err();
+        let inner = err
+            .get_ref()
+            .unwrap()
+            .downcast_ref::<Utf8ErrorAt>()
+            .unwrap();
+        assert_eq!(inner.offset(), 12000);
+        assert_eq!(inner.kind(), UtfError::OverlongSequence);
+        assert_eq!(out.len(), 12000);
+
+        let err = StreamError::from(err);
+        assert_eq!(err.utf8_offset(), Some(12000));
+    }
+
+    #[test]
+    fn test_utf8_reader_small_buffers() {
+        let input = "aé€😀ข".repeat(10);
+        for size in 1..8 {
+            let mut reader = Utf8Reader::new(Cursor::new(input.as_bytes()));
+            let mut out = Vec::new();
+            let mut buf = vec![0; size];
+            loop {
+                let n = reader.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                out.extend_from_slice(&buf[..n]);
+            }
+            assert_eq!(out, input.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_utf8_reader_error_offset_small_buffers() {
+        let mut input = "aé€😀".repeat(5).into_bytes();
+        let offset = input.len() + 1;
+        input.extend_from_slice(b"b\xf0\x9f\xe2\x82\xac");
+        for size in 1..=3 {
+            let mut reader = Utf8Reader::new(Cursor::new(&input));
+            let mut out = Vec::new();
+            let mut buf = vec![0; size];
+            let err = loop {
+                match reader.read(&mut buf) {
+                    Ok(n) => out.extend_from_slice(&buf[..n]),
+                    Err(err) => break err,
+                }
+            };
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(StreamError::from(err).utf8_offset(), Some(offset as u64));
+            assert_eq!(reader.error_offset(), Some(offset as u64));
+            assert_eq!(out, &input[..offset]);
+        }
+        let mut reader = Utf8Reader::new(Cursor::new("fine"));
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert_eq!(reader.error_offset(), None);
+    }
+
+    #[test]
+    fn test_utf8_reader_holds_back_partial_sequence() {
+        // The first read ends inside the 4-byte sequence; its bytes must not
+        // be handed out before it turns out to be invalid.
+        let input: &[u8] = &[0x61, 0xf0, 0x9f, 0x41];
+        let mut reader = Utf8Reader::new(input);
+        let mut buf = [0; 3];
+        assert_eq!(reader.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 0x61);
+        let err = StreamError::from(reader.read(&mut buf).unwrap_err());
+        assert_eq!(err.utf8_offset(), Some(1));
+    }
+
+    #[test]
+    fn test_buf_utf8_reader_read_line() {
+        let text = "first line é€\nsecond 😀😀 line\nขฃค\nno newline 😀";
+        for capacity in [1, 4, 5, 7, 64].iter() {
+            let mut reader = BufUtf8Reader::with_capacity(*capacity, text.as_bytes());
+            let mut lines = Vec::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() != 0 {
+                lines.push(std::mem::take(&mut line));
+            }
+            assert_eq!(lines.concat(), text, "capacity {}", capacity);
+            assert_eq!(lines.len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_buf_utf8_reader_fill_buf_on_boundaries() {
+        // With the smallest buffer every fill holds exactly whole characters.
+        let text = "a😀b€é😀";
+        let mut reader = BufUtf8Reader::with_capacity(0, text.as_bytes());
+        let mut out = Vec::new();
+        loop {
+            let buf = reader.fill_buf().unwrap();
+            if buf.is_empty() {
+                break;
+            }
+            assert!(std::str::from_utf8(buf).is_ok(), "{:x?}", buf);
+            out.extend_from_slice(buf);
+            let len = buf.len();
+            reader.consume(len);
+        }
+        assert_eq!(out, text.as_bytes());
+
+        // Partial consumes of a fill keep returning the rest of it.
+        let mut reader = BufUtf8Reader::new("abc😀".as_bytes());
+        assert_eq!(reader.fill_buf().unwrap(), "abc😀".as_bytes());
+        reader.consume(2);
+        assert_eq!(reader.fill_buf().unwrap(), "c😀".as_bytes());
+        reader.consume(100);
+        assert!(reader.fill_buf().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_buf_utf8_reader_errors() {
+        let mut reader = BufUtf8Reader::with_capacity(4, &b"ab\xe2\x82x"[..]);
+        let mut out = Vec::new();
+        let err = StreamError::from(reader.read_to_end(&mut out).unwrap_err());
+        assert_eq!(out, b"ab");
+        assert_eq!(err.utf8_offset(), Some(2));
+        assert!(reader.fill_buf().is_err());
+
+        // A truncated character is never handed out.
+        let mut reader = BufUtf8Reader::new(&b"ab\xf0\x9f"[..]);
+        assert_eq!(reader.fill_buf().unwrap(), b"ab");
+        reader.consume(2);
+        let err = StreamError::from(reader.fill_buf().unwrap_err());
+        assert_eq!(err.utf8_offset(), Some(2));
+    }
+
+    /// Produces `len` bytes of repeated text, with `corrupt` overwritten by
+    /// 0xff, without storing the input.
+    struct Synthetic {
+        pos: usize,
+        len: usize,
+        corrupt: Option<usize>,
+        interrupt: bool,
+    }
+
+    const PATTERN: &[u8] = "Some text, é€😀ข and more text.\n".as_bytes();
+
+    impl Read for Synthetic {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            // Odd read sizes split characters.
+            let n = buf.len().min(self.len - self.pos).min(40_009);
+            for (i, byte) in buf[..n].iter_mut().enumerate() {
+                let pos = self.pos + i;
+                *byte = if Some(pos) == self.corrupt {
+                    0xff
+                } else {
+                    PATTERN[pos % PATTERN.len()]
+                };
+            }
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_validate_reader_large() {
+        let len = 100 * 1024 * 1024 / PATTERN.len() * PATTERN.len();
+        let reader = Synthetic {
+            pos: 0,
+            len,
+            corrupt: None,
+            interrupt: false,
+        };
+        let stats = validate_reader(reader).unwrap();
+        let per_pattern = std::str::from_utf8(PATTERN).unwrap().chars().count();
+        assert_eq!(stats.bytes, len as u64);
+        assert_eq!(stats.chars, (len / PATTERN.len() * per_pattern) as u64);
+        assert!(!stats.ascii_only && stats.is_valid());
+    }
+
+    #[test]
+    fn test_validate_reader_corrupt() {
+        for capacity in [1, 3, 4096, 1 << 16].iter() {
+            let reader = Synthetic {
+                pos: 0,
+                len: 1 << 20,
+                corrupt: Some(300_001),
+                interrupt: false,
+            };
+            let err = validate_reader_with_capacity(reader, *capacity).unwrap_err();
+            // The corrupted byte is the lead of a '€'.
+            assert_eq!(err.utf8_offset(), Some(300_001));
+        }
+        let stats = validate_reader_with_capacity(&b"abc"[..], 0).unwrap();
+        assert_eq!((stats.bytes, stats.chars, stats.ascii_only), (3, 3, true));
+        let err = validate_reader(&[0x61, 0xf0, 0x9f][..]).unwrap_err();
+        assert_eq!(err.utf8_offset(), Some(1));
+    }
+
+    #[test]
+    fn test_utf8_reader_eof_mid_sequence() {
+        let mut reader = Utf8Reader::new(Cursor::new(vec![0x61, 0xf0, 0x9f]));
+        let mut out = Vec::new();
+        let err = StreamError::from(reader.read_to_end(&mut out).unwrap_err());
+        assert_eq!(err.utf8_offset(), Some(1));
+    }
+
+    #[test]
+    fn test_utf8_writer_split_character() {
+        let mut writer = Utf8Writer::new(Vec::new());
+        assert_eq!(writer.write(b"a\xe2").unwrap(), 2);
+        assert_eq!(writer.get_ref(), b"a");
+        assert_eq!(writer.write(b"\x82").unwrap(), 1);
+        assert_eq!(writer.get_ref(), b"a");
+        // Flushing keeps the partial character.
+        writer.flush().unwrap();
+        assert_eq!(writer.write(b"\xacb").unwrap(), 2);
+        assert_eq!(writer.get_ref(), "a€b".as_bytes());
+        writer.finish().unwrap();
+        assert_eq!(writer.error_offset(), None);
+    }
+
+    #[test]
+    fn test_utf8_writer_errors() {
+        let mut writer = Utf8Writer::new(Vec::new());
+        writer.write_all(b"ok").unwrap();
+        // The valid bytes before the error go through, and the error comes
+        // with the next write.
+        assert_eq!(writer.write(b"!\xff").unwrap(), 1);
+        assert_eq!(writer.get_ref(), b"ok!");
+        let err = writer.write(b"\xff").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = StreamError::from(err);
+        assert_eq!(err.utf8_offset(), Some(3));
+        // The rest never does.
+        assert!(writer.write(b"more").is_err());
+        assert!(writer.finish().is_err());
+        assert_eq!(writer.error_offset(), Some(3));
+        assert_eq!(writer.into_inner(), b"ok!");
+
+        // `write_all` sends the valid bytes once and then fails.
+        let mut writer = Utf8Writer::new(Vec::new());
+        let err = StreamError::from(writer.write_all(b"ok!\xff?").unwrap_err());
+        assert_eq!(err.utf8_offset(), Some(3));
+        assert_eq!(writer.get_ref(), b"ok!");
+
+        // A carried sequence that the next write breaks.
+        let mut writer = Utf8Writer::new(Vec::new());
+        writer.write_all(b"a\xe2\x82").unwrap();
+        let err = writer.write(b"b").unwrap_err();
+        let inner = err
+            .get_ref()
+            .unwrap()
+            .downcast_ref::<Utf8ErrorAt>()
+            .unwrap();
+        assert_eq!(inner.kind(), UtfError::IncompleteSequence);
+        assert_eq!(inner.offset(), 1);
+        assert_eq!(writer.get_ref(), b"a");
+
+        let mut writer = Utf8Writer::new(Vec::new());
+        writer.write_all(&"é\u{1f600}".as_bytes()[..4]).unwrap();
+        let err = StreamError::from(writer.finish().unwrap_err());
+        assert_eq!(err.utf8_offset(), Some(2));
+        assert_eq!(writer.get_ref(), "é".as_bytes());
+    }
+
+    /// Takes `budget` writes before failing.
+    struct Flaky {
+        out: Vec<u8>,
+        budget: usize,
+    }
+
+    impl Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.budget == 0 {
+                return Err(io::Error::other("full"));
+            }
+            self.budget -= 1;
+            self.out.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_utf8_writer_retry_after_inner_error() {
+        let mut writer = Utf8Writer::new(Flaky {
+            out: Vec::new(),
+            budget: 1,
+        });
+        assert_eq!(writer.write(b"a\xe2").unwrap(), 2);
+        assert!(writer.write(b"\x82\xacb").is_err());
+        assert_eq!(writer.get_ref().out, b"a");
+        writer.get_mut().budget = 2;
+        assert_eq!(writer.write(b"\x82\xacb").unwrap(), 3);
+        assert_eq!(writer.get_ref().out, "a€b".as_bytes());
+        writer.finish().unwrap();
+
+        // The completed character goes through even if the rest fails.
+        let mut writer = Utf8Writer::new(Flaky {
+            out: Vec::new(),
+            budget: 1,
+        });
+        assert_eq!(writer.write(b"\xe2\x82").unwrap(), 2);
+        assert_eq!(writer.write(b"\xacbc").unwrap(), 1);
+        assert_eq!(writer.get_ref().out, "€".as_bytes());
+        writer.get_mut().budget = 1;
+        assert_eq!(writer.write(b"bc").unwrap(), 2);
+        assert_eq!(writer.get_ref().out, "€bc".as_bytes());
+        writer.finish().unwrap();
+
+        // An inner error comes before the invalid sequence is found.
+        let mut writer = Utf8Writer::new(Flaky {
+            out: Vec::new(),
+            budget: 0,
+        });
+        let err = StreamError::from(writer.write(b"ab\xff").unwrap_err());
+        assert!(!err.is_utf8());
+        assert_eq!(writer.error_offset(), None);
+        writer.get_mut().budget = 1;
+        assert_eq!(writer.write(b"ab\xff").unwrap(), 2);
+        assert_eq!(writer.get_ref().out, b"ab");
+        let err = StreamError::from(writer.write(b"\xff").unwrap_err());
+        assert_eq!(err.utf8_offset(), Some(2));
+    }
+
+    #[test]
+    fn test_utf8_writer_matches_utf8_reader() {
+        let mut rng = XorShift::new(272);
+        for _ in 0..500 {
+            let fragments = rng.below(20);
+            let input = random_corrupt(&mut rng, fragments);
+            let mut expected = Vec::new();
+            let expected_err = Utf8Reader::new(Cursor::new(&input))
+                .read_to_end(&mut expected)
+                .err()
+                .map(|err| StreamError::from(err).utf8_offset());
+
+            let mut writer = Utf8Writer::new(Vec::new());
+            let mut rest = &input[..];
+            let mut result = Ok(());
+            while !rest.is_empty() && result.is_ok() {
+                let (chunk, tail) = rest.split_at((1 + rng.below(5)).min(rest.len()));
+                result = writer.write_all(chunk);
+                rest = tail;
+            }
+            let result = result.and_then(|()| writer.finish());
+            let err = result.err().map(|err| StreamError::from(err).utf8_offset());
+            assert_eq!(err, expected_err, "{:x?}", input);
+            assert_eq!(writer.get_ref(), &expected, "{:x?}", input);
+        }
+    }
+}
@@ -1,5 +1,159 @@
 // https://github.com/lemire/validateutf8-experiments
 
+//! Validation and decoding of untrusted UTF-8.
+//!
+//! No function of this crate panics, whatever the input; see the
+//! `test_panic_free` tests.
+//!
+//! Iterators over characters end after the first UTF-8 error: every later
+//! call to `next` returns `None` without looking at the input again. The
+//! exception is [`Utf16Chars`], which resynchronizes after an unpaired
+//! surrogate like [`char::decode_utf16`].
+//!
+//! Without the default `std` feature the crate is `no_std`. Validation,
+//! decoding and the error types only need `core`; the `alloc` feature adds
+//! everything that returns a `String` or `Vec`, and `std` adds the I/O
+//! adapters and the `std::error::Error` impls. The `simd` feature lets
+//! [`validate_bulk`] use SSE2 where the CPU has it.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod backend;
+#[cfg(feature = "alloc")]
+mod bitmap;
+mod bom;
+#[cfg(feature = "std")]
+mod bufread;
+#[cfg(feature = "alloc")]
+mod canonical;
+#[cfg(feature = "std")]
+mod char_reader;
+mod cmp;
+mod code_points;
+#[cfg(feature = "alloc")]
+mod combining;
+mod concat;
 mod core;
+mod decode_utf8;
+mod decoder;
+mod dfa;
+mod encode;
+mod error;
+#[cfg(feature = "alloc")]
+mod escape;
+#[cfg(feature = "tokio")]
+mod file;
+#[cfg(feature = "futures")]
+mod futures_reader;
+#[cfg(feature = "rand")]
+mod generate;
+#[cfg(feature = "http")]
+mod http;
+mod invalid_ranges;
+#[cfg(feature = "std")]
+mod io;
+#[cfg(feature = "alloc")]
+mod lossy;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "alloc")]
+mod policy;
+mod rchars;
+mod runs;
+mod search;
+#[cfg(feature = "alloc")]
+mod stats;
+mod stream;
+#[cfg(feature = "alloc")]
+mod string_ext;
+#[cfg(all(test, feature = "std"))]
+mod test_panic_free;
+#[cfg(test)]
+mod test_util;
+mod utf16;
+mod utf8_chunks;
+mod valid_str;
+#[cfg(feature = "alloc")]
+mod valid_string;
+mod validate;
 
-pub use self::core::validate_next;
+pub use self::backend::{
+    backends, utf16_backends, Backend, Bulk, Dfa, Mismatch, Paranoid, Scalar, Utf16Backend,
+    Utf16Scalar, Utf16Word, Word,
+};
+#[cfg(feature = "alloc")]
+pub use self::bitmap::{char_start_bitmap, CharStartBitmap};
+pub use self::bom::{has_bom, skip_bom, strip_bom};
+#[cfg(feature = "std")]
+pub use self::bufread::{chunks_from_bufread, BufChunks, Chunk};
+#[cfg(feature = "alloc")]
+pub use self::canonical::{canonicalize, Dialect, LoneSurrogates};
+#[cfg(feature = "std")]
+pub use self::char_reader::{CharReadError, CharReader};
+pub use self::cmp::cmp_utf16_order;
+pub use self::code_points::{code_point_indices, count_code_points, CodePointIndices};
+#[cfg(feature = "alloc")]
+pub use self::combining::scan_combining_runs;
+pub use self::concat::{validate_concat, SeamError, ValidatedPiece};
+pub use self::core::UtfError;
+pub use self::core::{
+    decode_word, next_code_point, validate_next, validate_next_counted, validate_next_fallible,
+    validate_next_with, validate_prev, ValidationProfile,
+};
+pub use self::decode_utf8::{decode_utf8, AsByte, DecodeUtf8, TryAsByte};
+pub use self::decoder::{DecodeStep, Utf8Decoder};
+pub use self::dfa::validate_dfa;
+pub use self::encode::{encode_utf8, encode_utf8_iter, EncodeUtf8};
+pub use self::error::{DecodeError, Utf8ErrorAt};
+#[cfg(feature = "alloc")]
+pub use self::escape::{
+    escape_invalid, parse_hex_escaped, validate_hex_escaped, EscapeErrorKind, EscapedError,
+    ParseEscapeError,
+};
+#[cfg(feature = "tokio")]
+pub use self::file::{
+    validate_file, validate_file_with_capacity, FileError, FileReport, BLOCKING_THRESHOLD,
+};
+#[cfg(feature = "futures")]
+pub use self::futures_reader::FuturesUtf8Reader;
+#[cfg(feature = "rand")]
+pub use self::generate::{generate, generate_with, GenProfile};
+#[cfg(feature = "http")]
+pub use self::http::{Utf8BodyLimit, Utf8BodyRejection, ValidUtf8Body, DEFAULT_BODY_LIMIT};
+pub use self::invalid_ranges::{invalid_ranges, InvalidRanges};
+#[cfg(feature = "std")]
+pub use self::io::{
+    validate_reader, validate_reader_with_capacity, BufUtf8Reader, StreamError, Utf8Reader,
+    Utf8Writer,
+};
+#[cfg(feature = "alloc")]
+pub use self::lossy::{decode_lossy, lossy_decoded_len, repair_in_place, to_string_lossy, Lossy};
+#[cfg(feature = "rayon")]
+pub use self::parallel::to_string_lossy_parallel;
+#[cfg(feature = "alloc")]
+pub use self::policy::{BuildError, Policy, PolicyError, PolicyStream, ValidatorBuilder};
+pub use self::rchars::{rchars, RChars};
+pub use self::runs::{ascii_runs, Run, Runs};
+pub use self::search::find_str;
+#[cfg(feature = "alloc")]
+pub use self::stats::{analyze, TextStats};
+pub use self::stream::{ErrorDirective, InvalidRegion, Utf8Validator};
+#[cfg(feature = "alloc")]
+pub use self::string_ext::PushUtf8;
+#[cfg(feature = "std")]
+pub use self::utf16::Utf16Reader;
+pub use self::utf16::{
+    utf16_chars, validate_next_utf16, validate_utf16be, validate_utf16le, AsUnit, Endian,
+    Utf16Chars, Utf16CharsLossy, Utf16Error, Utf16ErrorAt,
+};
+pub use self::utf8_chunks::{utf8_chunks, Utf8Chunk, Utf8Chunks};
+pub use self::valid_str::ValidStr;
+#[cfg(feature = "alloc")]
+pub use self::valid_string::{EditError, ValidString};
+pub use self::validate::{
+    incomplete_suffix_len, split_valid, truncate_to_boundary, validate, validate_bulk,
+    validate_const, validate_from, validate_range, validate_with_hint, TextHint,
+};
@@ -0,0 +1,293 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::utf8_chunks::utf8_chunks;
+use crate::validate::validate;
+
+pub(crate) const REPLACEMENT: &str = "\u{fffd}";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Replacement<'a> {
+    Char([u8; 4], u8),
+    Str(&'a str),
+}
+
+/// Lossy decoding with a configurable replacement for invalid sequences.
+///
+/// The default replacement is U+FFFD; an empty replacement drops invalid
+/// sequences.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Lossy<'a> {
+    replacement: Replacement<'a>,
+}
+
+impl Default for Lossy<'static> {
+    fn default() -> Self {
+        Lossy::new()
+    }
+}
+
+impl Lossy<'static> {
+    pub const fn new() -> Self {
+        Lossy {
+            replacement: Replacement::Str(REPLACEMENT),
+        }
+    }
+}
+
+impl<'a> Lossy<'a> {
+    pub fn with_replacement(self, replacement: char) -> Lossy<'a> {
+        let mut buf = [0; 4];
+        let len = replacement.encode_utf8(&mut buf).len() as u8;
+        Lossy {
+            replacement: Replacement::Char(buf, len),
+        }
+    }
+
+    /// Replaces each invalid sequence with all of `replacement`.
+    pub fn with_replacement_str(self, replacement: &str) -> Lossy<'_> {
+        Lossy {
+            replacement: Replacement::Str(replacement),
+        }
+    }
+
+    #[inline]
+    pub fn replacement(&self) -> &str {
+        match &self.replacement {
+            // SAFETY: the bytes were encoded from a `char`.
+            Replacement::Char(buf, len) => unsafe {
+                core::str::from_utf8_unchecked(&buf[..*len as usize])
+            },
+            Replacement::Str(replacement) => replacement,
+        }
+    }
+
+    /// Exact length in bytes of [`decode`](Self::decode)'s output for `bytes`.
+    pub fn decoded_len(&self, bytes: &[u8]) -> usize {
+        let replacement = self.replacement().len();
+        utf8_chunks(bytes)
+            .map(|chunk| {
+                if chunk.invalid().is_empty() {
+                    chunk.valid().len()
+                } else {
+                    chunk.valid().len() + replacement
+                }
+            })
+            .sum()
+    }
+
+    /// Decodes `bytes`, replacing each maximal ill-formed subsequence.
+    ///
+    /// The output is allocated exactly once.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let mut out = String::with_capacity(self.decoded_len(bytes));
+        self.push_str(&mut out, bytes);
+        out
+    }
+
+    /// [`decode`](Self::decode), borrowing `bytes` when they are already
+    /// valid.
+    pub fn decode_cow<'b>(&self, bytes: &'b [u8]) -> Cow<'b, str> {
+        match validate(bytes) {
+            // SAFETY: `bytes` was just validated.
+            Ok(()) => Cow::Borrowed(unsafe { core::str::from_utf8_unchecked(bytes) }),
+            Err(_) => Cow::Owned(self.decode(bytes)),
+        }
+    }
+
+    /// Appends the decoded `bytes` to `out`, returning the number of
+    /// replacements.
+    pub fn push_str(&self, out: &mut String, bytes: &[u8]) -> usize {
+        let replacement = self.replacement();
+        let mut replacements = 0;
+        for chunk in utf8_chunks(bytes) {
+            out.push_str(chunk.valid());
+            if !chunk.invalid().is_empty() {
+                out.push_str(replacement);
+                replacements += 1;
+            }
+        }
+        replacements
+    }
+
+    /// Replaces each maximal ill-formed subsequence of `buf`, returning the
+    /// number of replacements.
+    ///
+    /// Does not allocate when `buf` is already valid.
+    pub fn repair_in_place(&self, buf: &mut Vec<u8>) -> usize {
+        let replacements = utf8_chunks(buf)
+            .filter(|chunk| !chunk.invalid().is_empty())
+            .count();
+        if replacements != 0 {
+            *buf = self.decode(buf).into_bytes();
+        }
+        replacements
+    }
+}
+
+/// Exact length in bytes of [`to_string_lossy`]'s output for `bytes`.
+pub fn lossy_decoded_len(bytes: &[u8]) -> usize {
+    Lossy::new().decoded_len(bytes)
+}
+
+/// Decodes `bytes`, replacing each maximal ill-formed subsequence with U+FFFD.
+///
+/// The output is allocated exactly once.
+pub fn to_string_lossy(bytes: &[u8]) -> String {
+    Lossy::new().decode(bytes)
+}
+
+/// Like [`String::from_utf8_lossy`]: borrows `bytes` if they are valid,
+/// and otherwise replaces each maximal ill-formed subsequence with U+FFFD.
+pub fn decode_lossy(bytes: &[u8]) -> Cow<'_, str> {
+    Lossy::new().decode_cow(bytes)
+}
+
+/// Replaces each maximal ill-formed subsequence of `buf` with U+FFFD,
+/// returning the number of replacements.
+///
+/// Does not allocate when `buf` is already valid.
+pub fn repair_in_place(buf: &mut Vec<u8>) -> usize {
+    Lossy::new().repair_in_place(buf)
+}
+
+#[cfg(test)]
+mod test_lossy {
+    use super::*;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    #[test]
+    fn test_to_string_lossy_matches_std() {
+        let inputs: &[&[u8]] = &[
+            b"",
+            b"plain",
+            "ขฃค😀".as_bytes(),
+            &[0xff, 0xff],
+            &[0xe0, 0x80],
+            &[0x61, 0xf0, 0x9f, 0x98],
+            &[0xf0, 0x9f, 0x98, 0x61],
+            &[0xed, 0xa0, 0x80, 0x61],
+            &[0xc0, 0x80, 0xc1],
+            &[0x80, 0x80, 0x80],
+        ];
+        for input in inputs {
+            assert_eq!(to_string_lossy(input), String::from_utf8_lossy(input));
+        }
+    }
+
+    #[test]
+    fn test_decode_lossy_matches_std() {
+        let inputs: &[&[u8]] = &[
+            b"",
+            "ขฃค😀".as_bytes(),
+            &[0xff, 0xff],
+            &[0xe0, 0x80],
+            &[0xf0, 0x9f, 0x98],
+            &[0x61, 0xf0, 0x9f, 0x98, 0x62, 0xf4, 0x8f, 0xbf],
+            &[0xf0, 0x80, 0x80, 0x80],
+            &[0xf4, 0x90, 0x80, 0x80],
+            &[0xed, 0xa0, 0x80, 0xed, 0xbf, 0xbf],
+            &[0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80],
+            &[0xc2, 0x41, 0xe2, 0x82, 0x41],
+        ];
+        for input in inputs {
+            let decoded = decode_lossy(input);
+            let expected = String::from_utf8_lossy(input);
+            assert_eq!(decoded, expected, "{:x?}", input);
+            assert_eq!(
+                matches!(decoded, Cow::Borrowed(_)),
+                matches!(expected, Cow::Borrowed(_))
+            );
+        }
+        let mut rng = XorShift::new(255);
+        for _ in 0..500 {
+            let fragments = rng.below(30);
+            let input = random_corrupt(&mut rng, fragments);
+            assert_eq!(decode_lossy(&input), String::from_utf8_lossy(&input));
+        }
+        assert_eq!(
+            Lossy::new().with_replacement('?').decode_cow(b"a\xffb"),
+            "a?b"
+        );
+    }
+
+    #[test]
+    fn test_lossy_decoded_len_property() {
+        let mut rng = XorShift::new(203);
+        for _ in 0..2000 {
+            let fragments = rng.below(40);
+            let input = random_corrupt(&mut rng, fragments);
+            let decoded = to_string_lossy(&input);
+            assert_eq!(decoded, String::from_utf8_lossy(&input), "{:x?}", input);
+            assert_eq!(lossy_decoded_len(&input), decoded.len(), "{:x?}", input);
+            // A single, exactly sized allocation.
+            assert_eq!(decoded.capacity(), decoded.len());
+        }
+    }
+
+    #[test]
+    fn test_repair_in_place() {
+        let mut valid = "ok €".as_bytes().to_vec();
+        let ptr = valid.as_ptr();
+        assert_eq!(repair_in_place(&mut valid), 0);
+        assert_eq!(valid.as_ptr(), ptr);
+
+        let mut buf = vec![0x61, 0xff, 0xe0, 0x80, 0x62, 0xf0, 0x9f];
+        assert_eq!(repair_in_place(&mut buf), 4);
+        assert_eq!(buf, "a\u{fffd}\u{fffd}\u{fffd}b\u{fffd}".as_bytes());
+    }
+
+    #[test]
+    fn test_skip_len_agrees_with_lossy() {
+        let mut rng = XorShift::new(212);
+        for _ in 0..2000 {
+            let fragments = rng.below(40);
+            let input = random_corrupt(&mut rng, fragments);
+            let mut kept = Vec::new();
+            let mut rest = &input[..];
+            while let Err(err) = crate::validate::validate(rest) {
+                assert!((1..=3).contains(&err.skip_len()), "{:x?}", input);
+                kept.extend_from_slice(&rest[..err.offset()]);
+                rest = &rest[err.offset() + err.skip_len()..];
+            }
+            kept.extend_from_slice(rest);
+            // Not `replace(REPLACEMENT, "")`: the input may contain U+FFFD.
+            let expected: String = utf8_chunks(&input).map(|chunk| chunk.valid()).collect();
+            assert_eq!(kept, expected.as_bytes(), "{:x?}", input);
+        }
+    }
+
+    #[test]
+    fn test_lossy_custom_replacement() {
+        let input = [0x61, 0xff, 0xe0, 0x80, 0x62, 0xf0, 0x9f];
+        let cases = [
+            (Lossy::new().with_replacement('?'), "a???b?"),
+            (
+                Lossy::new().with_replacement('\u{2370}'),
+                "a\u{2370}\u{2370}\u{2370}b\u{2370}",
+            ),
+            (
+                Lossy::new().with_replacement_str("<bad>"),
+                "a<bad><bad><bad>b<bad>",
+            ),
+            // An empty replacement drops the invalid sequences.
+            (Lossy::new().with_replacement_str(""), "ab"),
+        ];
+        for (lossy, expected) in cases.iter() {
+            let decoded = lossy.decode(&input);
+            assert_eq!(decoded, *expected);
+            assert_eq!(lossy.decoded_len(&input), expected.len());
+            assert_eq!(decoded.capacity(), decoded.len());
+
+            let mut buf = input.to_vec();
+            assert_eq!(lossy.repair_in_place(&mut buf), 4);
+            assert_eq!(buf, expected.as_bytes());
+
+            let mut out = String::from(">");
+            assert_eq!(lossy.push_str(&mut out, &input), 4);
+            assert_eq!(out, format!(">{}", expected));
+        }
+        assert_eq!(Lossy::default().replacement(), REPLACEMENT);
+    }
+}
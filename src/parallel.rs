@@ -0,0 +1,90 @@
+use rayon::prelude::*;
+
+use crate::core::is_continuation;
+use crate::lossy::to_string_lossy;
+
+/// Moves `pos` back to a position where sequential lossy decoding starts a
+/// new character or a new ill-formed subsequence.
+///
+/// Every byte that is not a continuation byte starts one, and so does a
+/// continuation byte with no lead byte in the three bytes before it.
+#[inline]
+fn safe_split(bytes: &[u8], pos: usize) -> usize {
+    if pos >= bytes.len() || !is_continuation(bytes[pos]) {
+        return pos;
+    }
+    (pos.saturating_sub(3)..pos)
+        .rev()
+        .find(|&lead| !is_continuation(bytes[lead]))
+        .unwrap_or(pos)
+}
+
+/// Like [`to_string_lossy`], but converts chunks of about `chunk_size`
+/// bytes in parallel.
+///
+/// Chunks are split only where sequential decoding would start a new
+/// character or ill-formed subsequence, so the output is the same.
+pub fn to_string_lossy_parallel(bytes: &[u8], chunk_size: usize) -> String {
+    let chunk_size = chunk_size.max(4);
+    let mut chunks = Vec::with_capacity(bytes.len() / chunk_size + 1);
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = match start.checked_add(chunk_size) {
+            Some(end) if end < bytes.len() => safe_split(bytes, end),
+            _ => bytes.len(),
+        };
+        chunks.push(&bytes[start..end]);
+        start = end;
+    }
+    let parts: Vec<String> = chunks
+        .par_iter()
+        .map(|chunk| to_string_lossy(chunk))
+        .collect();
+    let mut out = String::with_capacity(parts.iter().map(String::len).sum());
+    for part in &parts {
+        out.push_str(part);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test_parallel {
+    use super::*;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    #[test]
+    fn test_lossy_parallel_matches_sequential() {
+        let mut rng = XorShift::new(235);
+        for _ in 0..3000 {
+            let fragments = rng.below(60);
+            let input = random_corrupt(&mut rng, fragments);
+            let chunk_size = rng.below(20);
+            assert_eq!(
+                to_string_lossy_parallel(&input, chunk_size),
+                to_string_lossy(&input),
+                "{:x?} / {}",
+                input,
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_lossy_parallel_boundaries() {
+        // Truncated sequences, stray continuation runs and overlongs at every
+        // shift against the chunk size.
+        let tail: &[u8] = b"\xf0\x9f\x98a\x80\x80\x80\x80\x80\xe0\x80\x80\xed\xa0\x80\xf4\x90\x80\x80\xf0\x9f\x98\x80";
+        for pad in 0..8 {
+            let mut input = vec![b'x'; pad];
+            input.extend_from_slice(tail);
+            for chunk_size in 1..12 {
+                assert_eq!(
+                    to_string_lossy_parallel(&input, chunk_size),
+                    to_string_lossy(&input)
+                );
+            }
+        }
+        assert_eq!(to_string_lossy_parallel(b"", 10), "");
+        assert_eq!(to_string_lossy_parallel(b"abc", usize::MAX), "abc");
+    }
+}
@@ -0,0 +1,898 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use crate::core::decode_step;
+use crate::decoder::Utf8Decoder;
+use crate::error::Utf8ErrorAt;
+
+const BOM: u32 = 0xfeff;
+
+/// Whether `code_point` is in the private use area of the BMP or in one of
+/// the two supplementary private use planes.
+#[inline]
+const fn is_private_use(code_point: u32) -> bool {
+    matches!(code_point, 0xe000..=0xf8ff | 0xf0000..=0xffffd | 0x100000..=0x10fffd)
+}
+
+/// A set of code points kept as sorted, disjoint, non-adjacent ranges.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct RangeSet {
+    ranges: Vec<(u32, u32)>,
+}
+
+impl RangeSet {
+    /// Adds `ranges` and merges overlapping or adjacent ones; empty ranges
+    /// are dropped.
+    fn extend<I: IntoIterator<Item = RangeInclusive<u32>>>(&mut self, ranges: I) {
+        self.ranges.extend(
+            ranges
+                .into_iter()
+                .map(RangeInclusive::into_inner)
+                .filter(|(start, end)| start <= end),
+        );
+        self.ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(self.ranges.len());
+        for &(start, end) in self.ranges.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        self.ranges = merged;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The range containing `code_point`, if any.
+    fn find(&self, code_point: u32) -> Option<(u32, u32)> {
+        let i = self.ranges.partition_point(|&(_, end)| end < code_point);
+        self.ranges
+            .get(i)
+            .copied()
+            .filter(|&(start, _)| start <= code_point)
+    }
+}
+
+/// Error of [`ValidatorBuilder::build`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// [`ValidatorBuilder::allow_only_ranges`] was given no non-empty range,
+    /// so no character at all would pass.
+    EmptyAllowlist,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::EmptyAllowlist => f.write_str("the allowlist of code points is empty"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for BuildError {}
+
+/// Error of a [`Policy`]: invalid UTF-8, or a valid character the policy
+/// rejects.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PolicyError {
+    Utf8(Utf8ErrorAt),
+    /// U+FEFF anywhere but at the very start of the input.
+    InteriorBom {
+        offset: u64,
+    },
+    /// A private-use character.
+    PrivateUse {
+        offset: u64,
+        code_point: u32,
+    },
+    /// A character of planes 4 to 13, none of which has assigned characters.
+    UnassignedPlane {
+        offset: u64,
+        code_point: u32,
+    },
+    /// A character above the configured maximum.
+    AboveMaxCodePoint {
+        offset: u64,
+        code_point: u32,
+    },
+    /// A character outside every range of the allowlist.
+    NotAllowed {
+        offset: u64,
+        code_point: u32,
+    },
+    /// A character of the denylist; `range` is the matching range, after
+    /// overlapping and adjacent ranges were merged.
+    Denied {
+        offset: u64,
+        code_point: u32,
+        range: (u32, u32),
+    },
+}
+
+impl PolicyError {
+    /// Byte offset of the offending sequence or character, from the start
+    /// of the input or stream; `u64` as in [`Utf8ErrorAt::stream_offset`].
+    pub fn offset(&self) -> u64 {
+        match *self {
+            PolicyError::Utf8(err) => err.stream_offset(),
+            PolicyError::InteriorBom { offset }
+            | PolicyError::PrivateUse { offset, .. }
+            | PolicyError::UnassignedPlane { offset, .. }
+            | PolicyError::AboveMaxCodePoint { offset, .. }
+            | PolicyError::NotAllowed { offset, .. }
+            | PolicyError::Denied { offset, .. } => offset,
+        }
+    }
+
+    /// Stable numeric code for FFI and logs: that of
+    /// [`UtfError::code`](crate::UtfError::code) for invalid UTF-8, and one
+    /// of these, which are frozen, for a rejected character:
+    ///
+    /// | code | variant |
+    /// |------|---------|
+    /// | 100 | `InteriorBom` |
+    /// | 101 | `PrivateUse` |
+    /// | 102 | `UnassignedPlane` |
+    /// | 103 | `AboveMaxCodePoint` |
+    /// | 104 | `NotAllowed` |
+    /// | 105 | `Denied` |
+    pub fn code(&self) -> u16 {
+        match self {
+            PolicyError::Utf8(err) => err.code(),
+            PolicyError::InteriorBom { .. } => 100,
+            PolicyError::PrivateUse { .. } => 101,
+            PolicyError::UnassignedPlane { .. } => 102,
+            PolicyError::AboveMaxCodePoint { .. } => 103,
+            PolicyError::NotAllowed { .. } => 104,
+            PolicyError::Denied { .. } => 105,
+        }
+    }
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::Utf8(err) => fmt::Display::fmt(err, f),
+            PolicyError::InteriorBom { offset } => write!(f, "byte order mark at byte {}", offset),
+            PolicyError::PrivateUse { offset, code_point } => {
+                write!(
+                    f,
+                    "private-use character U+{:04X} at byte {}",
+                    code_point, offset
+                )
+            }
+            PolicyError::UnassignedPlane { offset, code_point } => write!(
+                f,
+                "character U+{:04X} of an unassigned plane at byte {}",
+                code_point, offset
+            ),
+            PolicyError::AboveMaxCodePoint { offset, code_point } => write!(
+                f,
+                "character U+{:04X} above the maximum at byte {}",
+                code_point, offset
+            ),
+            PolicyError::NotAllowed { offset, code_point } => write!(
+                f,
+                "character U+{:04X} outside the allowed ranges at byte {}",
+                code_point, offset
+            ),
+            PolicyError::Denied {
+                offset,
+                code_point,
+                range: (start, end),
+            } => write!(
+                f,
+                "character U+{:04X} of the denied range U+{:04X}..=U+{:04X} at byte {}",
+                code_point, start, end, offset
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for PolicyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PolicyError::Utf8(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Utf8ErrorAt> for PolicyError {
+    fn from(err: Utf8ErrorAt) -> Self {
+        PolicyError::Utf8(err)
+    }
+}
+
+/// Builds a [`Policy`]: UTF-8 validation plus rules about which valid
+/// characters are acceptable.
+#[derive(Clone, Debug, Default)]
+pub struct ValidatorBuilder {
+    policy: Policy,
+}
+
+impl ValidatorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects U+FEFF at any offset but 0 of the input or stream.
+    pub fn reject_interior_bom(mut self, reject: bool) -> Self {
+        self.policy.reject_interior_bom = reject;
+        self
+    }
+
+    /// Rejects characters above `max`.
+    pub fn max_code_point(mut self, max: u32) -> Self {
+        self.policy.max_code_point = max;
+        self
+    }
+
+    /// Rejects characters of planes 4 to 13 (U+40000..U+DFFFF), which have
+    /// no assigned characters.
+    ///
+    /// This is a check by plane, not by character: unassigned characters of
+    /// the other planes still pass.
+    pub fn reject_unassigned_planes(mut self, reject: bool) -> Self {
+        self.policy.reject_unassigned_planes = reject;
+        self
+    }
+
+    /// Rejects private-use characters: U+E000..U+F8FF, U+F0000..U+FFFFD and
+    /// U+100000..U+10FFFD.
+    pub fn reject_private_use(mut self, reject: bool) -> Self {
+        self.policy.reject_private_use = reject;
+        self
+    }
+
+    /// Rejects characters in any of `ranges`. Calling this again adds to
+    /// the ranges.
+    pub fn deny_ranges<I: IntoIterator<Item = RangeInclusive<u32>>>(mut self, ranges: I) -> Self {
+        self.policy.denied.extend(ranges);
+        self
+    }
+
+    /// Rejects characters outside all of `ranges`. Calling this again adds
+    /// to the ranges.
+    pub fn allow_only_ranges<I: IntoIterator<Item = RangeInclusive<u32>>>(
+        mut self,
+        ranges: I,
+    ) -> Self {
+        self.policy
+            .allowed
+            .get_or_insert_with(RangeSet::default)
+            .extend(ranges);
+        self
+    }
+
+    /// Fails if an allowlist was given but has no non-empty range.
+    pub fn build(self) -> Result<Policy, BuildError> {
+        match self.policy.allowed {
+            Some(ref allowed) if allowed.is_empty() => Err(BuildError::EmptyAllowlist),
+            _ => Ok(self.policy),
+        }
+    }
+}
+
+/// Validation rules built by [`ValidatorBuilder`].
+///
+/// A character breaking several rules is reported for the first of: the
+/// maximum code point, the allowlist, the denylist, unassigned planes,
+/// private use, interior BOM.
+#[derive(Clone, Debug)]
+pub struct Policy {
+    max_code_point: u32,
+    reject_unassigned_planes: bool,
+    reject_private_use: bool,
+    reject_interior_bom: bool,
+    allowed: Option<RangeSet>,
+    denied: RangeSet,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            max_code_point: char::MAX as u32,
+            reject_unassigned_planes: false,
+            reject_private_use: false,
+            reject_interior_bom: false,
+            allowed: None,
+            denied: RangeSet::default(),
+        }
+    }
+}
+
+impl Policy {
+    #[inline]
+    fn is_plain(&self) -> bool {
+        self.max_code_point >= char::MAX as u32
+            && self.allowed.is_none()
+            && self.denied.is_empty()
+            && !(self.reject_unassigned_planes
+                || self.reject_private_use
+                || self.reject_interior_bom)
+    }
+
+    /// Checks the character `code_point` found at stream offset `offset`.
+    #[inline]
+    fn check(&self, offset: u64, code_point: u32) -> Result<(), PolicyError> {
+        if code_point > self.max_code_point {
+            return Err(PolicyError::AboveMaxCodePoint { offset, code_point });
+        }
+        if let Some(ref allowed) = self.allowed {
+            if allowed.find(code_point).is_none() {
+                return Err(PolicyError::NotAllowed { offset, code_point });
+            }
+        }
+        if let Some(range) = self.denied.find(code_point) {
+            return Err(PolicyError::Denied {
+                offset,
+                code_point,
+                range,
+            });
+        }
+        if self.reject_unassigned_planes && (0x40000..=0xdffff).contains(&code_point) {
+            return Err(PolicyError::UnassignedPlane { offset, code_point });
+        }
+        if self.reject_private_use && is_private_use(code_point) {
+            return Err(PolicyError::PrivateUse { offset, code_point });
+        }
+        if self.reject_interior_bom && code_point == BOM && offset != 0 {
+            return Err(PolicyError::InteriorBom { offset });
+        }
+        Ok(())
+    }
+
+    /// Checks all of `text`, which starts at stream offset `base`.
+    fn check_str(&self, base: u64, text: &str) -> Result<(), PolicyError> {
+        if self.is_plain() {
+            return Ok(());
+        }
+        text.char_indices()
+            .try_for_each(|(i, c)| self.check(base.saturating_add(i as u64), c as u32))
+    }
+
+    /// Calls `found` with every problem of `bytes` in order, until it returns
+    /// `false`.
+    fn each_error<F: FnMut(PolicyError) -> bool>(&self, bytes: &[u8], mut found: F) {
+        let mut pos = 0;
+        while pos < bytes.len() {
+            match decode_step(&bytes[pos..]) {
+                Ok((code_point, length)) => {
+                    if let Err(err) = self.check(pos as u64, code_point) {
+                        if !found(err) {
+                            return;
+                        }
+                    }
+                    pos += length;
+                }
+                Err(err) => {
+                    if !found(err.at(pos).into()) {
+                        return;
+                    }
+                    pos += err.skip_len();
+                }
+            }
+        }
+    }
+
+    /// Checks that `bytes` is valid UTF-8 that follows the policy.
+    pub fn validate(&self, bytes: &[u8]) -> Result<(), PolicyError> {
+        if self.is_plain() {
+            return crate::validate::validate(bytes).map_err(PolicyError::from);
+        }
+        let mut first = None;
+        self.each_error(bytes, |err| {
+            first = Some(err);
+            false
+        });
+        first.map_or(Ok(()), Err)
+    }
+
+    /// Checks `bytes` as if it started at offset `base` of a larger text,
+    /// returning it as text.
+    pub(crate) fn validate_at<'a>(
+        &self,
+        bytes: &'a [u8],
+        base: usize,
+    ) -> Result<&'a str, PolicyError> {
+        crate::validate::validate(bytes).map_err(|err| PolicyError::from(err.at(base)))?;
+        debug_assert!(core::str::from_utf8(bytes).is_ok());
+        // SAFETY: `bytes` was just validated.
+        let text = unsafe { core::str::from_utf8_unchecked(bytes) };
+        self.check_str(base as u64, text)?;
+        Ok(text)
+    }
+
+    /// Checks the character `c` found at offset `offset`.
+    #[inline]
+    pub(crate) fn check_char(&self, offset: usize, c: char) -> Result<(), PolicyError> {
+        self.check(offset as u64, c as u32)
+    }
+
+    /// Lists every problem of `bytes`, invalid sequences and rejected
+    /// characters alike, in order.
+    pub fn scan(&self, bytes: &[u8]) -> Vec<PolicyError> {
+        let mut errors = Vec::new();
+        self.each_error(bytes, |err| {
+            errors.push(err);
+            true
+        });
+        errors
+    }
+
+    /// Starts validating a stream pushed in chunks; offsets count from the
+    /// start of the stream.
+    pub fn stream(&self) -> PolicyStream<'_> {
+        PolicyStream {
+            policy: self,
+            decoder: Utf8Decoder::new(),
+            offset: 0,
+            error: None,
+        }
+    }
+}
+
+/// Incremental validation against a [`Policy`], created by
+/// [`Policy::stream`].
+#[derive(Clone, Debug)]
+pub struct PolicyStream<'p> {
+    policy: &'p Policy,
+    decoder: Utf8Decoder,
+    /// Stream offset of the next character to check.
+    offset: u64,
+    error: Option<PolicyError>,
+}
+
+impl PolicyStream<'_> {
+    /// Validates the next chunk of the stream.
+    ///
+    /// Once an error is returned, every later call returns it again.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), PolicyError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        let result = self.push_inner(chunk);
+        if let Err(err) = result {
+            self.error = Some(err);
+        }
+        result
+    }
+
+    fn push_inner(&mut self, chunk: &[u8]) -> Result<(), PolicyError> {
+        let step = self.decoder.decode(chunk);
+        if let Some(c) = step.stitched {
+            self.policy.check(self.offset, c as u32)?;
+            self.offset = self.offset.saturating_add(c.len_utf8() as u64);
+        }
+        self.policy.check_str(self.offset, step.text)?;
+        self.offset = self.offset.saturating_add(step.text.len() as u64);
+        match step.error {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the stream did not end in the middle of a character.
+    pub fn finish(self) -> Result<(), PolicyError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.decoder.finish().map_err(PolicyError::from)
+    }
+}
+
+#[cfg(test)]
+mod test_policy {
+    use super::*;
+    use crate::core::UtfError;
+
+    fn no_interior_bom() -> Policy {
+        ValidatorBuilder::new()
+            .reject_interior_bom(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_interior_bom() {
+        let text = "\u{feff}lead is fine, \u{feff}this is not".as_bytes();
+        assert_eq!(
+            ValidatorBuilder::new().build().unwrap().validate(text),
+            Ok(())
+        );
+        let policy = no_interior_bom();
+        assert_eq!(
+            policy.validate(text),
+            Err(PolicyError::InteriorBom { offset: 17 })
+        );
+        assert_eq!(policy.validate("\u{feff}ok".as_bytes()), Ok(()));
+
+        let mut twice = text.to_vec();
+        twice.extend_from_slice(b"\xff\xef\xbb\xbf");
+        assert_eq!(
+            policy.scan(&twice),
+            [
+                PolicyError::InteriorBom { offset: 17 },
+                PolicyError::Utf8(Utf8ErrorAt::new(text.len(), UtfError::InvalidLead, 1)),
+                PolicyError::InteriorBom {
+                    offset: text.len() as u64 + 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_policy_error_codes() {
+        let errors = [
+            PolicyError::InteriorBom { offset: 0 },
+            PolicyError::PrivateUse {
+                offset: 0,
+                code_point: 0xe000,
+            },
+            PolicyError::UnassignedPlane {
+                offset: 0,
+                code_point: 0x40000,
+            },
+            PolicyError::AboveMaxCodePoint {
+                offset: 0,
+                code_point: 0x80,
+            },
+            PolicyError::NotAllowed {
+                offset: 0,
+                code_point: 0x80,
+            },
+            PolicyError::Denied {
+                offset: 0,
+                code_point: 0x80,
+                range: (0x80, 0x80),
+            },
+        ];
+        let codes: Vec<u16> = errors.iter().map(PolicyError::code).collect();
+        assert_eq!(codes, [100, 101, 102, 103, 104, 105]);
+        let utf8 = PolicyError::Utf8(Utf8ErrorAt::new(3, UtfError::InvalidCodePoint, 1));
+        assert_eq!(utf8.code(), UtfError::InvalidCodePoint.code());
+        for kind in UtfError::ALL.iter() {
+            assert!(kind.code() < 100);
+        }
+    }
+
+    #[test]
+    fn test_interior_bom_stream_offsets() {
+        let policy = no_interior_bom();
+        // The BOM starts the second chunk but not the stream.
+        let mut stream = policy.stream();
+        assert_eq!(stream.push(b"\xef\xbb\xbfabc"), Ok(()));
+        let err = stream.push("\u{feff}def".as_bytes()).unwrap_err();
+        assert_eq!(err, PolicyError::InteriorBom { offset: 6 });
+        assert_eq!(stream.finish(), Err(err));
+
+        // Split across chunks, at offset 0 and later.
+        let mut stream = policy.stream();
+        assert_eq!(stream.push(b"\xef"), Ok(()));
+        assert_eq!(stream.push(b"\xbb\xbfa\xef\xbb"), Ok(()));
+        let err = stream.push(b"\xbf").unwrap_err();
+        assert_eq!(err.offset(), 4);
+
+        let mut stream = policy.stream();
+        assert_eq!(stream.push(b"a\xf0\x9f"), Ok(()));
+        let err = stream.push(b"x").unwrap_err();
+        assert!(matches!(err, PolicyError::Utf8(_)));
+        assert_eq!(err.offset(), 1);
+    }
+
+    #[test]
+    fn test_stream_offsets_past_4_gib() {
+        let policy = no_interior_bom();
+        let start = u64::from(u32::MAX) - 2;
+        let mut stream = policy.stream();
+        stream.offset = start;
+        stream.decoder = Utf8Decoder::with_consumed(start);
+        assert_eq!(stream.push(b"ab\xef\xbb"), Ok(()));
+        let err = stream.push(b"\xbf").unwrap_err();
+        assert_eq!(err, PolicyError::InteriorBom { offset: start + 2 });
+
+        let plain = ValidatorBuilder::new().build().unwrap();
+        let mut stream = plain.stream();
+        stream.offset = start;
+        stream.decoder = Utf8Decoder::with_consumed(start);
+        assert_eq!(stream.push("a€b".as_bytes()), Ok(()));
+        let err = stream.push(b"\xff").unwrap_err();
+        assert_eq!(err.offset(), start + 5);
+        assert_eq!(stream.finish(), Err(err));
+    }
+
+    #[test]
+    fn test_private_use() {
+        let policy = ValidatorBuilder::new()
+            .reject_private_use(true)
+            .build()
+            .unwrap();
+        let rejected = |c: char| {
+            let text = format!("ab{}", c);
+            let err = policy.validate(text.as_bytes()).err();
+            assert_eq!(
+                ValidatorBuilder::new()
+                    .build()
+                    .unwrap()
+                    .validate(text.as_bytes()),
+                Ok(())
+            );
+            err == Some(PolicyError::PrivateUse {
+                offset: 2,
+                code_point: c as u32,
+            })
+        };
+        assert!(rejected('\u{e000}'));
+        assert!(rejected('\u{f8ff}'));
+        assert!(rejected('\u{f0000}'));
+        assert!(rejected('\u{10fffd}'));
+        // CJK Compatibility Ideographs start right after the BMP area.
+        assert!(!rejected('\u{f900}'));
+        assert!(!rejected('\u{effff}'));
+        assert!(!rejected('\u{10ffff}'));
+
+        let text = "\u{e000}x\u{f8ff}\u{f900}\u{10fffd}".as_bytes();
+        let offsets: Vec<_> = policy.scan(text).iter().map(PolicyError::offset).collect();
+        assert_eq!(offsets, [0, 4, 10]);
+    }
+
+    #[test]
+    fn test_unassigned_planes() {
+        let policy = ValidatorBuilder::new()
+            .reject_unassigned_planes(true)
+            .build()
+            .unwrap();
+        let check = |code_point: u32| {
+            let text = char::from_u32(code_point).unwrap().to_string();
+            policy.validate(text.as_bytes())
+        };
+        assert_eq!(check(0x3ffff), Ok(()));
+        assert_eq!(
+            check(0x40000),
+            Err(PolicyError::UnassignedPlane {
+                offset: 0,
+                code_point: 0x40000
+            })
+        );
+        assert_eq!(
+            check(0xdffff),
+            Err(PolicyError::UnassignedPlane {
+                offset: 0,
+                code_point: 0xdffff
+            })
+        );
+        assert_eq!(check(0xe0000), Ok(()));
+    }
+
+    #[test]
+    fn test_unassigned_planes_with_max_code_point() {
+        let text = "\u{3ffff}\u{40000}\u{e0001}".as_bytes();
+        // Both rules apply to U+40000; the maximum is reported.
+        let policy = ValidatorBuilder::new()
+            .reject_unassigned_planes(true)
+            .max_code_point(0x3ffff)
+            .build()
+            .unwrap();
+        assert_eq!(
+            policy.scan(text),
+            [
+                PolicyError::AboveMaxCodePoint {
+                    offset: 4,
+                    code_point: 0x40000
+                },
+                PolicyError::AboveMaxCodePoint {
+                    offset: 8,
+                    code_point: 0xe0001
+                },
+            ]
+        );
+        // A maximum above the planes leaves them to the plane rule.
+        let policy = ValidatorBuilder::new()
+            .max_code_point(0xe0000)
+            .reject_unassigned_planes(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            policy.scan(text),
+            [
+                PolicyError::UnassignedPlane {
+                    offset: 4,
+                    code_point: 0x40000
+                },
+                PolicyError::AboveMaxCodePoint {
+                    offset: 8,
+                    code_point: 0xe0001
+                },
+            ]
+        );
+    }
+
+    /// The emoji blocks, from Miscellaneous Symbols and Pictographs to
+    /// Symbols and Pictographs Extended-A, given out of order.
+    fn emoji_blocks() -> Vec<RangeInclusive<u32>> {
+        vec![
+            0x1f900..=0x1f9ff,
+            0x1f600..=0x1f64f,
+            0x1f300..=0x1f5ff,
+            0x1fa70..=0x1faff,
+            0x1f680..=0x1f6ff,
+        ]
+    }
+
+    #[test]
+    fn test_range_set_normalized() {
+        let mut set = RangeSet::default();
+        set.extend(emoji_blocks());
+        assert_eq!(
+            set.ranges,
+            [
+                (0x1f300, 0x1f64f),
+                (0x1f680, 0x1f6ff),
+                (0x1f900, 0x1f9ff),
+                (0x1fa70, 0x1faff)
+            ]
+        );
+        // Overlapping, nested, adjacent, repeated and empty ranges.
+        set.extend(vec![
+            0x1f6f0..=0x1f8ff,
+            0x1f700..=0x1f7ff,
+            RangeInclusive::new(5, 4),
+            0x1fb00..=0x1fb00,
+        ]);
+        set.extend(vec![0x1f300..=0x1f300, u32::MAX..=u32::MAX]);
+        assert_eq!(
+            set.ranges,
+            [
+                (0x1f300, 0x1f64f),
+                (0x1f680, 0x1f9ff),
+                (0x1fa70, 0x1fb00),
+                (u32::MAX, u32::MAX)
+            ]
+        );
+        assert_eq!(set.find(0x1f2ff), None);
+        assert_eq!(set.find(0x1f300), Some((0x1f300, 0x1f64f)));
+        assert_eq!(set.find(0x1f650), None);
+        assert_eq!(set.find(0x1f900), Some((0x1f680, 0x1f9ff)));
+        assert_eq!(set.find(0x1fa00), None);
+        assert_eq!(set.find(0x1fb00), Some((0x1fa70, 0x1fb00)));
+        assert_eq!(set.find(u32::MAX), Some((u32::MAX, u32::MAX)));
+    }
+
+    #[test]
+    fn test_deny_emoji_blocks() {
+        let policy = ValidatorBuilder::new()
+            .deny_ranges(emoji_blocks())
+            .build()
+            .unwrap();
+        assert_eq!(policy.validate("plain ✓ text €".as_bytes()), Ok(()));
+        let text = "a😀b🚀c🦀\u{1f650}🫠".as_bytes();
+        assert_eq!(
+            policy.scan(text),
+            [
+                PolicyError::Denied {
+                    offset: 1,
+                    code_point: 0x1f600,
+                    range: (0x1f300, 0x1f64f)
+                },
+                PolicyError::Denied {
+                    offset: 6,
+                    code_point: 0x1f680,
+                    range: (0x1f680, 0x1f6ff)
+                },
+                PolicyError::Denied {
+                    offset: 11,
+                    code_point: 0x1f980,
+                    range: (0x1f900, 0x1f9ff)
+                },
+                PolicyError::Denied {
+                    offset: 19,
+                    code_point: 0x1fae0,
+                    range: (0x1fa70, 0x1faff)
+                },
+            ]
+        );
+        let mut stream = policy.stream();
+        assert_eq!(stream.push(b"ab\xf0\x9f"), Ok(()));
+        assert_eq!(
+            stream.push(b"\x98\x80"),
+            Err(PolicyError::Denied {
+                offset: 2,
+                code_point: 0x1f600,
+                range: (0x1f300, 0x1f64f)
+            })
+        );
+    }
+
+    #[test]
+    fn test_allow_only_latin1() {
+        let policy = ValidatorBuilder::new()
+            .allow_only_ranges(vec![0..=0x7f])
+            .allow_only_ranges(vec![0x80..=0xff])
+            .build()
+            .unwrap();
+        assert_eq!(policy.validate("façade, déjà vu ÿ".as_bytes()), Ok(()));
+        assert_eq!(
+            policy.scan("ÿĀ€".as_bytes()),
+            [
+                PolicyError::NotAllowed {
+                    offset: 2,
+                    code_point: 0x100
+                },
+                PolicyError::NotAllowed {
+                    offset: 4,
+                    code_point: 0x20ac
+                },
+            ]
+        );
+        // Invalid UTF-8 is still reported as such.
+        assert!(matches!(
+            policy.validate(b"ab\xff"),
+            Err(PolicyError::Utf8(_))
+        ));
+    }
+
+    #[test]
+    fn test_empty_allowlist() {
+        let empty: Vec<RangeInclusive<u32>> = Vec::new();
+        assert_eq!(
+            ValidatorBuilder::new()
+                .allow_only_ranges(empty)
+                .build()
+                .err(),
+            Some(BuildError::EmptyAllowlist)
+        );
+        let inverted = vec![RangeInclusive::new(0x80, 0x7f)];
+        assert_eq!(
+            ValidatorBuilder::new()
+                .allow_only_ranges(inverted)
+                .build()
+                .err(),
+            Some(BuildError::EmptyAllowlist)
+        );
+    }
+
+    #[test]
+    fn test_ranges_compose_with_other_rules() {
+        let policy = ValidatorBuilder::new()
+            .allow_only_ranges(vec![0..=0xffff])
+            .deny_ranges(vec![0x20ac..=0x20ac])
+            .reject_private_use(true)
+            .reject_interior_bom(true)
+            .max_code_point(0xfffe)
+            .build()
+            .unwrap();
+        let text = "\u{feff}a€\u{e000}\u{feff}\u{ffff}😀".as_bytes();
+        assert_eq!(
+            policy.scan(text),
+            [
+                PolicyError::Denied {
+                    offset: 4,
+                    code_point: 0x20ac,
+                    range: (0x20ac, 0x20ac)
+                },
+                PolicyError::PrivateUse {
+                    offset: 7,
+                    code_point: 0xe000
+                },
+                PolicyError::InteriorBom { offset: 10 },
+                PolicyError::AboveMaxCodePoint {
+                    offset: 13,
+                    code_point: 0xffff
+                },
+                // Above the maximum as well as outside the allowlist.
+                PolicyError::AboveMaxCodePoint {
+                    offset: 16,
+                    code_point: 0x1f600
+                },
+            ]
+        );
+    }
+}
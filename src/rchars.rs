@@ -0,0 +1,138 @@
+use core::iter::FusedIterator;
+
+use crate::core::{decode_step, is_continuation, UtfError};
+use crate::error::Utf8ErrorAt;
+
+/// Iterator over the characters of a byte slice from the end towards the
+/// start, created by [`rchars`].
+///
+/// Each step looks at most 4 bytes back. After the first error the iterator
+/// is exhausted.
+#[derive(Clone, Debug)]
+pub struct RChars<'a> {
+    bytes: &'a [u8],
+}
+
+/// Decodes `bytes` backwards, without looking at more than the characters
+/// actually yielded.
+pub fn rchars(bytes: &[u8]) -> RChars<'_> {
+    RChars { bytes }
+}
+
+impl<'a> RChars<'a> {
+    /// The bytes that have not been decoded yet.
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    fn fail(&mut self, err: Utf8ErrorAt) -> Option<Result<char, Utf8ErrorAt>> {
+        self.bytes = &[];
+        Some(Err(err))
+    }
+}
+
+impl<'a> Iterator for RChars<'a> {
+    type Item = Result<char, Utf8ErrorAt>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = self.bytes.len();
+        let last = *self.bytes.last()?;
+        if last < 0x80 {
+            self.bytes = &self.bytes[..end - 1];
+            return Some(Ok(last as char));
+        }
+        let mut start = end - 1;
+        while start > 0 && end - start < 4 && is_continuation(self.bytes[start]) {
+            start -= 1;
+        }
+        if is_continuation(self.bytes[start]) {
+            // Four or more continuation bytes in a row, or no lead at all.
+            return self.fail(Utf8ErrorAt::new(end - 1, UtfError::InvalidLead, 1));
+        }
+        match decode_step(&self.bytes[start..end]) {
+            Ok((code_point, length)) if start + length == end => {
+                self.bytes = &self.bytes[..start];
+                char::from_u32(code_point).map(Ok)
+            }
+            // A complete character followed by stray continuation bytes; the
+            // last of them is the first problem seen from the back.
+            Ok(_) => self.fail(Utf8ErrorAt::new(end - 1, UtfError::InvalidLead, 1)),
+            Err(err) => self.fail(err.at(start)),
+        }
+    }
+}
+
+impl FusedIterator for RChars<'_> {}
+
+#[cfg(test)]
+mod test_rchars {
+    use super::*;
+
+    #[test]
+    fn test_rchars_matches_std() {
+        let inputs = [
+            "",
+            "abc",
+            "¡¢£¤¥¦§¨©ª«¬\u{AD}®¯°",
+            "ขฃคฅฆงจฉชซฌญฎฏฐฑฒณดตถ",
+            "😀𒀀𒀁𒀂 mixed é€😀ข",
+        ];
+        for input in inputs.iter() {
+            let expected: Vec<_> = input.chars().rev().map(Ok).collect();
+            let decoded: Vec<_> = rchars(input.as_bytes()).collect();
+            assert_eq!(decoded, expected);
+        }
+    }
+
+    #[test]
+    fn test_rchars_incomplete_suffix() {
+        let mut bytes = "a€".as_bytes().to_vec();
+        bytes.extend_from_slice(&[0xf0, 0x9f, 0x98]);
+        let mut it = rchars(&bytes);
+        let err = it.next().unwrap().unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (4, UtfError::NotEnoughRoom));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_rchars_continuation_runs() {
+        let bytes = [0x61, 0x80, 0x80, 0x80, 0x80];
+        let err = rchars(&bytes).next().unwrap().unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (4, UtfError::InvalidLead));
+
+        // A valid 2-byte character followed by a stray continuation byte.
+        let bytes = [0x61, 0xc3, 0xa9, 0xa9];
+        let err = rchars(&bytes).next().unwrap().unwrap_err();
+        assert_eq!(err.offset(), 3);
+
+        let mut it = rchars(&[0x80]);
+        assert_eq!(it.next().unwrap().unwrap_err().offset(), 0);
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn test_rchars_fused_after_error() {
+        let bytes = b"ab\xe0\x80cd";
+        let mut it = rchars(bytes);
+        assert_eq!(it.next(), Some(Ok('d')));
+        assert_eq!(it.next(), Some(Ok('c')));
+        assert!(it.next().unwrap().is_err());
+        for _ in 0..10 {
+            assert_eq!(it.next(), None);
+            assert!(it.as_bytes().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_rchars_partial_decode() {
+        let mut bytes = vec![0xff];
+        bytes.extend_from_slice("ab😀".as_bytes());
+        let mut it = rchars(&bytes);
+        assert_eq!(it.next(), Some(Ok('😀')));
+        assert_eq!(it.next(), Some(Ok('b')));
+        assert_eq!(it.next(), Some(Ok('a')));
+        assert_eq!(it.as_bytes(), [0xff]);
+        assert_eq!(it.next().unwrap().unwrap_err().offset(), 0);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::error::Utf8ErrorAt;
+use crate::validate::validate;
+
+/// A segment of the input yielded by [`Runs`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Run<'a> {
+    Ascii(&'a [u8]),
+    /// Text with at least one non-ASCII character, plus any ASCII runs too
+    /// short to stand on their own.
+    NonAscii(&'a str),
+}
+
+impl<'a> Run<'a> {
+    #[inline]
+    pub fn as_str(&self) -> &'a str {
+        match *self {
+            // SAFETY: ASCII is valid UTF-8.
+            Run::Ascii(bytes) => unsafe { core::str::from_utf8_unchecked(bytes) },
+            Run::NonAscii(text) => text,
+        }
+    }
+}
+
+/// Iterator over alternating ASCII and non-ASCII segments, created by
+/// [`ascii_runs`].
+#[derive(Clone, Debug)]
+pub struct Runs<'a> {
+    text: &'a str,
+    min_ascii_len: usize,
+}
+
+impl<'a> Runs<'a> {
+    /// Merges ASCII runs shorter than `len` into the neighbouring non-ASCII
+    /// segments. The default of 1 keeps every ASCII run.
+    pub fn min_ascii_len(mut self, len: usize) -> Self {
+        self.min_ascii_len = len.max(1);
+        self
+    }
+}
+
+/// Validates `bytes` and splits it into segments that are either all ASCII
+/// or contain non-ASCII characters. Together the segments tile `bytes`.
+pub fn ascii_runs(bytes: &[u8]) -> Result<Runs<'_>, Utf8ErrorAt> {
+    validate(bytes)?;
+    Ok(Runs {
+        // SAFETY: `bytes` was just validated.
+        text: unsafe { core::str::from_utf8_unchecked(bytes) },
+        min_ascii_len: 1,
+    })
+}
+
+#[inline]
+fn ascii_len(bytes: &[u8]) -> usize {
+    bytes.iter().take_while(|&&b| b < 0x80).count()
+}
+
+impl<'a> Iterator for Runs<'a> {
+    type Item = Run<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.text.is_empty() {
+            return None;
+        }
+        let bytes = self.text.as_bytes();
+        let ascii = ascii_len(bytes);
+        if ascii == bytes.len() || ascii >= self.min_ascii_len {
+            let (run, rest) = self.text.split_at(ascii);
+            self.text = rest;
+            return Some(Run::Ascii(run.as_bytes()));
+        }
+        let mut end = ascii;
+        loop {
+            end += bytes[end..].iter().take_while(|&&b| b >= 0x80).count();
+            let ascii = ascii_len(&bytes[end..]);
+            if ascii == 0 || ascii >= self.min_ascii_len {
+                break;
+            }
+            end += ascii;
+        }
+        let (run, rest) = self.text.split_at(end);
+        self.text = rest;
+        Some(Run::NonAscii(run))
+    }
+}
+
+#[cfg(test)]
+mod test_runs {
+    use super::*;
+
+    fn collect(text: &str, min: usize) -> Vec<Run<'_>> {
+        let runs: Vec<_> = ascii_runs(text.as_bytes())
+            .unwrap()
+            .min_ascii_len(min)
+            .collect();
+        let joined: String = runs.iter().map(Run::as_str).collect();
+        assert_eq!(joined, text);
+        for pair in runs.windows(2) {
+            let kinds = (
+                matches!(pair[0], Run::Ascii(_)),
+                matches!(pair[1], Run::Ascii(_)),
+            );
+            assert!(kinds.0 != kinds.1, "{:?}", runs);
+        }
+        runs
+    }
+
+    #[test]
+    fn test_runs_tile_input() {
+        assert_eq!(collect("", 1), []);
+        assert_eq!(collect("abc", 1), [Run::Ascii(b"abc")]);
+        assert_eq!(collect("é", 1), [Run::NonAscii("é")]);
+        assert_eq!(
+            collect("ab€😀cd", 1),
+            [Run::Ascii(b"ab"), Run::NonAscii("€😀"), Run::Ascii(b"cd")]
+        );
+        for text in ["a中b文c", "中 文 字 abc", "x", "中文 ", " 中文"].iter() {
+            for min in 1..6 {
+                collect(text, min);
+            }
+        }
+    }
+
+    #[test]
+    fn test_runs_min_len_coalescing() {
+        let text = "中文, 字符 and more 中文";
+        assert_eq!(
+            collect(text, 3),
+            [
+                Run::NonAscii("中文, 字符"),
+                Run::Ascii(b" and more "),
+                Run::NonAscii("中文"),
+            ]
+        );
+        // Short ASCII at either end joins the non-ASCII segment next to it.
+        assert_eq!(collect("ab中cd", 3), [Run::NonAscii("ab中cd")]);
+        assert_eq!(
+            collect("abc中de", 3),
+            [Run::Ascii(b"abc"), Run::NonAscii("中de")]
+        );
+        // Without non-ASCII text there is nothing to merge into.
+        assert_eq!(collect("ab", 3), [Run::Ascii(b"ab")]);
+    }
+
+    #[test]
+    fn test_runs_invalid() {
+        let err = ascii_runs(b"ab\xff").unwrap_err();
+        assert_eq!(err.offset(), 2);
+    }
+}
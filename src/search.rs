@@ -0,0 +1,136 @@
+use crate::core::is_continuation;
+use crate::error::Utf8ErrorAt;
+use crate::validate::validate;
+
+/// Bytes validated, and then searched, at a time (at least 4).
+const BLOCK: usize = 64 * 1024;
+
+/// Finds the first occurrence of `needle` that starts on a character boundary
+/// of `haystack`.
+///
+/// `haystack` is validated block by block and each block is searched with
+/// [`str::find`], so only about one block past the match is validated; an
+/// error is returned if invalid UTF-8 is found before the match, or anywhere
+/// when there is no match.
+pub fn find_str(haystack: &[u8], needle: &str) -> Result<Option<usize>, Utf8ErrorAt> {
+    find_str_blocks(haystack, needle, BLOCK)
+}
+
+fn find_str_blocks(
+    haystack: &[u8],
+    needle: &str,
+    block_len: usize,
+) -> Result<Option<usize>, Utf8ErrorAt> {
+    if needle.is_empty() {
+        return Ok(Some(0));
+    }
+    // `haystack[search_from..valid_end]` is validated text that may still
+    // hold the start of a match.
+    let mut search_from = 0;
+    let mut valid_end = 0;
+    while valid_end < haystack.len() {
+        let end = haystack.len().min(valid_end + block_len);
+        let block = &haystack[valid_end..end];
+        let (block_valid, error) = match validate(block) {
+            Ok(()) => (block.len(), None),
+            // A character cut by the end of the block, or an error whose kind
+            // may depend on the bytes after it, is checked with the next one.
+            Err(err) if end < haystack.len() && block.len() - err.offset() < 4 => {
+                (err.offset(), None)
+            }
+            Err(err) => (err.offset(), Some(err.at(valid_end))),
+        };
+        valid_end += block_valid;
+        debug_assert!(core::str::from_utf8(&haystack[search_from..valid_end]).is_ok());
+        // SAFETY: both ends are character boundaries of validated text.
+        let text = unsafe { core::str::from_utf8_unchecked(&haystack[search_from..valid_end]) };
+        if let Some(index) = text.find(needle) {
+            return Ok(Some(search_from + index));
+        }
+        if let Some(err) = error {
+            return Err(err);
+        }
+        // A match may still start in the last `needle.len() - 1` bytes.
+        search_from = search_from.max(valid_end.saturating_sub(needle.len() - 1));
+        while search_from < valid_end && is_continuation(haystack[search_from]) {
+            search_from -= 1;
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test_search {
+    use super::*;
+    use crate::core::UtfError;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    #[test]
+    fn test_find_str_empty_needle() {
+        assert_eq!(find_str(b"", ""), Ok(Some(0)));
+        assert_eq!(find_str(&[0xff], ""), Ok(Some(0)));
+    }
+
+    #[test]
+    fn test_find_str_multibyte() {
+        let haystack = "ขฃค 😀€ abc 😀€";
+        let expected = haystack.find("😀€");
+        assert_eq!(find_str(haystack.as_bytes(), "😀€"), Ok(expected));
+        assert_eq!(find_str(haystack.as_bytes(), "zz"), Ok(None));
+    }
+
+    #[test]
+    fn test_find_str_invalid_before_match() {
+        // The needle bytes follow a truncated sequence, so they are never a
+        // confirmed match.
+        let mut haystack = b"ab".to_vec();
+        haystack.extend_from_slice(&[0xf0, 0x9f]);
+        haystack.extend_from_slice("€".as_bytes());
+        let err = find_str(&haystack, "€").unwrap_err();
+        assert_eq!(err.offset(), 2);
+        assert_eq!(err.kind(), UtfError::IncompleteSequence);
+    }
+
+    #[test]
+    fn test_find_str_invalid_after_match() {
+        let mut haystack = "a€".as_bytes().to_vec();
+        haystack.push(0xff);
+        assert_eq!(find_str(&haystack, "€"), Ok(Some(1)));
+        assert_eq!(find_str(&haystack, "b").unwrap_err().offset(), 4);
+    }
+
+    #[test]
+    fn test_find_str_long_haystack() {
+        let mut haystack = "a".repeat(4095);
+        haystack.push('😀');
+        assert_eq!(find_str(haystack.as_bytes(), "😀"), Ok(Some(4095)));
+        assert_eq!(find_str(haystack.as_bytes(), "a😀"), Ok(Some(4094)));
+    }
+
+    #[test]
+    fn test_find_str_across_blocks() {
+        let mut rng = XorShift::new(201);
+        let needles = ["a", "é", "€😀", "ab", "\u{fffd}"];
+        for _ in 0..2000 {
+            let fragments = rng.below(30);
+            let input = random_corrupt(&mut rng, fragments);
+            let needle = needles[rng.below(needles.len())];
+            // A match can only start before the first error.
+            let expected = match validate(&input) {
+                Ok(()) => Ok(core::str::from_utf8(&input).unwrap().find(needle)),
+                Err(err) => match core::str::from_utf8(&input[..err.offset()])
+                    .unwrap()
+                    .find(needle)
+                {
+                    Some(index) => Ok(Some(index)),
+                    None => Err(err),
+                },
+            };
+            for block_len in 4..12 {
+                let found = find_str_blocks(&input, needle, block_len);
+                assert_eq!(found, expected, "{:x?} {:?} {}", input, needle, block_len);
+            }
+            assert_eq!(find_str(&input, needle), expected);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use alloc::vec::Vec;
+
+use crate::core::{decode_step, is_continuation};
+use crate::error::Utf8ErrorAt;
+use crate::validate::incomplete_suffix_len;
+
+/// Summary of a byte buffer produced by [`analyze`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TextStats {
+    /// Total number of bytes examined; `u64` like stream offsets, as the
+    /// reader functions count whole streams.
+    pub bytes: u64,
+    /// Number of valid characters.
+    pub chars: u64,
+    /// Whether every byte is ASCII.
+    pub ascii_only: bool,
+    /// Every maximal ill-formed subsequence, in order.
+    pub errors: Vec<Utf8ErrorAt>,
+}
+
+impl TextStats {
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Counts the characters of `bytes` and collects all of its errors.
+pub fn analyze(bytes: &[u8]) -> TextStats {
+    let mut stats = TextStats {
+        ascii_only: true,
+        ..TextStats::default()
+    };
+    analyze_block(&mut stats, bytes, true);
+    stats
+}
+
+/// Adds `bytes`, the next block of a stream, to `stats`. Unless `last` is
+/// set, a truncated character at the end is left out; the returned length of
+/// it must be passed again at the start of the next block.
+///
+/// Offsets in `stats.errors` count from the start of the stream, which is
+/// `stats.bytes` before the call.
+pub(crate) fn analyze_block(stats: &mut TextStats, bytes: &[u8], last: bool) -> usize {
+    // A byte that is not a continuation byte always starts a new character
+    // or ill-formed subsequence, so stopping before it does not change the
+    // result. The kind of an error at the very end may depend on the bytes
+    // after it, so such errors wait for the next block as well.
+    let tail = match incomplete_suffix_len(bytes) {
+        _ if last => 0,
+        Ok(tail) => tail,
+        Err(_) => bytes
+            .iter()
+            .rev()
+            .take(4)
+            .position(|&b| !is_continuation(b))
+            .map_or(0, |pos| pos + 1),
+    };
+    let end = bytes.len() - tail;
+    let base = stats.bytes;
+    stats.bytes = base.saturating_add(end as u64);
+    stats.ascii_only &= bytes[..end].is_ascii();
+    let mut pos = 0;
+    // Decoding still sees the tail, which may tell what kind of error a
+    // character cut short before it is.
+    while pos < end {
+        match decode_step(&bytes[pos..]) {
+            Ok((_, length)) => {
+                stats.chars += 1;
+                pos += length;
+            }
+            Err(err) => {
+                stats.errors.push(err.at(pos).at_stream(base));
+                pos += err.skip_len();
+            }
+        }
+    }
+    tail
+}
+
+#[cfg(test)]
+mod test_stats {
+    use super::*;
+    use crate::core::UtfError;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    #[test]
+    fn test_analyze_valid() {
+        let stats = analyze("aé€😀".as_bytes());
+        assert_eq!(stats.bytes, 10);
+        assert_eq!(stats.chars, 4);
+        assert!(!stats.ascii_only);
+        assert!(stats.is_valid());
+        assert!(analyze(b"").ascii_only);
+    }
+
+    #[test]
+    fn test_analyze_blocks_matches_analyze() {
+        let mut rng = XorShift::new(237);
+        for _ in 0..2000 {
+            let fragments = rng.below(40);
+            let input = random_corrupt(&mut rng, fragments);
+            let block = 1 + rng.below(8);
+            let mut stats = TextStats {
+                ascii_only: true,
+                ..TextStats::default()
+            };
+            let mut tail: Vec<u8> = Vec::new();
+            for chunk in input.chunks(block) {
+                tail.extend_from_slice(chunk);
+                let len = analyze_block(&mut stats, &tail, false);
+                tail.drain(..tail.len() - len);
+            }
+            analyze_block(&mut stats, &tail, true);
+            assert_eq!(stats, analyze(&input), "{:x?} / {}", input, block);
+        }
+    }
+
+    #[test]
+    fn test_analyze_errors() {
+        let stats = analyze(&[0x61, 0xff, 0x62, 0xe0, 0x80, 0xf0, 0x9f]);
+        assert_eq!(stats.chars, 2);
+        let errors: Vec<_> = stats
+            .errors
+            .iter()
+            .map(|e| (e.offset(), e.kind()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (1, UtfError::InvalidLead),
+                (3, UtfError::OverlongSequence),
+                (4, UtfError::InvalidLead),
+                (5, UtfError::NotEnoughRoom),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analyze_block_past_4_gib() {
+        let base = u64::from(u32::MAX) - 1;
+        let mut stats = TextStats {
+            bytes: base,
+            ascii_only: true,
+            ..TextStats::default()
+        };
+        assert_eq!(analyze_block(&mut stats, b"ab\xffcd", true), 0);
+        assert_eq!(stats.bytes, base + 5);
+        assert_eq!(stats.chars, 4);
+        assert_eq!(stats.errors[0].stream_offset(), base + 2);
+    }
+}
@@ -0,0 +1,528 @@
+use core::fmt;
+
+use crate::core::{decode_step, second_byte_range, UtfError};
+use crate::error::Utf8ErrorAt;
+
+/// Validates `bytes`, returning the length of a trailing truncated sequence
+/// (0 if `bytes` ends on a character boundary).
+///
+/// A last byte whose kind of error depends on the byte after it is also
+/// left in the tail, so that the error does not depend on where the input
+/// was split; [`tail_error`] tells what it is if the input ends there.
+#[inline]
+pub(crate) fn validate_chunk(bytes: &[u8]) -> Result<usize, Utf8ErrorAt> {
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if bytes[pos] < 0x80 {
+            pos += 1;
+            continue;
+        }
+        match decode_step(&bytes[pos..]) {
+            Ok((_, length)) => pos += length,
+            Err(err) if err.error_len().is_none() => return Ok(bytes.len() - pos),
+            Err(_) if pos + 1 == bytes.len() && kind_depends_on_next(bytes[pos]) => return Ok(1),
+            Err(err) => return Err(err.at(pos)),
+        }
+    }
+    Ok(0)
+}
+
+/// The error for a non-empty tail left by [`validate_chunk`] once the input
+/// ends, at offset 0.
+#[inline]
+pub(crate) fn tail_error(tail: &[u8]) -> Utf8ErrorAt {
+    // Complete characters are never left in the tail.
+    decode_step(tail)
+        .err()
+        .unwrap_or_else(|| Utf8ErrorAt::truncated(0, tail.len() as u8))
+}
+
+/// Whether the kind of error of `lead` depends on the byte after it, as
+/// for leads that can never start a character.
+#[inline]
+fn kind_depends_on_next(lead: u8) -> bool {
+    let (lo, hi) = second_byte_range(lead);
+    lo > hi
+}
+
+/// An invalid sequence, as passed to the handler set with
+/// [`Utf8Validator::set_error_handler`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidRegion<'a> {
+    /// Offset from the start of the stream.
+    pub offset: u64,
+    pub kind: UtfError,
+    /// The maximal ill-formed subsequence, which may have arrived in more
+    /// than one chunk.
+    pub bytes: &'a [u8],
+}
+
+/// What [`Utf8Validator`] does after an invalid sequence.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorDirective {
+    /// Skip the sequence and keep validating.
+    Continue,
+    /// Fail, and keep failing with this error.
+    Abort,
+}
+
+#[cfg(feature = "alloc")]
+type ErrorHandler = alloc::boxed::Box<dyn FnMut(&InvalidRegion<'_>) -> ErrorDirective + Send>;
+
+/// Incremental validator for input that arrives in chunks.
+///
+/// Up to 3 bytes of a sequence split across chunks are carried over, and
+/// error offsets count from the start of the stream as `u64`, see
+/// [`Utf8ErrorAt::stream_offset`].
+#[derive(Default)]
+pub struct Utf8Validator {
+    pending: [u8; 4],
+    pending_len: usize,
+    consumed: u64,
+    error: Option<Utf8ErrorAt>,
+    error_count: u64,
+    #[cfg(feature = "alloc")]
+    handler: Option<ErrorHandler>,
+}
+
+impl fmt::Debug for Utf8Validator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Utf8Validator");
+        debug
+            .field("pending", &self.pending())
+            .field("consumed", &self.consumed)
+            .field("error", &self.error)
+            .field("error_count", &self.error_count);
+        #[cfg(feature = "alloc")]
+        debug.field("handler", &self.handler.is_some());
+        debug.finish()
+    }
+}
+
+impl Utf8Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `handler` for every invalid sequence instead of failing on the
+    /// first one; the stream only fails if it returns
+    /// [`ErrorDirective::Abort`].
+    #[cfg(feature = "alloc")]
+    pub fn set_error_handler<F>(&mut self, handler: F)
+    where
+        F: FnMut(&InvalidRegion<'_>) -> ErrorDirective + Send + 'static,
+    {
+        self.handler = Some(alloc::boxed::Box::new(handler));
+    }
+
+    /// A copy of the state without the error handler, for pushing input that
+    /// may have to be taken back.
+    #[cfg(feature = "std")]
+    pub(crate) fn snapshot(&self) -> Self {
+        Utf8Validator {
+            pending: self.pending,
+            pending_len: self.pending_len,
+            consumed: self.consumed,
+            error: self.error,
+            error_count: self.error_count,
+            handler: None,
+        }
+    }
+
+    /// Number of invalid sequences seen so far.
+    #[inline]
+    pub fn error_count(&self) -> u64 {
+        self.error_count
+    }
+
+    /// Validates the next chunk of the stream.
+    ///
+    /// Once an error is returned, every later call returns it again.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), Utf8ErrorAt> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        let result = self.push_inner(chunk);
+        if let Err(err) = result {
+            self.error = Some(err);
+        }
+        result
+    }
+
+    /// Counts `err` and asks the handler whether to go on.
+    fn report(&mut self, err: Utf8ErrorAt, bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+        self.error_count = self.error_count.saturating_add(1);
+        let directive = self.ask_handler(&InvalidRegion {
+            offset: err.stream_offset(),
+            kind: err.kind(),
+            bytes,
+        });
+        match directive {
+            ErrorDirective::Abort => Err(err),
+            ErrorDirective::Continue => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn ask_handler(&mut self, region: &InvalidRegion<'_>) -> ErrorDirective {
+        match &mut self.handler {
+            Some(handler) => handler(region),
+            None => ErrorDirective::Abort,
+        }
+    }
+
+    /// Without `alloc` there is no handler, and the first error fails.
+    #[cfg(not(feature = "alloc"))]
+    fn ask_handler(&mut self, _region: &InvalidRegion<'_>) -> ErrorDirective {
+        ErrorDirective::Abort
+    }
+
+    fn push_inner(&mut self, chunk: &[u8]) -> Result<(), Utf8ErrorAt> {
+        if chunk.is_empty() {
+            return Ok(());
+        }
+        let base = self.consumed;
+        self.consumed = self.consumed.saturating_add(chunk.len() as u64);
+        let whole = chunk.len();
+        let mut chunk = chunk;
+        if self.pending_len != 0 {
+            // Complete the carried sequence with at most 3 bytes of the chunk.
+            let start = base - self.pending_len as u64;
+            let take = chunk.len().min(4 - self.pending_len);
+            let mut stitched = self.pending;
+            stitched[self.pending_len..self.pending_len + take].copy_from_slice(&chunk[..take]);
+            match decode_step(&stitched[..self.pending_len + take]) {
+                Ok((_, length)) => {
+                    chunk = &chunk[length - self.pending_len..];
+                    self.pending_len = 0;
+                }
+                Err(err) if err.error_len().is_none() => {
+                    self.pending = stitched;
+                    self.pending_len += take;
+                    return Ok(());
+                }
+                Err(err) => {
+                    // The carried bytes are a valid prefix or a single lead,
+                    // so the ill-formed subsequence covers all of them.
+                    let skip = err.skip_len();
+                    self.report(err.at_stream(start), &stitched[..skip])?;
+                    chunk = &chunk[skip - self.pending_len..];
+                    self.pending_len = 0;
+                }
+            }
+        }
+        loop {
+            let chunk_start = base.saturating_add((whole - chunk.len()) as u64);
+            match validate_chunk(chunk) {
+                Ok(tail) => {
+                    self.pending[..tail].copy_from_slice(&chunk[chunk.len() - tail..]);
+                    self.pending_len = tail;
+                    return Ok(());
+                }
+                Err(err) => {
+                    let (pos, skip) = (err.offset(), err.skip_len());
+                    self.report(err.at_stream(chunk_start), &chunk[pos..pos + skip])?;
+                    chunk = &chunk[pos + skip..];
+                }
+            }
+        }
+    }
+
+    /// Checks that the stream did not end in the middle of a sequence.
+    pub fn finish(&mut self) -> Result<(), Utf8ErrorAt> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        if self.pending_len != 0 {
+            let start = self.consumed - self.pending_len as u64;
+            let pending = self.pending;
+            let len = self.pending_len;
+            let err = tail_error(&pending[..len]).at_stream(start);
+            self.pending_len = 0;
+            if let Err(err) = self.report(err, &pending[..len]) {
+                self.error = Some(err);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Total number of bytes pushed so far.
+    #[inline]
+    pub fn bytes_consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Bytes of the incomplete sequence at the end of the input pushed so far.
+    ///
+    /// This may also be a lead that can never start a character, whose kind
+    /// of error is only known with the next byte.
+    #[inline]
+    pub fn pending(&self) -> &[u8] {
+        &self.pending[..self.pending_len]
+    }
+
+    /// The first error seen, if any.
+    #[inline]
+    pub fn error(&self) -> Option<Utf8ErrorAt> {
+        self.error
+    }
+}
+
+#[cfg(test)]
+mod test_stream {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::stats::analyze;
+    use crate::test_util::{random_corrupt, XorShift};
+    #[cfg(feature = "alloc")]
+    use std::sync::{Arc, Mutex};
+
+    fn push_all(chunks: &[&[u8]]) -> Result<(), Utf8ErrorAt> {
+        let mut validator = Utf8Validator::new();
+        for chunk in chunks {
+            validator.push(chunk)?;
+        }
+        validator.finish()
+    }
+
+    #[test]
+    fn test_validator_chunkings() {
+        let input = "aé€😀ข".as_bytes();
+        for split in 0..=input.len() {
+            let (a, b) = input.split_at(split);
+            assert_eq!(push_all(&[a, b]), Ok(()));
+        }
+        let bytes: Vec<&[u8]> = input.chunks(1).collect();
+        assert_eq!(push_all(&bytes), Ok(()));
+    }
+
+    #[test]
+    fn test_validator_errors() {
+        let err = push_all(&[b"ab", &[0xf0, 0x9f], &[0x41]]).unwrap_err();
+        assert_eq!(err.offset(), 2);
+        assert_eq!(err.kind(), UtfError::IncompleteSequence);
+
+        let err = push_all(&[b"ab", &[0xff]]).unwrap_err();
+        assert_eq!(err.offset(), 2);
+        assert_eq!(err.kind(), UtfError::InvalidLead);
+
+        let err = push_all(&[b"ab", &[0xf0], &[0x9f, 0x98]]).unwrap_err();
+        assert_eq!(err.offset(), 2);
+        assert_eq!(err.kind(), UtfError::NotEnoughRoom);
+    }
+
+    #[test]
+    fn test_validator_chunk_edges() {
+        // A 4-byte character one byte per chunk, with empty chunks between.
+        let emoji = "a😀".as_bytes();
+        let mut chunks: Vec<&[u8]> = Vec::new();
+        for byte in emoji.chunks(1) {
+            chunks.push(byte);
+            chunks.push(b"");
+        }
+        assert_eq!(push_all(&chunks), Ok(()));
+
+        // An invalid lead starting a chunk, with and without a character
+        // left pending by the previous one.
+        let err = push_all(&[b"ab", b"", &[0xff, 0x61]]).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (2, UtfError::InvalidLead));
+        let err = push_all(&[&[0x61, 0xe2, 0x82], &[0xff]]).unwrap_err();
+        assert_eq!(
+            (err.offset(), err.kind()),
+            (1, UtfError::IncompleteSequence)
+        );
+        let mut validator = Utf8Validator::new();
+        validator.push(&[0x61, 0xe2, 0x82]).unwrap();
+        assert!(validator.push(&[0xff]).is_err());
+
+        // The kind of error of C0 at the end of a chunk depends on the next.
+        let err = push_all(&[b"a\xc0", b"", b"b"]).unwrap_err();
+        assert_eq!(
+            (err.offset(), err.kind()),
+            (1, UtfError::IncompleteSequence)
+        );
+        let err = push_all(&[b"a\xc0", b"\x80"]).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, UtfError::OverlongSequence));
+        let err = push_all(&[b"a\xf5", b""]).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, UtfError::InvalidCodePoint));
+    }
+
+    #[test]
+    fn test_validator_chunking_independent() {
+        let mut rng = XorShift::new(256);
+        for _ in 0..1000 {
+            let fragments = rng.below(20);
+            let input = random_corrupt(&mut rng, fragments);
+            let expected = crate::validate::validate(&input);
+            for max in [1, 2, 3, 5, 8].iter() {
+                let mut validator = Utf8Validator::new();
+                let mut rest = &input[..];
+                let mut result = Ok(());
+                while !rest.is_empty() && result.is_ok() {
+                    let (chunk, tail) = rest.split_at(rng.below(*max + 1).min(rest.len()));
+                    result = validator.push(chunk);
+                    rest = tail;
+                }
+                let result = result.and_then(|()| validator.finish());
+                assert_eq!(result, expected, "{:x?} / {}", input, max);
+            }
+            // Single bytes put every error at the end of a chunk.
+            assert_eq!(decode_bytewise(&input), expected, "{:x?}", input);
+            #[cfg(feature = "std")]
+            assert_eq!(read_bytewise(&input), expected, "{:x?}", input);
+        }
+    }
+
+    fn decode_bytewise(input: &[u8]) -> Result<(), Utf8ErrorAt> {
+        let mut decoder = crate::decoder::Utf8Decoder::new();
+        for byte in input {
+            if let Some(err) = decoder.decode(core::slice::from_ref(byte)).error {
+                return Err(err);
+            }
+        }
+        decoder.finish()
+    }
+
+    /// Reads `input` through a `BufUtf8Reader` that gets one byte per read.
+    #[cfg(feature = "std")]
+    fn read_bytewise(input: &[u8]) -> Result<(), Utf8ErrorAt> {
+        use crate::io::{BufUtf8Reader, StreamError};
+        use std::io::Read;
+
+        struct OneByte<'a>(&'a [u8]);
+
+        impl Read for OneByte<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                match self.0.split_first() {
+                    Some((first, rest)) if !buf.is_empty() => {
+                        buf[0] = *first;
+                        self.0 = rest;
+                        Ok(1)
+                    }
+                    _ => Ok(0),
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        match BufUtf8Reader::new(OneByte(input)).read_to_end(&mut out) {
+            Ok(_) => Ok(()),
+            Err(err) => match StreamError::from(err) {
+                StreamError::Utf8(err) => Err(err),
+                StreamError::Io(err) => panic!("{}", err),
+            },
+        }
+    }
+
+    #[test]
+    fn test_validator_past_4_gib() {
+        let mut validator = Utf8Validator::new();
+        validator.consumed = (1 << 32) - 2;
+        validator.push(&[0x61, 0xe2]).unwrap();
+        validator.push(&[0x82, 0xac, 0x62]).unwrap();
+        let err = validator.push(&[0x80]).unwrap_err();
+        assert_eq!(err.stream_offset(), (1 << 32) + 3);
+        assert_eq!(validator.bytes_consumed(), (1 << 32) + 4);
+
+        // A sequence split across the boundary.
+        let mut validator = Utf8Validator::new();
+        validator.consumed = (1 << 32) - 1;
+        validator.push(&[0xf0]).unwrap();
+        let err = validator.push(&[0x9f, 0x41]).unwrap_err();
+        assert_eq!(err.stream_offset(), (1 << 32) - 1);
+
+        let mut validator = Utf8Validator::new();
+        validator.consumed = u64::MAX - 1;
+        validator.push(b"abc").unwrap();
+        assert_eq!(validator.bytes_consumed(), u64::MAX);
+        let err = validator.push(&[0xff]).unwrap_err();
+        assert_eq!(err.stream_offset(), u64::MAX);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_error_handler_aborts_on_third_error() {
+        let mut validator = Utf8Validator::new();
+        let mut seen = 0;
+        validator.set_error_handler(move |_| {
+            seen += 1;
+            if seen == 3 {
+                ErrorDirective::Abort
+            } else {
+                ErrorDirective::Continue
+            }
+        });
+        validator.push(b"a\xffb\xe2\x82").unwrap();
+        validator.push(b"c").unwrap();
+        assert_eq!(validator.error_count(), 2);
+        let err = validator.push(b"d\xc0\x80").unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (7, UtfError::OverlongSequence));
+        assert_eq!(validator.error_count(), 3);
+        assert_eq!(validator.push(b"fine"), Err(err));
+        assert_eq!(validator.finish(), Err(err));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_error_handler_need_not_be_sync() {
+        // A `Cell` is `Send` but not `Sync`.
+        let seen = std::cell::Cell::new(0);
+        let mut validator = Utf8Validator::new();
+        validator.set_error_handler(move |_| {
+            seen.set(seen.get() + 1);
+            if seen.get() < 2 {
+                ErrorDirective::Continue
+            } else {
+                ErrorDirective::Abort
+            }
+        });
+        validator.push(b"\xff").unwrap();
+        assert!(validator.push(b"\xff").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_error_handler_matches_analyze() {
+        let mut rng = XorShift::new(241);
+        for _ in 0..1000 {
+            let fragments = rng.below(40);
+            let input = random_corrupt(&mut rng, fragments);
+            let regions = Arc::new(Mutex::new(Vec::new()));
+            let mut validator = Utf8Validator::new();
+            let recorded = regions.clone();
+            validator.set_error_handler(move |region| {
+                let mut recorded = recorded.lock().unwrap();
+                recorded.push((region.offset as usize, region.bytes.to_vec()));
+                ErrorDirective::Continue
+            });
+            let mut rest = &input[..];
+            while !rest.is_empty() {
+                let (chunk, tail) = rest.split_at(rng.below(6).min(rest.len()));
+                validator.push(chunk).unwrap();
+                rest = tail;
+            }
+            validator.finish().unwrap();
+
+            let expected: Vec<_> = analyze(&input)
+                .errors
+                .iter()
+                .map(|err| {
+                    let start = err.offset();
+                    (start, input[start..start + err.skip_len()].to_vec())
+                })
+                .collect();
+            assert_eq!(*regions.lock().unwrap(), expected, "{:x?}", input);
+            assert_eq!(validator.error_count(), expected.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_validator_sticky_error() {
+        let mut validator = Utf8Validator::new();
+        let err = validator.push(&[0x61, 0xc0]).and(validator.push(&[0x80]));
+        assert_eq!(err.unwrap_err().offset(), 1);
+        assert_eq!(validator.push(b"fine").unwrap_err().offset(), 1);
+        assert_eq!(validator.finish().unwrap_err().offset(), 1);
+    }
+}
@@ -0,0 +1,64 @@
+use alloc::string::String;
+
+use crate::error::Utf8ErrorAt;
+use crate::lossy::Lossy;
+use crate::validate::validate;
+
+/// Appending possibly invalid UTF-8 to a [`String`] without a temporary.
+pub trait PushUtf8 {
+    /// Appends `bytes` if they are valid UTF-8, leaving `self` unchanged
+    /// otherwise.
+    fn push_utf8_checked(&mut self, bytes: &[u8]) -> Result<(), Utf8ErrorAt>;
+
+    /// Appends `bytes`, replacing each maximal ill-formed subsequence with
+    /// U+FFFD, and returns the number of replacements.
+    fn push_utf8_lossy(&mut self, bytes: &[u8]) -> usize;
+}
+
+impl PushUtf8 for String {
+    fn push_utf8_checked(&mut self, bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+        validate(bytes)?;
+        // SAFETY: `bytes` was just validated.
+        self.push_str(unsafe { core::str::from_utf8_unchecked(bytes) });
+        Ok(())
+    }
+
+    fn push_utf8_lossy(&mut self, bytes: &[u8]) -> usize {
+        let lossy = Lossy::new();
+        self.reserve(lossy.decoded_len(bytes));
+        lossy.push_str(self, bytes)
+    }
+}
+
+#[cfg(test)]
+mod test_string_ext {
+    use super::*;
+    use crate::core::UtfError;
+
+    #[test]
+    fn test_push_utf8_checked_all_or_nothing() {
+        let mut s = String::from("head ");
+        assert_eq!(s.push_utf8_checked("é€😀".as_bytes()), Ok(()));
+        assert_eq!(s, "head é€😀");
+
+        // The only problem is a truncated character at the very end.
+        let mut bytes = "long valid text ".repeat(10).into_bytes();
+        bytes.extend_from_slice(&[0xf0, 0x9f, 0x98]);
+        let err = s.push_utf8_checked(&bytes).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (160, UtfError::NotEnoughRoom));
+        assert_eq!(s, "head é€😀");
+
+        let err = s.push_utf8_checked(b"abc\xff").unwrap_err();
+        assert_eq!(err.offset(), 3);
+        assert_eq!(s, "head é€😀");
+    }
+
+    #[test]
+    fn test_push_utf8_lossy() {
+        let mut s = String::from("x");
+        assert_eq!(s.push_utf8_lossy(b"plain"), 0);
+        let input = [0x61, 0xff, 0xe0, 0x80, 0x62, 0xf0, 0x9f];
+        assert_eq!(s.push_utf8_lossy(&input), 4);
+        assert_eq!(s, format!("xplain{}", String::from_utf8_lossy(&input)));
+    }
+}
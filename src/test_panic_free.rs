@@ -0,0 +1,258 @@
+//! Feeds adversarial inputs to every public function; none of them may
+//! panic. Tests run with overflow checks on.
+
+use std::io::{Cursor, Read, Write};
+
+use crate::test_util::{random_corrupt, XorShift};
+use crate::*;
+
+fn adversarial_inputs() -> Vec<Vec<u8>> {
+    let mut rng = XorShift::new(221);
+    let mut inputs = Vec::new();
+    for len in 0..=64 {
+        inputs.push((0..len).map(|_| rng.next_u64() as u8).collect());
+        inputs.push(vec![0xff; len]);
+        inputs.push((0..len).map(|i| 0x80 | (i as u8 & 0x3f)).collect());
+        inputs.push(vec![0xf0; len]);
+        // Leads of every length, each cut right before its end.
+        inputs.push(
+            [0xc3, 0xe2, 0x82, 0xf0, 0x9f, 0x98]
+                .iter()
+                .cycle()
+                .take(len)
+                .copied()
+                .collect(),
+        );
+    }
+    for fragments in 0..200 {
+        inputs.push(random_corrupt(&mut rng, fragments % 40));
+    }
+    inputs
+}
+
+fn exercise(bytes: &[u8]) {
+    let len = bytes.len();
+    if let Err(err) = validate(bytes) {
+        let _ = err.lead_byte(bytes);
+        let _ = err.code_point(bytes);
+        let _ = err.code_point(&bytes[..len / 2]);
+    }
+    let _ = validate_with_hint(bytes, TextHint::Cjk);
+    let _ = validate_dfa(bytes);
+    let _ = validate_bulk(bytes);
+    let _ = split_valid(bytes);
+    let _ = validate_const(bytes);
+    for max_len in [0, 1, len / 2, len.saturating_sub(1), len, usize::MAX].iter() {
+        let _ = truncate_to_boundary(bytes, *max_len);
+    }
+    let _ = incomplete_suffix_len(bytes);
+    for i in [
+        0,
+        1,
+        2,
+        3,
+        len / 2,
+        len.saturating_sub(1),
+        len,
+        len + 5,
+        usize::MAX,
+    ]
+    .iter()
+    {
+        let _ = validate_from(bytes, *i);
+        let _ = validate_range(bytes, *i, len / 3);
+        let _ = validate_range(bytes, 0, *i);
+    }
+
+    let _ = to_string_lossy(bytes);
+    let _ = decode_lossy(bytes);
+    let _ = lossy_decoded_len(bytes);
+    let mut buf = bytes.to_vec();
+    repair_in_place(&mut buf);
+    let mut s = String::new();
+    let _ = s.push_utf8_checked(bytes);
+    s.push_utf8_lossy(bytes);
+
+    let escaped = escape_invalid(bytes);
+    assert_eq!(parse_hex_escaped(&escaped).as_deref(), Ok(bytes));
+    let _ = validate_hex_escaped(&escaped);
+    let _ = parse_hex_escaped(&String::from_utf8_lossy(bytes));
+
+    let _ = analyze(bytes);
+    let _ = scan_combining_runs(bytes, 0);
+    let _ = find_str(bytes, "a");
+    let _ = find_str(bytes, "😀");
+    rchars(bytes).for_each(drop);
+    for chunk in utf8_chunks(bytes) {
+        let _ = (chunk.valid(), chunk.invalid(), chunk.error());
+    }
+    invalid_ranges(bytes).for_each(drop);
+    code_point_indices(bytes).for_each(drop);
+    let _ = count_code_points(bytes);
+    let mut it = bytes.iter();
+    while it.len() != 0 {
+        let _ = validate_next(&mut it);
+    }
+    let mut it = bytes.iter();
+    while next_code_point(&mut it).is_some() {}
+    for window in bytes.windows(4) {
+        let code_point = u32::from_le_bytes([window[0], window[1], window[2], window[3]]);
+        let _ = encode_utf8(code_point, &mut [0; 4][..len % 5]);
+        let _ = encode_utf8_iter(code_point).map(|it| it.for_each(drop));
+    }
+    let mut it = Cursor::new(bytes).bytes();
+    while validate_next_fallible(&mut it).is_some() {}
+    for profile in [
+        ValidationProfile::Wtf8,
+        ValidationProfile::Cesu8,
+        ValidationProfile::ModifiedUtf8,
+    ]
+    .iter()
+    {
+        let mut it = bytes.iter();
+        while it.len() != 0 {
+            let _ = validate_next_with(&mut it, *profile);
+        }
+    }
+    let mut it = bytes.iter();
+    while it.len() != 0 {
+        let _ = validate_prev(&mut it);
+    }
+    let mut it = bytes.iter();
+    while it.len() != 0 {
+        let _ = validate_next_counted(&mut it);
+    }
+    decode_utf8(bytes).for_each(drop);
+    let _ = (
+        has_bom(bytes),
+        strip_bom(bytes),
+        skip_bom(&mut bytes.iter()),
+    );
+    let mut it = decode_utf8(bytes);
+    while it.skip_bom() {}
+    it.for_each(drop);
+    for window in bytes.windows(4) {
+        let _ = decode_word(u32::from_le_bytes([
+            window[0], window[1], window[2], window[3],
+        ]));
+    }
+
+    if let Ok(bitmap) = char_start_bitmap(bytes) {
+        for i in [0, len / 2, len, len + 1, usize::MAX].iter() {
+            bitmap.is_char_start(*i);
+            bitmap.rank(*i);
+            bitmap.select(*i);
+        }
+    }
+    if let Ok(runs) = ascii_runs(bytes) {
+        runs.clone().for_each(drop);
+        runs.min_ascii_len(0).for_each(drop);
+    }
+
+    let (a, b) = bytes.split_at(len / 2);
+    let _ = validate_concat(&[ValidatedPiece::Validated(a), ValidatedPiece::Unvalidated(b)]);
+    let _ = validate_concat(&[ValidatedPiece::Validated(b), ValidatedPiece::Validated(a)]);
+
+    for size in [1, 2, 3, 7].iter() {
+        let mut validator = Utf8Validator::new();
+        let mut decoder = Utf8Decoder::new();
+        for chunk in bytes.chunks(*size) {
+            let _ = validator.push(chunk);
+            decoder.decode(chunk);
+        }
+        let _ = validator.finish();
+        let _ = decoder.finish();
+        let mut validator = Utf8Validator::new();
+        validator.set_error_handler(|_| ErrorDirective::Continue);
+        for chunk in bytes.chunks(*size) {
+            let _ = validator.push(chunk);
+        }
+        let _ = validator.finish();
+    }
+    CharReader::new(bytes).for_each(drop);
+    let mut chunks = chunks_from_bufread(std::io::BufReader::with_capacity(3, bytes));
+    while let Ok(Some(_)) = chunks.next_chunk() {}
+    let mut out = [0; 3];
+    for capacity in [0, 1, 3].iter() {
+        let _ = validate_reader_with_capacity(bytes, *capacity);
+        let _ = BufUtf8Reader::with_capacity(*capacity, bytes).read_to_end(&mut Vec::new());
+    }
+    let mut reader = Utf8Reader::new(Cursor::new(bytes));
+    while let Ok(n) = reader.read(&mut out) {
+        if n == 0 {
+            break;
+        }
+    }
+    let _ = reader.error_offset();
+    let mut writer = Utf8Writer::new(Vec::new());
+    for chunk in bytes.chunks(3) {
+        let _ = writer.write(chunk);
+    }
+    let _ = writer.finish();
+    let _ = writer.error_offset();
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+    let mut it = units.clone();
+    while validate_next_utf16(&mut it).is_some() {}
+    utf16_chars(units.clone()).for_each(drop);
+    utf16_chars(units).lossy().for_each(drop);
+    let _ = validate_utf16le(bytes);
+    let _ = validate_utf16be(bytes);
+    for backend in utf16_backends() {
+        let _ = backend.validate(bytes, Endian::Little);
+        let _ = backend.validate(bytes, Endian::Big);
+    }
+    for lone in [LoneSurrogates::Reject, LoneSurrogates::Replace].iter() {
+        let mut reader = Utf16Reader::new(bytes).lone_surrogates(*lone);
+        while let Ok(n) = reader.read(&mut out) {
+            if n == 0 {
+                break;
+            }
+        }
+    }
+    let mut string = ValidString::new(
+        ValidatorBuilder::new()
+            .reject_interior_bom(true)
+            .allow_only_ranges(vec![0..=0x7ff, 0x1_0000..=u32::MAX])
+            .deny_ranges(vec![0x7f..=0x9f])
+            .build()
+            .unwrap(),
+    );
+    for at in 0..4 {
+        let _ = string.insert_bytes(at, bytes);
+        let _ = string.replace_range(at..at + 3, &bytes[..bytes.len().min(at)]);
+        let _ = string.remove_char_at(at);
+    }
+}
+
+#[test]
+fn test_public_api_panic_free() {
+    for input in adversarial_inputs() {
+        exercise(&input);
+    }
+}
+
+#[test]
+fn test_error_offsets_saturate() {
+    let err = validate(&[0xff]).unwrap_err();
+    assert_eq!(err.at(usize::MAX).offset(), usize::MAX);
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_generate_panic_free() {
+    let mut rng = rand::thread_rng();
+    let profiles = [
+        GenProfile::AsciiHeavy,
+        GenProfile::EuropeanMix,
+        GenProfile::CjkHeavy,
+        GenProfile::EmojiHeavy,
+        GenProfile::UniformScalars,
+    ];
+    for profile in profiles.iter() {
+        for len in 0..=64 {
+            assert_eq!(generate_with(&mut rng, len, *profile, true).len(), len);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Helpers shared by the unit tests.
+
+/// Small deterministic xorshift generator, so tests don't need `rand`.
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        XorShift(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// Interesting fragments to build corrupt inputs from: valid characters of
+/// every length, truncated sequences, overlongs, surrogates and stray bytes.
+const FRAGMENTS: &[&[u8]] = &[
+    b"a",
+    b"Z ",
+    "é".as_bytes(),
+    "€".as_bytes(),
+    "ข".as_bytes(),
+    "😀".as_bytes(),
+    &[0x80],
+    &[0xbf],
+    &[0xc0, 0x80],
+    &[0xc1, 0xbf],
+    &[0xc2],
+    &[0xe0, 0x80],
+    &[0xe0, 0xa0],
+    &[0xed, 0xa0, 0x80],
+    &[0xef, 0xbf],
+    &[0xf0, 0x8f, 0xbf, 0xbf],
+    &[0xf0, 0x9f, 0x98],
+    &[0xf4, 0x90, 0x80, 0x80],
+    &[0xf5, 0x80],
+    &[0xfe],
+    &[0xff],
+];
+
+/// Random bytes biased towards UTF-8 edge cases, `fragments` pieces long.
+pub(crate) fn random_corrupt(rng: &mut XorShift, fragments: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    for _ in 0..fragments {
+        if rng.below(8) == 0 {
+            out.push(rng.next_u64() as u8);
+        } else {
+            out.extend_from_slice(FRAGMENTS[rng.below(FRAGMENTS.len())]);
+        }
+    }
+    out
+}
+
+/// The test cases of Markus Kuhn's UTF-8 decoder stress test
+/// (`UTF-8-test.txt`), by section number, with whether each is well-formed.
+fn kuhn_cases() -> Vec<(String, Vec<u8>, bool)> {
+    let mut cases = Vec::new();
+    let mut add = |section: &str, bytes: &[u8], valid: bool| {
+        cases.push((section.to_string(), bytes.to_vec(), valid));
+    };
+    // 1: some correct UTF-8 text.
+    add("1", "κόσμε".as_bytes(), true);
+    // 2.1-2.3: first and last sequences of each length, other boundaries.
+    let boundaries: &[(&str, &[u8], bool)] = &[
+        ("2.1.1", &[0x00], true),
+        ("2.1.2", &[0xc2, 0x80], true),
+        ("2.1.3", &[0xe0, 0xa0, 0x80], true),
+        ("2.1.4", &[0xf0, 0x90, 0x80, 0x80], true),
+        ("2.1.5", &[0xf8, 0x88, 0x80, 0x80, 0x80], false),
+        ("2.1.6", &[0xfc, 0x84, 0x80, 0x80, 0x80, 0x80], false),
+        ("2.2.1", &[0x7f], true),
+        ("2.2.2", &[0xdf, 0xbf], true),
+        ("2.2.3", &[0xef, 0xbf, 0xbf], true),
+        ("2.2.4", &[0xf7, 0xbf, 0xbf, 0xbf], false),
+        ("2.2.5", &[0xfb, 0xbf, 0xbf, 0xbf, 0xbf], false),
+        ("2.2.6", &[0xfd, 0xbf, 0xbf, 0xbf, 0xbf, 0xbf], false),
+        ("2.3.1", &[0xed, 0x9f, 0xbf], true),
+        ("2.3.2", &[0xee, 0x80, 0x80], true),
+        ("2.3.3", &[0xef, 0xbf, 0xbd], true),
+        ("2.3.4", &[0xf4, 0x8f, 0xbf, 0xbf], true),
+        ("2.3.5", &[0xf4, 0x90, 0x80, 0x80], false),
+    ];
+    for (section, bytes, valid) in boundaries {
+        add(section, bytes, *valid);
+    }
+    // 3.1: unexpected continuation bytes, alone, in runs, and all of them.
+    for len in 1..=8 {
+        let run: Vec<u8> = [0x80, 0xbf].iter().copied().cycle().take(len).collect();
+        add("3.1", &run, false);
+    }
+    add("3.1.9", &(0x80..=0xbf).collect::<Vec<u8>>(), false);
+    // 3.2: lonely start bytes, each followed by a space.
+    for lead in 0xc0..=0xfdu8 {
+        add("3.2", &[lead, b' '], false);
+    }
+    // 3.3: sequences with the last continuation byte missing, and 3.4 all
+    // of them in a row.
+    let cut: &[&[u8]] = &[
+        &[0xc0],
+        &[0xe0, 0x80],
+        &[0xf0, 0x80, 0x80],
+        &[0xf8, 0x80, 0x80, 0x80],
+        &[0xfc, 0x80, 0x80, 0x80, 0x80],
+        &[0xdf],
+        &[0xef, 0xbf],
+        &[0xf7, 0xbf, 0xbf],
+        &[0xfb, 0xbf, 0xbf, 0xbf],
+        &[0xfd, 0xbf, 0xbf, 0xbf, 0xbf],
+    ];
+    for bytes in cut {
+        add("3.3", bytes, false);
+    }
+    add("3.4", &cut.concat(), false);
+    // 3.5: bytes that never appear.
+    add("3.5.1", &[0xfe], false);
+    add("3.5.2", &[0xff], false);
+    add("3.5.3", &[0xfe, 0xfe, 0xff, 0xff], false);
+    // 4.1-4.3: overlong '/', maximal overlongs, overlong NUL.
+    let overlongs: &[&[u8]] = &[
+        &[0xc0, 0xaf],
+        &[0xe0, 0x80, 0xaf],
+        &[0xf0, 0x80, 0x80, 0xaf],
+        &[0xf8, 0x80, 0x80, 0x80, 0xaf],
+        &[0xfc, 0x80, 0x80, 0x80, 0x80, 0xaf],
+        &[0xc1, 0xbf],
+        &[0xe0, 0x9f, 0xbf],
+        &[0xf0, 0x8f, 0xbf, 0xbf],
+        &[0xf8, 0x87, 0xbf, 0xbf, 0xbf],
+        &[0xfc, 0x83, 0xbf, 0xbf, 0xbf, 0xbf],
+        &[0xc0, 0x80],
+        &[0xe0, 0x80, 0x80],
+        &[0xf0, 0x80, 0x80, 0x80],
+        &[0xf8, 0x80, 0x80, 0x80, 0x80],
+        &[0xfc, 0x80, 0x80, 0x80, 0x80, 0x80],
+    ];
+    for bytes in overlongs {
+        add("4", bytes, false);
+    }
+    // 5.1-5.2: single and paired UTF-16 surrogates.
+    let surrogates: [[u8; 3]; 7] = [
+        [0xed, 0xa0, 0x80],
+        [0xed, 0xad, 0xbf],
+        [0xed, 0xae, 0x80],
+        [0xed, 0xaf, 0xbf],
+        [0xed, 0xb0, 0x80],
+        [0xed, 0xbe, 0x80],
+        [0xed, 0xbf, 0xbf],
+    ];
+    for bytes in surrogates.iter() {
+        add("5.1", bytes, false);
+    }
+    for high in &surrogates[..4] {
+        for low in [surrogates[4], surrogates[6]].iter() {
+            add("5.2", &[&high[..], &low[..]].concat(), false);
+        }
+    }
+    // 5.3: noncharacters, which are well-formed.
+    let mut noncharacters: Vec<u32> = (0xfdd0..=0xfdef).collect();
+    for plane in 0..=0x10 {
+        noncharacters.extend([plane << 16 | 0xfffe, plane << 16 | 0xffff].iter());
+    }
+    for code_point in noncharacters {
+        let c = char::from_u32(code_point).unwrap();
+        add("5.3", c.to_string().as_bytes(), true);
+    }
+    cases
+}
+
+/// The lines of Kuhn's stress test: each case quoted in ASCII text, as in
+/// the file, with whether it is well-formed.
+pub(crate) fn kuhn_lines() -> Vec<(Vec<u8>, bool)> {
+    kuhn_cases()
+        .into_iter()
+        .map(|(section, bytes, valid)| {
+            let mut line = format!("{}  test case: \"", section).into_bytes();
+            line.extend_from_slice(&bytes);
+            line.extend_from_slice(b"\"    |\n");
+            (line, valid)
+        })
+        .collect()
+}
@@ -0,0 +1,822 @@
+use core::fmt;
+use core::iter::FusedIterator;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+#[cfg(feature = "std")]
+use crate::canonical::LoneSurrogates;
+
+const REPLACEMENT: char = '\u{fffd}';
+
+/// What went wrong in UTF-16 input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Utf16Error {
+    /// A lead surrogate at the end of the input.
+    LeadAtEnd(u16),
+    /// A lead surrogate followed by a code unit that is not a trail
+    /// surrogate.
+    MissingTrail(u16),
+    /// A trail surrogate without a lead surrogate before it.
+    UnexpectedTrail(u16),
+    /// The input ended in the middle of a code unit.
+    OddLength,
+}
+
+impl Utf16Error {
+    /// The surrogate that is not part of a pair, if that is the error.
+    #[inline]
+    pub const fn unpaired_surrogate(&self) -> Option<u16> {
+        match *self {
+            Utf16Error::LeadAtEnd(unit)
+            | Utf16Error::MissingTrail(unit)
+            | Utf16Error::UnexpectedTrail(unit) => Some(unit),
+            Utf16Error::OddLength => None,
+        }
+    }
+}
+
+/// A [`Utf16Error`] together with the index of the code unit that caused it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Utf16ErrorAt {
+    index: usize,
+    kind: Utf16Error,
+}
+
+impl Utf16ErrorAt {
+    #[inline]
+    pub(crate) const fn new(index: usize, kind: Utf16Error) -> Self {
+        Utf16ErrorAt { index, kind }
+    }
+
+    /// Index of the offending code unit, counting the BOM if there is one.
+    #[inline]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    #[inline]
+    pub const fn kind(&self) -> Utf16Error {
+        self.kind
+    }
+}
+
+impl fmt::Display for Utf16ErrorAt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Utf16Error::LeadAtEnd(unit) => write!(
+                f,
+                "lead surrogate {:#06x} at the end of the input at code unit {}",
+                unit, self.index
+            ),
+            Utf16Error::MissingTrail(unit) => write!(
+                f,
+                "lead surrogate {:#06x} without a trail surrogate at code unit {}",
+                unit, self.index
+            ),
+            Utf16Error::UnexpectedTrail(unit) => write!(
+                f,
+                "trail surrogate {:#06x} without a lead surrogate at code unit {}",
+                unit, self.index
+            ),
+            Utf16Error::OddLength => write!(f, "truncated code unit at code unit {}", self.index),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for Utf16ErrorAt {}
+
+#[cfg(feature = "std")]
+impl From<Utf16ErrorAt> for io::Error {
+    fn from(err: Utf16ErrorAt) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Decodes the character starting with code unit `first`, given the unit
+/// after it, if any. Returns the character and the number of units used.
+#[inline]
+pub(crate) fn decode_pair(first: u16, second: Option<u16>) -> Result<(char, usize), Utf16Error> {
+    match first {
+        0xd800..=0xdbff => match second {
+            Some(low @ 0xdc00..=0xdfff) => {
+                let code_point = 0x10000 + ((first as u32 - 0xd800) << 10 | (low as u32 - 0xdc00));
+                char::from_u32(code_point)
+                    .map(|c| (c, 2))
+                    .ok_or(Utf16Error::MissingTrail(first))
+            }
+            Some(_) => Err(Utf16Error::MissingTrail(first)),
+            None => Err(Utf16Error::LeadAtEnd(first)),
+        },
+        0xdc00..=0xdfff => Err(Utf16Error::UnexpectedTrail(first)),
+        _ => char::from_u32(first as u32)
+            .map(|c| (c, 1))
+            .ok_or(Utf16Error::UnexpectedTrail(first)),
+    }
+}
+
+/// Items of a code unit iterator accepted by [`validate_next_utf16`].
+pub trait AsUnit<T> {
+    fn as_unit(&self) -> T;
+}
+
+impl AsUnit<u16> for u16 {
+    #[inline]
+    fn as_unit(&self) -> u16 {
+        *self
+    }
+}
+
+impl AsUnit<u16> for &u16 {
+    #[inline]
+    fn as_unit(&self) -> u16 {
+        **self
+    }
+}
+
+/// Decodes the next character from `it`, combining a surrogate pair into
+/// one code point, or returns `None` once `it` is exhausted.
+///
+/// Looks ahead on a clone of `it`, so a unit that turns out not to be the
+/// trail surrogate of a pair is not consumed: every error consumes exactly
+/// the one surrogate it reports.
+pub fn validate_next_utf16<I>(it: &mut I) -> Option<Result<u32, Utf16Error>>
+where
+    I: Iterator + Clone,
+    I::Item: AsUnit<u16>,
+{
+    let first = it.next()?.as_unit();
+    let second = match first {
+        0xd800..=0xdbff => it.clone().next().map(|unit| unit.as_unit()),
+        _ => None,
+    };
+    Some(decode_pair(first, second).map(|(c, units)| {
+        if units == 2 {
+            it.next();
+        }
+        c as u32
+    }))
+}
+
+/// Iterator over the characters of a sequence of UTF-16 code units, created
+/// by [`utf16_chars`].
+///
+/// Unlike [`char::decode_utf16`], errors carry the index of the unpaired
+/// surrogate. Decoding resumes with the next code unit after an error, so
+/// the iterator is only fused if `I` is.
+#[derive(Clone, Debug)]
+pub struct Utf16Chars<I> {
+    units: I,
+    /// A unit read ahead that did not pair with the one before it.
+    pending: Option<u16>,
+    index: usize,
+}
+
+/// Decodes the code units yielded by `units`.
+pub fn utf16_chars<I: Iterator<Item = u16>>(units: I) -> Utf16Chars<I> {
+    Utf16Chars {
+        units,
+        pending: None,
+        index: 0,
+    }
+}
+
+impl<I: Iterator<Item = u16>> Utf16Chars<I> {
+    /// Replaces every unpaired surrogate with U+FFFD.
+    pub fn lossy(self) -> Utf16CharsLossy<I> {
+        Utf16CharsLossy { inner: self }
+    }
+}
+
+impl<I: Iterator<Item = u16>> Iterator for Utf16Chars<I> {
+    type Item = Result<char, Utf16ErrorAt>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.pending.take().or_else(|| self.units.next())?;
+        let second = match first {
+            0xd800..=0xdbff => self.units.next(),
+            _ => None,
+        };
+        let index = self.index;
+        match decode_pair(first, second) {
+            Ok((c, units)) => {
+                self.index = index.saturating_add(units);
+                Some(Ok(c))
+            }
+            Err(kind) => {
+                self.pending = second;
+                self.index = index.saturating_add(1);
+                Some(Err(Utf16ErrorAt::new(index, kind)))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (low, high) = self.units.size_hint();
+        let pending = self.pending.is_some() as usize;
+        (
+            low.saturating_add(pending).div_ceil(2),
+            high.and_then(|high| high.checked_add(pending)),
+        )
+    }
+}
+
+impl<I: FusedIterator<Item = u16>> FusedIterator for Utf16Chars<I> {}
+
+/// Iterator returned by [`Utf16Chars::lossy`].
+#[derive(Clone, Debug)]
+pub struct Utf16CharsLossy<I> {
+    inner: Utf16Chars<I>,
+}
+
+impl<I: Iterator<Item = u16>> Iterator for Utf16CharsLossy<I> {
+    type Item = char;
+
+    #[inline]
+    fn next(&mut self) -> Option<char> {
+        self.inner.next().map(|c| c.unwrap_or(REPLACEMENT))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I: FusedIterator<Item = u16>> FusedIterator for Utf16CharsLossy<I> {}
+
+/// Byte order of UTF-16 input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// Whether any of the four code units in `word` is a surrogate.
+#[inline]
+fn has_surrogate(word: u64) -> bool {
+    const LANES: u64 = 0x0001_0001_0001_0001;
+    // A lane is zero exactly where its unit is in D800..=DFFF.
+    let x = (word & (0xf800 * LANES)) ^ (0xd800 * LANES);
+    x.wrapping_sub(LANES) & !x & (0x8000 * LANES) != 0
+}
+
+/// The first error [`utf16_chars`] finds, one code unit at a time: the
+/// reference for [`validate_utf16`].
+pub(crate) fn validate_utf16_scalar(bytes: &[u8], endian: Endian) -> Result<(), Utf16ErrorAt> {
+    let units = bytes.chunks_exact(2).map(|pair| match endian {
+        Endian::Little => u16::from_le_bytes([pair[0], pair[1]]),
+        Endian::Big => u16::from_be_bytes([pair[0], pair[1]]),
+    });
+    if let Some(Err(err)) = utf16_chars(units).find(Result::is_err) {
+        return Err(err);
+    }
+    if bytes.len() & 1 != 0 {
+        return Err(Utf16ErrorAt::new(bytes.len() / 2, Utf16Error::OddLength));
+    }
+    Ok(())
+}
+
+/// Checks four code units at a time where there are no surrogates, and the
+/// rest like [`validate_utf16_scalar`].
+pub(crate) fn validate_utf16(bytes: &[u8], endian: Endian) -> Result<(), Utf16ErrorAt> {
+    let units = bytes.len() / 2;
+    let unit = |i: usize| -> Option<u16> {
+        let pair = [*bytes.get(2 * i)?, *bytes.get(2 * i + 1)?];
+        Some(match endian {
+            Endian::Little => u16::from_le_bytes(pair),
+            Endian::Big => u16::from_be_bytes(pair),
+        })
+    };
+    let mut i = 0;
+    while i < units {
+        if i + 4 <= units {
+            // Lane order does not matter, so the bytes can be loaded as is.
+            let mut word = [0; 8];
+            word.copy_from_slice(&bytes[2 * i..2 * i + 8]);
+            let word = match endian {
+                Endian::Little => u64::from_le_bytes(word),
+                Endian::Big => u64::from_be_bytes(word),
+            };
+            if !has_surrogate(word) {
+                i += 4;
+                continue;
+            }
+        }
+        // `i < units`, so the unit exists.
+        let first = unit(i).unwrap_or(0);
+        match decode_pair(first, unit(i + 1)) {
+            Ok((_, length)) => i += length,
+            Err(kind) => return Err(Utf16ErrorAt::new(i, kind)),
+        }
+    }
+    if 2 * units != bytes.len() {
+        return Err(Utf16ErrorAt::new(units, Utf16Error::OddLength));
+    }
+    Ok(())
+}
+
+/// Checks that `bytes` is valid little-endian UTF-16, four code units at a
+/// time where there are no surrogates.
+///
+/// A BOM is treated as a character; error indices count code units.
+pub fn validate_utf16le(bytes: &[u8]) -> Result<(), Utf16ErrorAt> {
+    validate_utf16(bytes, Endian::Little)
+}
+
+/// Big-endian counterpart of [`validate_utf16le`].
+pub fn validate_utf16be(bytes: &[u8]) -> Result<(), Utf16ErrorAt> {
+    validate_utf16(bytes, Endian::Big)
+}
+
+#[cfg(feature = "std")]
+const BUF_LEN: usize = 4096;
+
+/// A reader that transcodes UTF-16 from the inner reader to UTF-8.
+///
+/// A leading BOM selects the byte order and is dropped; without one the
+/// byte order given to [`with_endian`](Self::with_endian) is used, little
+/// endian by default. Errors are reported as [`io::ErrorKind::InvalidData`]
+/// wrapping a [`Utf16ErrorAt`], and every read after an error fails again.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Utf16Reader<R> {
+    inner: R,
+    endian: Endian,
+    lone_surrogates: LoneSurrogates,
+    sniffed: bool,
+    raw: [u8; BUF_LEN],
+    start: usize,
+    end: usize,
+    eof: bool,
+    /// Index of the code unit at `raw[start]`.
+    index: usize,
+    /// UTF-8 of a character that did not fit into the caller's buffer.
+    out: [u8; 4],
+    out_start: usize,
+    out_end: usize,
+    error: Option<Utf16ErrorAt>,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Utf16Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Utf16Reader {
+            inner,
+            endian: Endian::Little,
+            lone_surrogates: LoneSurrogates::Reject,
+            sniffed: false,
+            raw: [0; BUF_LEN],
+            start: 0,
+            end: 0,
+            eof: false,
+            index: 0,
+            out: [0; 4],
+            out_start: 0,
+            out_end: 0,
+            error: None,
+        }
+    }
+
+    /// Byte order of input without a BOM.
+    pub fn with_endian(mut self, endian: Endian) -> Self {
+        self.endian = endian;
+        self
+    }
+
+    pub fn lone_surrogates(mut self, lone_surrogates: LoneSurrogates) -> Self {
+        self.lone_surrogates = lone_surrogates;
+        self
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads until at least `n` raw bytes are buffered or EOF is reached.
+    fn fill(&mut self, n: usize) -> io::Result<()> {
+        while self.end - self.start < n && !self.eof {
+            if self.start != 0 {
+                self.raw.copy_within(self.start..self.end, 0);
+                self.end -= self.start;
+                self.start = 0;
+            }
+            match self.inner.read(&mut self.raw[self.end..]) {
+                Ok(0) => self.eof = true,
+                Ok(read) => self.end += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn unit_at(&self, unit: usize) -> Option<u16> {
+        let pos = self.start + 2 * unit;
+        if pos + 2 > self.end {
+            return None;
+        }
+        let bytes = [self.raw[pos], self.raw[pos + 1]];
+        Some(match self.endian {
+            Endian::Little => u16::from_le_bytes(bytes),
+            Endian::Big => u16::from_be_bytes(bytes),
+        })
+    }
+
+    #[inline]
+    fn consume(&mut self, units: usize) {
+        self.start += 2 * units;
+        self.index += units;
+    }
+
+    /// Decodes the next character, reading from the inner reader only if
+    /// `may_read` is set. `Ok(None)` means EOF, or that more input is needed.
+    fn next_char(&mut self, may_read: bool) -> io::Result<Option<char>> {
+        if may_read {
+            self.fill(4)?;
+        }
+        if !self.sniffed {
+            if self.end - self.start < 2 && !self.eof {
+                return Ok(None);
+            }
+            self.sniffed = true;
+            match self.raw[self.start..self.end] {
+                [0xff, 0xfe, ..] => self.endian = Endian::Little,
+                [0xfe, 0xff, ..] => self.endian = Endian::Big,
+                _ => {}
+            }
+            if self.unit_at(0) == Some(0xfeff) {
+                self.consume(1);
+            }
+            return self.next_char(may_read);
+        }
+        let first = match self.unit_at(0) {
+            Some(first) => first,
+            None if self.eof && self.start != self.end => {
+                return Err(Utf16ErrorAt::new(self.index, Utf16Error::OddLength).into())
+            }
+            None => return Ok(None),
+        };
+        let second = self.unit_at(1);
+        if second.is_none() && !self.eof && (0xd800..=0xdbff).contains(&first) {
+            // The low surrogate may still come.
+            return Ok(None);
+        }
+        match decode_pair(first, second) {
+            Ok((c, units)) => {
+                self.consume(units);
+                Ok(Some(c))
+            }
+            Err(kind) => match self.lone_surrogates {
+                LoneSurrogates::Reject => Err(Utf16ErrorAt::new(self.index, kind).into()),
+                LoneSurrogates::Replace => {
+                    self.consume(1);
+                    Ok(Some(REPLACEMENT))
+                }
+            },
+        }
+    }
+
+    fn read_inner(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        if self.out_start < self.out_end {
+            n = (self.out_end - self.out_start).min(buf.len());
+            buf[..n].copy_from_slice(&self.out[self.out_start..self.out_start + n]);
+            self.out_start += n;
+        }
+        while n < buf.len() {
+            let c = match self.next_char(n == 0) {
+                Ok(Some(c)) => c,
+                Ok(None) => break,
+                // Hand out what was decoded first.
+                Err(_) if n != 0 => break,
+                Err(err) => return Err(err),
+            };
+            let len = c.len_utf8();
+            if n + len <= buf.len() {
+                c.encode_utf8(&mut buf[n..]);
+                n += len;
+            } else {
+                c.encode_utf8(&mut self.out);
+                let fits = buf.len() - n;
+                buf[n..].copy_from_slice(&self.out[..fits]);
+                self.out_start = fits;
+                self.out_end = len;
+                n = buf.len();
+            }
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for Utf16Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(err) = self.error {
+            return Err(err.into());
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let result = self.read_inner(buf);
+        if let Err(err) = &result {
+            self.error = err
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<Utf16ErrorAt>())
+                .copied();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test_utf16 {
+    use super::*;
+    use crate::test_util::XorShift;
+
+    #[test]
+    fn test_utf16_chars_matches_std() {
+        let text = "plain, é€ข, and 😀𒀀 astral";
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let decoded: Vec<_> = utf16_chars(units.iter().copied()).collect();
+        let expected: Vec<_> = text.chars().map(Ok).collect();
+        assert_eq!(decoded, expected);
+        let lossy: String = utf16_chars(units.iter().copied()).lossy().collect();
+        assert_eq!(lossy, text);
+    }
+
+    #[test]
+    fn test_validate_next_utf16_round_trip() {
+        let text = "a\u{7ff}€\u{d7ff}\u{e000}\u{ffff}😀\u{10000}\u{10ffff}";
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let mut it = units.iter();
+        let mut decoded = String::new();
+        while let Some(result) = validate_next_utf16(&mut it) {
+            decoded.push(char::from_u32(result.unwrap()).unwrap());
+        }
+        assert_eq!(decoded, text);
+        // By value as well.
+        let mut it = text.encode_utf16();
+        assert_eq!(validate_next_utf16(&mut it), Some(Ok(0x61)));
+        assert_eq!(it.count(), units.len() - 1);
+    }
+
+    #[test]
+    fn test_validate_next_utf16_errors() {
+        let cases: [(&[u16], Utf16Error, usize); 5] = [
+            (&[0xd83d], Utf16Error::LeadAtEnd(0xd83d), 0),
+            (&[0xd83d, 0x61], Utf16Error::MissingTrail(0xd83d), 1),
+            (
+                &[0xdbff, 0xdbff, 0xdfff],
+                Utf16Error::MissingTrail(0xdbff),
+                2,
+            ),
+            (&[0xde00, 0x61], Utf16Error::UnexpectedTrail(0xde00), 1),
+            (&[0xdc00, 0xd800], Utf16Error::UnexpectedTrail(0xdc00), 1),
+        ];
+        for (units, kind, left) in cases.iter() {
+            let mut it = units.iter();
+            assert_eq!(
+                validate_next_utf16(&mut it),
+                Some(Err(*kind)),
+                "{:x?}",
+                units
+            );
+            // Only the reported surrogate was consumed.
+            assert_eq!(it.len(), *left);
+            assert_eq!(kind.unpaired_surrogate(), Some(units[0]));
+        }
+        let mut it = [0xdbffu16, 0xdbff, 0xdfff].iter();
+        validate_next_utf16(&mut it);
+        assert_eq!(validate_next_utf16(&mut it), Some(Ok(0x10ffff)));
+        assert_eq!(validate_next_utf16(&mut it), None);
+        assert_eq!(Utf16Error::OddLength.unpaired_surrogate(), None);
+    }
+
+    #[test]
+    fn test_utf16_chars_lone_surrogates() {
+        let units: Vec<u16> = "a😀b".encode_utf16().collect();
+        let (high, low) = (units[1], units[2]);
+        // A lone high or low surrogate at every position of "abc".
+        for surrogate in [high, low].iter() {
+            for pos in 0..=3 {
+                let mut input: Vec<u16> = "abc".encode_utf16().collect();
+                input.insert(pos, *surrogate);
+                let decoded: Vec<_> = utf16_chars(input.iter().copied()).collect();
+                let expected: Vec<_> = std::char::decode_utf16(input.iter().copied())
+                    .map(|c| c.map_err(|e| e.unpaired_surrogate()))
+                    .collect();
+                let unpaired = match *surrogate {
+                    0xdc00..=0xdfff => Utf16Error::UnexpectedTrail(*surrogate),
+                    _ if pos == 3 => Utf16Error::LeadAtEnd(*surrogate),
+                    _ => Utf16Error::MissingTrail(*surrogate),
+                };
+                for (i, (got, want)) in decoded.iter().zip(expected.iter()).enumerate() {
+                    match want {
+                        Ok(c) => assert_eq!(*got, Ok(*c)),
+                        Err(_) => assert_eq!(*got, Err(Utf16ErrorAt::new(i, unpaired))),
+                    }
+                }
+                assert_eq!(decoded.len(), expected.len());
+            }
+        }
+        // Two high surrogates in a row: both are unpaired, then a pair follows.
+        let input = [high, high, high, low];
+        let decoded: Vec<_> = utf16_chars(input.iter().copied()).collect();
+        let unpaired = Utf16Error::MissingTrail(high);
+        assert_eq!(
+            decoded,
+            [
+                Err(Utf16ErrorAt::new(0, unpaired)),
+                Err(Utf16ErrorAt::new(1, unpaired)),
+                Ok('😀'),
+            ]
+        );
+        let lossy: String = utf16_chars(input.iter().copied()).lossy().collect();
+        assert_eq!(lossy, "\u{fffd}\u{fffd}😀");
+    }
+
+    #[test]
+    fn test_utf16_chars_after_error_and_end() {
+        let input = [0x61, 0xdc00, 0x62];
+        let mut it = utf16_chars(input.iter().copied());
+        assert_eq!(it.next(), Some(Ok('a')));
+        assert!(it.next().unwrap().is_err());
+        assert_eq!(it.next(), Some(Ok('b')));
+        for _ in 0..10 {
+            assert_eq!(it.next(), None);
+        }
+        let mut it = utf16_chars(input.iter().copied()).lossy();
+        assert_eq!(it.by_ref().count(), 3);
+        assert_eq!(it.next(), None);
+    }
+
+    fn encode(units: &[u16], endian: Endian) -> Vec<u8> {
+        units
+            .iter()
+            .flat_map(|unit| match endian {
+                Endian::Little => unit.to_le_bytes(),
+                Endian::Big => unit.to_be_bytes(),
+            })
+            .collect()
+    }
+
+    fn check_both(units: &[u16]) {
+        for endian in [Endian::Little, Endian::Big].iter() {
+            let bytes = encode(units, *endian);
+            let fast = match endian {
+                Endian::Little => validate_utf16le(&bytes),
+                Endian::Big => validate_utf16be(&bytes),
+            };
+            assert_eq!(fast, validate_utf16_scalar(&bytes, *endian), "{:x?}", units);
+            let odd = &bytes[..bytes.len().saturating_sub(1)];
+            let fast = validate_utf16(odd, *endian);
+            assert_eq!(fast, validate_utf16_scalar(odd, *endian), "{:x?}", units);
+        }
+    }
+
+    #[test]
+    fn test_validate_utf16_block_boundaries() {
+        let (high, low) = (0xd83d, 0xde00);
+        for pad in 0..12 {
+            let mut units = vec![0x61; pad];
+            units.extend_from_slice(&[high, low]);
+            units.extend_from_slice(&[0x62; 9]);
+            check_both(&units);
+            assert_eq!(validate_utf16le(&encode(&units, Endian::Little)), Ok(()));
+            // Each half on its own, and the pair in the wrong order.
+            for bad in [[0x61, low], [high, 0x61], [low, high]].iter() {
+                units[pad..pad + 2].copy_from_slice(bad);
+                check_both(&units);
+                assert!(validate_utf16be(&encode(&units, Endian::Big)).is_err());
+            }
+        }
+        // Units that look like surrogates only in the other byte order.
+        check_both(&[0x00d8, 0x00dc, 0x61, 0x62, 0xd8d8, 0x00d8, 0x0dc0, 0x0000]);
+    }
+
+    #[test]
+    fn test_validate_utf16_matches_reference() {
+        let mut rng = XorShift::new(243);
+        let interesting = [
+            0x0061, 0x00e9, 0xd83d, 0xdbff, 0xdc00, 0xde00, 0xfeff, 0xffff,
+        ];
+        for _ in 0..5000 {
+            let len = rng.below(24);
+            let units: Vec<u16> = (0..len)
+                .map(|_| interesting[rng.below(interesting.len())])
+                .collect();
+            check_both(&units);
+        }
+        assert_eq!(
+            validate_utf16le(&[0x61, 0x00, 0x62]),
+            Err(Utf16ErrorAt::new(1, Utf16Error::OddLength))
+        );
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test_utf16_reader {
+    use super::*;
+
+    /// Returns one byte per read.
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((first, rest)) if !buf.is_empty() => {
+                    buf[0] = *first;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn le_with_bom(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    }
+
+    fn read_all<R: Read>(mut reader: R, size: usize) -> io::Result<String> {
+        let mut out = Vec::new();
+        let mut buf = vec![0; size];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                return Ok(String::from_utf8(out).unwrap());
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    fn error_of(err: io::Error) -> Utf16ErrorAt {
+        *err.get_ref()
+            .unwrap()
+            .downcast_ref::<Utf16ErrorAt>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_utf16_reader_emoji() {
+        let text = "emoji 😀😁 and é€ข, ".repeat(300);
+        let input = le_with_bom(&text);
+        for size in 1..8 {
+            let reader = Utf16Reader::new(OneByte(&input));
+            assert_eq!(read_all(reader, size).unwrap(), text);
+        }
+        assert_eq!(read_all(Utf16Reader::new(&input[..]), 4096).unwrap(), text);
+
+        let mut big_endian = vec![0xfe, 0xff];
+        big_endian.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(
+            read_all(Utf16Reader::new(&big_endian[..]), 100).unwrap(),
+            text
+        );
+        let no_bom = &big_endian[2..];
+        let reader = Utf16Reader::new(no_bom).with_endian(Endian::Big);
+        assert_eq!(read_all(reader, 100).unwrap(), text);
+    }
+
+    #[test]
+    fn test_utf16_reader_odd_length() {
+        let mut input = le_with_bom("ab");
+        input.push(0x63);
+        let mut reader = Utf16Reader::new(&input[..]);
+        let mut buf = [0; 16];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        let err = error_of(reader.read(&mut buf).unwrap_err());
+        assert_eq!((err.index(), err.kind()), (3, Utf16Error::OddLength));
+        assert!(reader.read(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_utf16_reader_lone_surrogate() {
+        let mut input = le_with_bom("a");
+        input.extend_from_slice(&0xd83du16.to_le_bytes());
+        input.extend_from_slice(&u16::to_le_bytes(b'b' as u16));
+        let err = error_of(read_all(Utf16Reader::new(OneByte(&input)), 8).unwrap_err());
+        assert_eq!(
+            (err.index(), err.kind()),
+            (2, Utf16Error::MissingTrail(0xd83d))
+        );
+
+        let reader = Utf16Reader::new(OneByte(&input)).lone_surrogates(LoneSurrogates::Replace);
+        assert_eq!(read_all(reader, 8).unwrap(), "a\u{fffd}b");
+        // A high surrogate at the very end.
+        let reader = Utf16Reader::new(&input[..6]).lone_surrogates(LoneSurrogates::Replace);
+        assert_eq!(read_all(reader, 8).unwrap(), "a\u{fffd}");
+    }
+}
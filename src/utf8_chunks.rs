@@ -0,0 +1,168 @@
+use core::iter::FusedIterator;
+
+use crate::core::{decode_step, UtfError};
+
+/// A valid run of a byte slice and the maximal ill-formed subsequence that
+/// follows it, yielded by [`Utf8Chunks`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Utf8Chunk<'a> {
+    valid: &'a str,
+    invalid: &'a [u8],
+    error: Option<UtfError>,
+}
+
+impl<'a> Utf8Chunk<'a> {
+    /// The valid text before [`invalid`](Self::invalid), possibly empty.
+    #[inline]
+    pub fn valid(&self) -> &'a str {
+        self.valid
+    }
+
+    /// The 1 to 3 bytes of the ill-formed subsequence, empty only for the
+    /// last chunk when the input ends with valid text.
+    #[inline]
+    pub fn invalid(&self) -> &'a [u8] {
+        self.invalid
+    }
+
+    /// What is wrong with [`invalid`](Self::invalid), if it is not empty.
+    #[inline]
+    pub fn error(&self) -> Option<UtfError> {
+        self.error
+    }
+}
+
+/// Iterator over the valid and invalid parts of a byte slice, created by
+/// [`utf8_chunks`].
+///
+/// Unlike the character iterators it does not stop at the first error, and
+/// the invalid parts are split the way lossy decoding replaces them.
+#[derive(Clone, Debug)]
+pub struct Utf8Chunks<'a> {
+    bytes: &'a [u8],
+}
+
+/// Splits `bytes` into valid text and invalid byte runs, like
+/// `<[u8]>::utf8_chunks` but with the kind of each error.
+pub fn utf8_chunks(bytes: &[u8]) -> Utf8Chunks<'_> {
+    Utf8Chunks { bytes }
+}
+
+impl<'a> Utf8Chunks<'a> {
+    /// The bytes that have not been split yet.
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl<'a> Iterator for Utf8Chunks<'a> {
+    type Item = Utf8Chunk<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let mut pos = 0;
+        let mut invalid_len = 0;
+        let mut error = None;
+        while pos < self.bytes.len() {
+            if self.bytes[pos] < 0x80 {
+                pos += 1;
+                continue;
+            }
+            match decode_step(&self.bytes[pos..]) {
+                Ok((_, length)) => pos += length,
+                Err(err) => {
+                    invalid_len = err.skip_len();
+                    error = Some(err.kind());
+                    break;
+                }
+            }
+        }
+        let (valid, rest) = self.bytes.split_at(pos);
+        let (invalid, rest) = rest.split_at(invalid_len);
+        self.bytes = rest;
+        debug_assert!(core::str::from_utf8(valid).is_ok());
+        // SAFETY: every byte of `valid` went through `decode_step`.
+        let valid = unsafe { core::str::from_utf8_unchecked(valid) };
+        Some(Utf8Chunk {
+            valid,
+            invalid,
+            error,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.bytes.len();
+        (usize::from(len != 0), Some(len))
+    }
+}
+
+impl FusedIterator for Utf8Chunks<'_> {}
+
+#[cfg(test)]
+mod test_utf8_chunks {
+    use super::*;
+    use crate::invalid_ranges::invalid_ranges;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    fn parts(bytes: &[u8]) -> Vec<(&str, &[u8], Option<UtfError>)> {
+        utf8_chunks(bytes)
+            .map(|chunk| (chunk.valid(), chunk.invalid(), chunk.error()))
+            .collect()
+    }
+
+    #[test]
+    fn test_utf8_chunks_fixtures() {
+        assert_eq!(parts(b""), []);
+        assert_eq!(parts("aé€".as_bytes()), [("aé€", &b""[..], None)]);
+        assert_eq!(
+            parts(b"ab\xe0\x80cd\xf0\x9f\x98"),
+            [
+                ("ab", &b"\xe0"[..], Some(UtfError::OverlongSequence)),
+                ("", &b"\x80"[..], Some(UtfError::InvalidLead)),
+                ("cd", &b"\xf0\x9f\x98"[..], Some(UtfError::NotEnoughRoom)),
+            ]
+        );
+        assert_eq!(
+            parts(b"\xff\xffz"),
+            [
+                ("", &b"\xff"[..], Some(UtfError::InvalidLead)),
+                ("", &b"\xff"[..], Some(UtfError::InvalidLead)),
+                ("z", &b""[..], None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_utf8_chunks_match_std_lossy() {
+        let mut rng = XorShift::new(273);
+        for _ in 0..2000 {
+            let fragments = rng.below(30);
+            let input = random_corrupt(&mut rng, fragments);
+            let mut joined = Vec::new();
+            let mut lossy = String::new();
+            let mut errors = Vec::new();
+            let mut offset = 0;
+            let mut it = utf8_chunks(&input);
+            while let Some(chunk) = it.next() {
+                joined.extend_from_slice(chunk.valid().as_bytes());
+                joined.extend_from_slice(chunk.invalid());
+                lossy.push_str(chunk.valid());
+                offset += chunk.valid().len();
+                if let Some(kind) = chunk.error() {
+                    lossy.push('\u{fffd}');
+                    errors.push((offset..offset + chunk.invalid().len(), kind));
+                } else {
+                    assert!(chunk.invalid().is_empty());
+                    assert!(it.as_bytes().is_empty());
+                }
+                offset += chunk.invalid().len();
+            }
+            assert_eq!(joined, input);
+            assert_eq!(lossy, String::from_utf8_lossy(&input), "{:x?}", input);
+            assert_eq!(errors, invalid_ranges(&input).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use core::borrow::Borrow;
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::Deref;
+
+use crate::error::Utf8ErrorAt;
+use crate::validate::validate;
+
+/// A string slice known to have been validated by this crate.
+///
+/// Compares, orders and hashes like the `str` it wraps, so `&str` can look
+/// up `ValidStr` keys in maps.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ValidStr<'a>(&'a str);
+
+impl<'a> ValidStr<'a> {
+    /// Validates `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Utf8ErrorAt> {
+        validate(bytes)?;
+        debug_assert!(core::str::from_utf8(bytes).is_ok());
+        // SAFETY: `bytes` was just validated.
+        Ok(ValidStr(unsafe { core::str::from_utf8_unchecked(bytes) }))
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for ValidStr<'a> {
+    type Error = Utf8ErrorAt;
+
+    #[inline]
+    fn try_from(bytes: &'a [u8]) -> Result<Self, Utf8ErrorAt> {
+        ValidStr::new(bytes)
+    }
+}
+
+impl<'a> From<&'a str> for ValidStr<'a> {
+    #[inline]
+    fn from(s: &'a str) -> Self {
+        ValidStr(s)
+    }
+}
+
+impl<'a> From<ValidStr<'a>> for &'a str {
+    #[inline]
+    fn from(s: ValidStr<'a>) -> Self {
+        s.0
+    }
+}
+
+impl Deref for ValidStr<'_> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+impl AsRef<str> for ValidStr<'_> {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl AsRef<[u8]> for ValidStr<'_> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl Borrow<str> for ValidStr<'_> {
+    #[inline]
+    fn borrow(&self) -> &str {
+        self.0
+    }
+}
+
+impl fmt::Display for ValidStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.0, f)
+    }
+}
+
+impl PartialEq<str> for ValidStr<'_> {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for ValidStr<'_> {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<ValidStr<'_>> for str {
+    #[inline]
+    fn eq(&self, other: &ValidStr<'_>) -> bool {
+        self == other.0
+    }
+}
+
+impl PartialEq<ValidStr<'_>> for &str {
+    #[inline]
+    fn eq(&self, other: &ValidStr<'_>) -> bool {
+        *self == other.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ValidStr<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+#[cfg(test)]
+mod test_valid_str {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::hash::{Hash, Hasher};
+
+    use super::*;
+    use crate::core::UtfError;
+
+    fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_valid_str_as_map_key() {
+        let mut map = HashMap::new();
+        map.insert(ValidStr::new("clé".as_bytes()).unwrap(), 1);
+        map.insert(ValidStr::from("😀"), 2);
+        assert_eq!(map.get("clé"), Some(&1));
+        assert_eq!(map.get("😀"), Some(&2));
+        assert_eq!(map.get("cle"), None);
+        let key = ValidStr::from("€uro");
+        assert_eq!(hash_of(&key), hash_of("€uro"));
+    }
+
+    #[test]
+    fn test_valid_str_conversions() {
+        let s = ValidStr::try_from("aé€".as_bytes()).unwrap();
+        assert_eq!(format!("{}", s), "aé€");
+        assert_eq!(format!("[{:>5}]", s), "[  aé€]");
+        assert_eq!(s.len(), 6);
+        assert_eq!(s, "aé€");
+        assert_eq!("aé€", s);
+        assert_eq!(s, *"aé€");
+        assert_eq!(*"aé€", s);
+        assert_ne!(s, "a");
+        let bytes: &[u8] = s.as_ref();
+        assert_eq!(bytes, "aé€".as_bytes());
+        let back: &str = s.into();
+        assert_eq!(back, "aé€");
+        assert!(ValidStr::from("a") < ValidStr::from("b"));
+        assert_eq!(
+            ValidStr::from("ab").cmp(&ValidStr::from("a😀")),
+            "ab".cmp("a😀")
+        );
+
+        let err = ValidStr::try_from(&b"a\xff"[..]).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, UtfError::InvalidLead));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_valid_str_serialize() {
+        let s = ValidStr::from("quote \" and 😀");
+        assert_eq!(serde_json::to_string(&s).unwrap(), "\"quote \\\" and 😀\"");
+    }
+}
@@ -0,0 +1,265 @@
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use core::fmt;
+use core::ops::{Deref, Range};
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use crate::policy::{Policy, PolicyError};
+
+/// Error of the editing methods of [`ValidString`]. The string is left
+/// unchanged.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EditError {
+    /// The offset is past the end or inside a character.
+    NotCharBoundary { offset: usize },
+    /// A range whose end comes before its start.
+    InvalidRange { start: usize, end: usize },
+    /// The new bytes are not UTF-8 or break the policy; offsets are those
+    /// the bytes would have had in the string.
+    Policy(PolicyError),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::NotCharBoundary { offset } => {
+                write!(f, "byte {} is not a character boundary", offset)
+            }
+            EditError::InvalidRange { start, end } => {
+                write!(f, "range {}..{} ends before it starts", start, end)
+            }
+            EditError::Policy(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for EditError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EditError::Policy(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<PolicyError> for EditError {
+    fn from(err: PolicyError) -> Self {
+        EditError::Policy(err)
+    }
+}
+
+/// An owned string that follows a [`Policy`], and keeps following it
+/// through edits.
+///
+/// Edits only validate the new bytes, never the untouched rest.
+#[derive(Clone, Debug)]
+pub struct ValidString {
+    text: String,
+    policy: Policy,
+}
+
+impl ValidString {
+    /// An empty string following `policy`.
+    pub fn new(policy: Policy) -> Self {
+        ValidString {
+            text: String::new(),
+            policy,
+        }
+    }
+
+    /// Validates `bytes` against `policy`.
+    pub fn from_bytes(bytes: &[u8], policy: Policy) -> Result<Self, PolicyError> {
+        let text = policy.validate_at(bytes, 0)?.to_owned();
+        Ok(ValidString { text, policy })
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    #[inline]
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    fn check_boundary(&self, offset: usize) -> Result<(), EditError> {
+        if self.text.is_char_boundary(offset) {
+            Ok(())
+        } else {
+            Err(EditError::NotCharBoundary { offset })
+        }
+    }
+
+    /// Replaces the bytes in `byte_range`, which must start and end on
+    /// character boundaries, with `replacement`.
+    pub fn replace_range(
+        &mut self,
+        byte_range: Range<usize>,
+        replacement: &[u8],
+    ) -> Result<(), EditError> {
+        let Range { start, end } = byte_range;
+        if end < start {
+            return Err(EditError::InvalidRange { start, end });
+        }
+        self.check_boundary(start)?;
+        self.check_boundary(end)?;
+        let replacement = self.policy.validate_at(replacement, start)?;
+        // Only a character moved away from offset 0 can change its verdict.
+        if end == 0 && !replacement.is_empty() {
+            if let Some(c) = self.text.chars().next() {
+                self.policy.check_char(replacement.len(), c)?;
+            }
+        }
+        self.text.replace_range(start..end, replacement);
+        Ok(())
+    }
+
+    /// Inserts `bytes` at the character boundary `at`.
+    #[inline]
+    pub fn insert_bytes(&mut self, at: usize, bytes: &[u8]) -> Result<(), EditError> {
+        self.replace_range(at..at, bytes)
+    }
+
+    /// Removes and returns the character starting at `byte_offset`.
+    pub fn remove_char_at(&mut self, byte_offset: usize) -> Result<char, EditError> {
+        self.check_boundary(byte_offset)?;
+        match self.text[byte_offset..].chars().next() {
+            Some(_) => Ok(self.text.remove(byte_offset)),
+            None => Err(EditError::NotCharBoundary {
+                offset: byte_offset,
+            }),
+        }
+    }
+}
+
+impl Deref for ValidString {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl AsRef<str> for ValidString {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for ValidString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.text, f)
+    }
+}
+
+#[cfg(test)]
+mod test_valid_string {
+    use super::*;
+    use crate::core::UtfError;
+    use crate::policy::ValidatorBuilder;
+
+    fn strict() -> Policy {
+        ValidatorBuilder::new()
+            .reject_private_use(true)
+            .reject_interior_bom(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_replace_range_inside_char() {
+        let mut s = ValidString::from_bytes("a😀b".as_bytes(), strict()).unwrap();
+        for offset in 2..5 {
+            assert_eq!(
+                s.replace_range(offset..5, b"x"),
+                Err(EditError::NotCharBoundary { offset })
+            );
+            assert_eq!(
+                s.replace_range(0..offset, b"x"),
+                Err(EditError::NotCharBoundary { offset })
+            );
+        }
+        assert_eq!(
+            s.insert_bytes(7, b"x"),
+            Err(EditError::NotCharBoundary { offset: 7 })
+        );
+        let (start, end) = (3, 1);
+        assert_eq!(
+            s.replace_range(start..end, b"x"),
+            Err(EditError::InvalidRange { start: 3, end: 1 })
+        );
+        assert_eq!(s.as_str(), "a😀b");
+    }
+
+    #[test]
+    fn test_replace_range_policy_violation() {
+        let mut s = ValidString::from_bytes("\u{feff}abc".as_bytes(), strict()).unwrap();
+        let err = s.replace_range(4..5, "x\u{e000}".as_bytes()).unwrap_err();
+        assert_eq!(
+            err,
+            EditError::Policy(PolicyError::PrivateUse {
+                offset: 5,
+                code_point: 0xe000
+            })
+        );
+        let err = s.insert_bytes(4, "\u{feff}".as_bytes()).unwrap_err();
+        assert_eq!(
+            err,
+            EditError::Policy(PolicyError::InteriorBom { offset: 4 })
+        );
+        // The leading BOM would become interior.
+        let err = s.insert_bytes(0, b"x").unwrap_err();
+        assert_eq!(
+            err,
+            EditError::Policy(PolicyError::InteriorBom { offset: 1 })
+        );
+        match s.insert_bytes(5, b"\xe2\x82") {
+            Err(EditError::Policy(PolicyError::Utf8(err))) => {
+                assert_eq!((err.offset(), err.kind()), (5, UtfError::NotEnoughRoom))
+            }
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(s.as_str(), "\u{feff}abc");
+        // Replacing the BOM itself is fine.
+        s.replace_range(0..3, b"x").unwrap();
+        assert_eq!(s.as_str(), "xabc");
+    }
+
+    #[test]
+    fn test_edits_match_string() {
+        let mut s = ValidString::from_bytes("héllo wörld".as_bytes(), strict()).unwrap();
+        let mut expected = String::from("héllo wörld");
+        s.replace_range(1..3, "€€".as_bytes()).unwrap();
+        expected.replace_range(1..3, "€€");
+        s.insert_bytes(0, "😀".as_bytes()).unwrap();
+        expected.insert(0, '😀');
+        let len = s.len();
+        s.insert_bytes(len, b"!").unwrap();
+        expected.push('!');
+        assert_eq!(s.remove_char_at(4), Ok('h'));
+        expected.remove(4);
+        assert_eq!(s.remove_char_at(4), Ok('€'));
+        expected.remove(4);
+        assert_eq!(s.as_str(), expected);
+        let len = s.len();
+        assert_eq!(
+            s.remove_char_at(len),
+            Err(EditError::NotCharBoundary { offset: len })
+        );
+        assert_eq!(
+            s.remove_char_at(1),
+            Err(EditError::NotCharBoundary { offset: 1 })
+        );
+        assert_eq!(s.into_string(), expected);
+    }
+}
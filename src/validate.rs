@@ -0,0 +1,734 @@
+use crate::core::{decode_step, is_continuation, second_byte_range, sequence_length, UtfError};
+use crate::error::Utf8ErrorAt;
+
+const ASCII_MASK: u64 = 0x8080_8080_8080_8080;
+
+/// Length of the blocks checked by the fast pass of [`validate`].
+const BLOCK_LEN: usize = 4096;
+
+/// The kind of text [`validate_with_hint`] should be fastest for. Any input
+/// is validated correctly whatever the hint.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextHint {
+    /// Mostly ASCII, as most Western text; what [`validate`] assumes.
+    #[default]
+    Latin,
+    /// Mostly 3-byte characters, as Chinese, Japanese or Korean text.
+    Cjk,
+}
+
+/// Checks the non-ASCII character at `pos` of `block` for the fast pass,
+/// returning its length, or what [`check_block`] returns if it is invalid or
+/// cut short.
+#[inline]
+fn check_char(block: &[u8], pos: usize) -> Result<usize, Option<usize>> {
+    let lead = block[pos];
+    let length = sequence_length(lead);
+    if length == 0 {
+        return Err(None);
+    }
+    if pos + length > block.len() {
+        return Err(match decode_step(&block[pos..]) {
+            Err(err) if err.error_len().is_none() => Some(block.len() - pos),
+            _ => None,
+        });
+    }
+    let (lo, hi) = second_byte_range(lead);
+    if block[pos + 1] < lo || block[pos + 1] > hi {
+        return Err(None);
+    }
+    if !block[pos + 2..pos + length]
+        .iter()
+        .all(|&b| is_continuation(b))
+    {
+        return Err(None);
+    }
+    Ok(length)
+}
+
+/// Moves `pos` past the run of 8-byte ASCII words starting there.
+#[inline]
+fn skip_ascii_words(block: &[u8], mut pos: usize) -> usize {
+    // Two words per step while the run lasts.
+    while pos + 16 <= block.len() {
+        let mut words = [0; 16];
+        words.copy_from_slice(&block[pos..pos + 16]);
+        let (lo, hi) = words.split_at(8);
+        let (mut a, mut b) = ([0; 8], [0; 8]);
+        a.copy_from_slice(lo);
+        b.copy_from_slice(hi);
+        if (u64::from_ne_bytes(a) | u64::from_ne_bytes(b)) & ASCII_MASK != 0 {
+            break;
+        }
+        pos += 16;
+    }
+    while pos + 8 <= block.len() {
+        let mut word = [0; 8];
+        word.copy_from_slice(&block[pos..pos + 8]);
+        if u64::from_ne_bytes(word) & ASCII_MASK != 0 {
+            break;
+        }
+        pos += 8;
+    }
+    pos
+}
+
+/// SSE2 ASCII skipping for [`validate_bulk`].
+#[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+mod sse2 {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::{__m128i, _mm_loadu_si128, _mm_movemask_epi8};
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::{__m128i, _mm_loadu_si128, _mm_movemask_epi8};
+
+    /// Moves `pos` past the run of 16-byte ASCII blocks starting there.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support SSE2.
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn skip_ascii_blocks(bytes: &[u8], mut pos: usize) -> usize {
+        while pos + 16 <= bytes.len() {
+            // SAFETY: the 16 bytes from `pos` are in bounds, and the load
+            // needs no alignment.
+            let block = _mm_loadu_si128(bytes.as_ptr().add(pos) as *const __m128i);
+            if _mm_movemask_epi8(block) != 0 {
+                break;
+            }
+            pos += 16;
+        }
+        pos
+    }
+}
+
+/// Moves `pos` past the run of ASCII bytes starting there.
+#[inline]
+fn skip_ascii(bytes: &[u8], pos: usize) -> usize {
+    #[cfg(all(feature = "simd", any(target_arch = "x86", target_arch = "x86_64")))]
+    let pos = if std::is_x86_feature_detected!("sse2") {
+        // SAFETY: SSE2 was detected.
+        unsafe { sse2::skip_ascii_blocks(bytes, pos) }
+    } else {
+        pos
+    };
+    let mut pos = skip_ascii_words(bytes, pos);
+    while pos < bytes.len() && bytes[pos] < 0x80 {
+        pos += 1;
+    }
+    pos
+}
+
+/// Checks `block` without working out what is wrong with it, returning the
+/// length of a truncated character at its end, or `None` if it is invalid.
+#[inline]
+fn check_block(block: &[u8]) -> Option<usize> {
+    let len = block.len();
+    let mut pos = 0;
+    while pos < len {
+        if block[pos] < 0x80 {
+            pos = skip_ascii_words(block, pos + 1);
+            continue;
+        }
+        match check_char(block, pos) {
+            Ok(length) => pos += length,
+            Err(result) => return result,
+        }
+    }
+    Some(0)
+}
+
+/// Whether `bytes` starts with a valid 3-byte character; `bytes` is at
+/// least 3 bytes long.
+#[inline(always)]
+fn is_three_byte(bytes: &[u8]) -> bool {
+    let (lead, b1, b2) = (bytes[0], bytes[1], bytes[2]);
+    let code_point =
+        u32::from(lead & 0x0f) << 12 | u32::from(b1 & 0x3f) << 6 | u32::from(b2 & 0x3f);
+    lead & 0xf0 == 0xe0
+        && (b1 & 0xc0) | (b2 & 0xc0) >> 2 == 0xa0
+        && code_point >= 0x800
+        && !(0xd800..=0xdfff).contains(&code_point)
+}
+
+/// [`check_block`] trying 3-byte characters first, four at a time.
+#[inline]
+fn check_block_cjk(block: &[u8]) -> Option<usize> {
+    let len = block.len();
+    let mut pos = 0;
+    while pos < len {
+        if pos + 12 <= len
+            && is_three_byte(&block[pos..])
+            && is_three_byte(&block[pos + 3..])
+            && is_three_byte(&block[pos + 6..])
+            && is_three_byte(&block[pos + 9..])
+        {
+            pos += 12;
+        } else if pos + 3 <= len && is_three_byte(&block[pos..]) {
+            pos += 3;
+        } else if block[pos] < 0x80 {
+            pos = skip_ascii_words(block, pos + 1);
+        } else {
+            match check_char(block, pos) {
+                Ok(length) => pos += length,
+                Err(result) => return result,
+            }
+        }
+    }
+    Some(0)
+}
+
+/// Runs `check` over the blocks of `bytes`, then works out the exact error
+/// of the first invalid one.
+#[inline]
+fn validate_blocks<F: Fn(&[u8]) -> Option<usize>>(
+    bytes: &[u8],
+    check: F,
+) -> Result<(), Utf8ErrorAt> {
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = start.saturating_add(BLOCK_LEN).min(bytes.len());
+        match check(&bytes[start..end]) {
+            // The next block starts with the character cut off here.
+            Some(tail) if end < bytes.len() || tail == 0 => start = end - tail,
+            _ => return validate_exact(&bytes[start..]).map_err(|err| err.at(start)),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that all of `bytes` is valid UTF-8.
+///
+/// A fast pass only tells valid from invalid blocks; the exact error is then
+/// worked out by decoding the first invalid block again.
+pub fn validate(bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+    validate_blocks(bytes, check_block)
+}
+
+/// [`validate`] with the fast pass tuned for the kind of text `hint` names.
+/// The result is the same for any hint.
+pub fn validate_with_hint(bytes: &[u8], hint: TextHint) -> Result<(), Utf8ErrorAt> {
+    match hint {
+        TextHint::Latin => validate_blocks(bytes, check_block),
+        TextHint::Cjk => validate_blocks(bytes, check_block_cjk),
+    }
+}
+
+/// Checks that all of `bytes` is valid UTF-8, skipping ASCII runs 16 bytes
+/// at a time and decoding the rest one character at a time.
+///
+/// With the `simd` feature the ASCII runs are checked with SSE2 where the CPU
+/// has it. The result is the same as that of [`validate`], error included;
+/// `validate` is faster on text that is not mostly ASCII.
+pub fn validate_bulk(bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+    let mut pos = 0;
+    loop {
+        pos = skip_ascii(bytes, pos);
+        if pos == bytes.len() {
+            return Ok(());
+        }
+        let (_, length) = decode_step(&bytes[pos..]).map_err(|err| err.at(pos))?;
+        pos += length;
+    }
+}
+
+/// Offset of the first invalid or truncated sequence of `bytes`, if any.
+///
+/// Accepts exactly what [`validate`] accepts, but is a `const fn`, so byte
+/// tables can be checked at compile time:
+///
+/// ```
+/// use valid_utf8::validate_const;
+///
+/// const GREETING: &[u8] = "grüß dich".as_bytes();
+/// const _: () = assert!(validate_const(GREETING).is_none());
+///
+/// assert_eq!(validate_const(b"ab\xc0\x80"), Some(2));
+/// ```
+pub const fn validate_const(bytes: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let lead = bytes[pos];
+        if lead < 0x80 {
+            pos += 1;
+            continue;
+        }
+        let length = sequence_length(lead);
+        let (low, high) = second_byte_range(lead);
+        if length < 2 || bytes.len() - pos < length {
+            return Some(pos);
+        }
+        // Overlongs, surrogates and values above U+10FFFF all show in the
+        // second byte.
+        if bytes[pos + 1] < low || bytes[pos + 1] > high {
+            return Some(pos);
+        }
+        let mut i = 2;
+        while i < length {
+            if !is_continuation(bytes[pos + i]) {
+                return Some(pos);
+            }
+            i += 1;
+        }
+        pos += length;
+    }
+    None
+}
+
+/// [`validate`] without the fast pass.
+pub(crate) fn validate_exact(bytes: &[u8]) -> Result<(), Utf8ErrorAt> {
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if bytes[pos] < 0x80 {
+            pos += 1;
+            continue;
+        }
+        let (_, length) = decode_step(&bytes[pos..]).map_err(|err| err.at(pos))?;
+        pos += length;
+    }
+    Ok(())
+}
+
+/// Moves `start` back to the lead byte of the character containing it,
+/// looking at most 3 bytes back.
+///
+/// A continuation byte without a lead in reach snaps back over up to 3
+/// continuation bytes, so the stray bytes get reported.
+#[inline]
+fn snap_back(bytes: &[u8], start: usize) -> usize {
+    let start = start.min(bytes.len());
+    let floor = start.saturating_sub(3);
+    for pos in (floor..start).rev() {
+        if !is_continuation(bytes[pos]) {
+            return if sequence_length(bytes[pos]) > start - pos {
+                pos
+            } else {
+                start
+            };
+        }
+    }
+    if start < bytes.len() && is_continuation(bytes[start]) {
+        floor
+    } else {
+        start
+    }
+}
+
+/// Moves `end` forward past the continuation bytes of the character it falls
+/// into, stepping over at most 3 of them.
+#[inline]
+fn snap_forward(bytes: &[u8], end: usize) -> usize {
+    let mut pos = end.min(bytes.len());
+    let end = pos;
+    while pos < bytes.len() && pos - end < 3 && is_continuation(bytes[pos]) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Validates `bytes` from `start` to the end, after snapping `start` back to
+/// the start of the character it falls into.
+///
+/// Error offsets are relative to the start of `bytes`.
+pub fn validate_from(bytes: &[u8], start: usize) -> Result<(), Utf8ErrorAt> {
+    validate_range(bytes, start, bytes.len())
+}
+
+/// Validates `bytes[start..end]`, after snapping `start` back and `end`
+/// forward to character boundaries.
+///
+/// Error offsets are relative to the start of `bytes`.
+pub fn validate_range(bytes: &[u8], start: usize, end: usize) -> Result<(), Utf8ErrorAt> {
+    let start = snap_back(bytes, start);
+    let end = snap_forward(bytes, end.max(start));
+    validate(&bytes[start..end]).map_err(|err| err.at(start))
+}
+
+/// Splits `bytes` into its longest valid prefix and the rest, which starts
+/// at the first invalid or truncated sequence.
+///
+/// A buffer cut in the middle of a character is not an error: the start of
+/// that character is the rest.
+#[doc(alias = "valid_prefix")]
+pub fn split_valid(bytes: &[u8]) -> (&str, &[u8]) {
+    let valid_up_to = validate(bytes)
+        .err()
+        .map_or(bytes.len(), |err| err.offset());
+    let (valid, rest) = bytes.split_at(valid_up_to);
+    debug_assert!(core::str::from_utf8(valid).is_ok());
+    // SAFETY: `validate` accepted every byte before `valid_up_to`.
+    (unsafe { core::str::from_utf8_unchecked(valid) }, rest)
+}
+
+/// Cuts `bytes` to at most `max_len` bytes, dropping a character that would
+/// be cut short, or already is at the end of `bytes`.
+///
+/// Like [`incomplete_suffix_len`], only the last character is looked at; if
+/// the input is valid, so is the result. Pass the result to [`split_valid`]
+/// to find out.
+pub fn truncate_to_boundary(bytes: &[u8], max_len: usize) -> &[u8] {
+    let prefix = &bytes[..max_len.min(bytes.len())];
+    match incomplete_suffix_len(prefix) {
+        Ok(tail) => &prefix[..prefix.len() - tail],
+        Err(_) => prefix,
+    }
+}
+
+/// Length of the truncated character at the end of `bytes`: 0 if `bytes`
+/// ends on a character boundary, 1 to 3 if it ends with the start of a
+/// longer character.
+///
+/// Only the last character, at most 4 bytes, is examined, so invalid data
+/// before it goes unnoticed. Trailing bytes that can never become valid are
+/// an error; for stray continuation bytes its offset is that of the first of
+/// them after the last complete character, looking at most 7 bytes back.
+pub fn incomplete_suffix_len(bytes: &[u8]) -> Result<usize, Utf8ErrorAt> {
+    let end = bytes.len();
+    let floor = end.saturating_sub(4);
+    let lead = match (floor..end).rev().find(|&pos| !is_continuation(bytes[pos])) {
+        Some(lead) => lead,
+        None if end == 0 => return Ok(0),
+        // No lead in reach: all of them are stray, except for the end of a
+        // character that starts before them.
+        None => {
+            return Err(Utf8ErrorAt::new(
+                first_stray(bytes, floor),
+                UtfError::InvalidLead,
+                1,
+            ))
+        }
+    };
+    match decode_step(&bytes[lead..]) {
+        Ok((_, length)) if lead + length == end => Ok(0),
+        Ok((_, length)) => Err(Utf8ErrorAt::new(lead + length, UtfError::InvalidLead, 1)),
+        Err(err) if err.error_len().is_none() => Ok(end - lead),
+        Err(err) => Err(err.at(lead)),
+    }
+}
+
+/// Where the stray continuation bytes from `floor` on start: after the
+/// character that starts in the 3 bytes before `floor`, if it reaches past it.
+fn first_stray(bytes: &[u8], floor: usize) -> usize {
+    let lead = (floor.saturating_sub(3)..floor)
+        .rev()
+        .find(|&pos| !is_continuation(bytes[pos]));
+    match lead.map(|lead| (lead, decode_step(&bytes[lead..]))) {
+        Some((lead, Ok((_, length)))) if lead + length > floor => lead + length,
+        _ => floor,
+    }
+}
+
+#[cfg(test)]
+mod test_validate {
+    use super::*;
+    use crate::core::validate_next;
+    use crate::test_util::{random_corrupt, XorShift};
+
+    /// Whether decoding with `validate_next` gets through all of `bytes`.
+    fn accepted_by_validate_next(bytes: &[u8]) -> bool {
+        let mut it = bytes.iter();
+        while it.len() != 0 {
+            if validate_next(&mut it).is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn check_const(bytes: &[u8]) {
+        let expected = validate(bytes).err().map(|err| err.offset());
+        assert_eq!(validate_const(bytes), expected, "{:x?}", bytes);
+        assert_eq!(expected.is_none(), accepted_by_validate_next(bytes));
+    }
+
+    #[test]
+    fn test_validate_const_matches_validate() {
+        let inputs = [
+            "!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~",
+            "¡¢£¤¥¦§¨©ª«¬\u{AD}®¯°±²³´µ¶·¸¹º»¼½¾¿ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔ",
+            "ขฃคฅฆงจฉชซฌญฎฏฐฑฒณดตถทธนบปผฝพฟภมยรฤลฦวศษสหฬอฮฯะัาำิีึืฺุู",
+            "😀𒀀𒀁𒀂𒀃𒀄𒀅𒀆𒀇𒀈𒀉𒀊𒀋𒀌𒀍𒀎𒀏",
+        ];
+        for input in inputs.iter() {
+            assert_eq!(validate_const(input.as_bytes()), None);
+            for end in 0..input.len() {
+                check_const(&input.as_bytes()[..end]);
+            }
+        }
+        for a in 0..=0xffu8 {
+            check_const(&[a]);
+            for b in 0..=0xffu8 {
+                check_const(&[a, b]);
+            }
+        }
+        let mut rng = XorShift::new(271);
+        for _ in 0..1000 {
+            let fragments = rng.below(40);
+            check_const(&random_corrupt(&mut rng, fragments));
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(b""), Ok(()));
+        assert_eq!(validate("aé€😀".as_bytes()), Ok(()));
+        let err = validate(&[0x61, 0x62, 0xed, 0xa0, 0x80]).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (2, UtfError::InvalidCodePoint));
+    }
+
+    #[test]
+    fn test_validate_matches_exact_across_blocks() {
+        // Characters and errors straddling every position around the first
+        // block boundaries.
+        let pieces: [&[u8]; 7] = [
+            "é".as_bytes(),
+            "€".as_bytes(),
+            "😀".as_bytes(),
+            b"\xf0\x9f\x98",
+            b"\xe0\x80",
+            b"\x80",
+            b"\xed\xa0\x80",
+        ];
+        for pad in BLOCK_LEN - 4..=BLOCK_LEN + 1 {
+            for piece in pieces.iter() {
+                for second in [0, BLOCK_LEN].iter() {
+                    let mut bytes = "é".repeat(pad / 2).into_bytes();
+                    bytes.resize(pad + second, b'a');
+                    bytes.extend_from_slice(piece);
+                    bytes.extend_from_slice(b"tail");
+                    assert_eq!(
+                        validate(&bytes),
+                        validate_exact(&bytes),
+                        "{} {:x?}",
+                        pad,
+                        piece
+                    );
+                    let cut = &bytes[..bytes.len() - 4];
+                    assert_eq!(validate(cut), validate_exact(cut));
+                }
+            }
+        }
+        let mut rng = XorShift::new(239);
+        for _ in 0..200 {
+            let mut bytes = "aé€😀".repeat(rng.below(2000)).into_bytes();
+            bytes.extend(random_corrupt(&mut rng, 4));
+            assert_eq!(validate(&bytes), validate_exact(&bytes));
+        }
+    }
+
+    #[test]
+    fn test_is_three_byte() {
+        for c in ['\u{800}', '€', '\u{d7ff}', '\u{e000}', '漢', '\u{ffff}'].iter() {
+            let mut buf = [0; 4];
+            assert!(is_three_byte(c.encode_utf8(&mut buf).as_bytes()), "{:?}", c);
+        }
+        let rejected: [&[u8]; 7] = [
+            b"\xe0\x9f\xbf",
+            b"\xed\xa0\x80",
+            b"\xed\xbf\xbf",
+            b"\xe2\x82\x2c",
+            b"\xe2\xc2\xac",
+            b"\xf0\x9f\x98",
+            b"abc",
+        ];
+        for bytes in rejected.iter() {
+            assert!(!is_three_byte(bytes), "{:x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn test_validate_with_hint_matches_exact() {
+        let mut rng = XorShift::new(247);
+        for round in 0..300 {
+            let target = rng.below(3 * BLOCK_LEN);
+            let mut bytes = Vec::new();
+            while bytes.len() < target {
+                let repeat = 1 + rng.below(50);
+                match rng.below(4) {
+                    0 => bytes.extend_from_slice(b"ab "),
+                    1 => bytes.extend_from_slice("é😀".as_bytes()),
+                    _ => bytes.extend_from_slice("漢字仮名交じり文".repeat(repeat).as_bytes()),
+                }
+            }
+            // Corrupt half of the inputs, anywhere.
+            if round % 2 == 0 {
+                let at = rng.below(bytes.len() + 1);
+                let fragments = 1 + rng.below(3);
+                let corrupt = random_corrupt(&mut rng, fragments);
+                bytes.splice(at..at, corrupt);
+            }
+            let expected = validate_exact(&bytes);
+            assert_eq!(validate(&bytes), expected);
+            for hint in [TextHint::Latin, TextHint::Cjk].iter() {
+                assert_eq!(validate_with_hint(&bytes, *hint), expected, "{:x?}", bytes);
+                let cut = &bytes[..bytes.len() / 2];
+                assert_eq!(validate_with_hint(cut, *hint), validate_exact(cut));
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_bulk_matches_validate() {
+        // Faults at every position around the 16-byte ASCII blocks.
+        for run in 0..40 {
+            for fault in [&b"\xff"[..], b"\xc3", b"\xe2\x82\xac", b"\xed\xa0\x80"].iter() {
+                let mut bytes = vec![b'a'; run];
+                bytes.extend_from_slice(fault);
+                bytes.resize(run + 40, b'b');
+                assert_eq!(validate_bulk(&bytes), validate(&bytes), "{:x?}", bytes);
+                let cut = &bytes[..run + 1];
+                assert_eq!(validate_bulk(cut), validate(cut), "{:x?}", cut);
+            }
+        }
+        let mut rng = XorShift::new(259);
+        for _ in 0..1000 {
+            let fragments = rng.below(40);
+            let bytes = random_corrupt(&mut rng, fragments);
+            assert_eq!(validate_bulk(&bytes), validate(&bytes), "{:x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn test_validate_from_each_byte_of_4_byte_char() {
+        let text = "ab😀cd".as_bytes();
+        for start in 0..=text.len() {
+            assert_eq!(validate_from(text, start), Ok(()), "start {}", start);
+            for end in start..=text.len() {
+                assert_eq!(
+                    validate_range(text, start, end),
+                    Ok(()),
+                    "{}..{}",
+                    start,
+                    end
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_from_edit_breaks_char() {
+        // Overwrite each byte of the 4-byte character with ASCII.
+        let text = "ab😀cd".as_bytes();
+        for edit in 2..6 {
+            let mut edited = text.to_vec();
+            edited[edit] = b'x';
+            let err = validate_from(&edited, edit).unwrap_err();
+            if edit == 2 {
+                // The lead is gone; the first stray continuation byte is reported.
+                assert_eq!((err.offset(), err.kind()), (3, UtfError::InvalidLead));
+            } else {
+                assert_eq!(
+                    (err.offset(), err.kind()),
+                    (2, UtfError::IncompleteSequence)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_from_inside_invalid_region() {
+        let bytes = [0x61, 0x80, 0x80, 0x80, 0x80, 0x80, 0x62];
+        let err = validate_from(&bytes, 5).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (2, UtfError::InvalidLead));
+        let err = validate_from(&[0x80, 0x80], 1).unwrap_err();
+        assert_eq!(err.offset(), 0);
+        assert_eq!(validate_from(b"abc", 10), Ok(()));
+        assert_eq!(validate_range(b"abc", 2, 1), Ok(()));
+    }
+
+    #[test]
+    fn test_incomplete_suffix_len_prefixes() {
+        for c in ['é', '€', '😀'].iter() {
+            let mut encoded = [0; 4];
+            let encoded = c.encode_utf8(&mut encoded).as_bytes();
+            for cut in 0..=encoded.len() {
+                let mut bytes = b"text".to_vec();
+                bytes.extend_from_slice(&encoded[..cut]);
+                let expected = if cut == encoded.len() { 0 } else { cut };
+                assert_eq!(incomplete_suffix_len(&bytes), Ok(expected), "{:x?}", bytes);
+                assert_eq!(incomplete_suffix_len(&encoded[..cut]), Ok(expected));
+            }
+        }
+        assert_eq!(incomplete_suffix_len(b""), Ok(0));
+        assert_eq!(incomplete_suffix_len(b"abc"), Ok(0));
+    }
+
+    #[test]
+    fn test_incomplete_suffix_len_invalid() {
+        // A stray continuation byte after a complete character.
+        let err = incomplete_suffix_len(&[0x61, 0xc3, 0xa9, 0xa9]).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (3, UtfError::InvalidLead));
+        let err = incomplete_suffix_len(&[0x61, 0x80]).unwrap_err();
+        assert_eq!(err.offset(), 1);
+        assert_eq!(
+            incomplete_suffix_len(&[0x80, 0x80]).unwrap_err().offset(),
+            0
+        );
+        // Without a lead in reach, the first stray byte in reach, wherever
+        // the window starts.
+        for len in 1..=8usize {
+            let mut bytes = vec![0x61; len.saturating_sub(4)];
+            bytes.resize(len, 0x80);
+            let err = incomplete_suffix_len(&bytes).unwrap_err();
+            assert_eq!(err.offset(), len.saturating_sub(4), "{:x?}", bytes);
+            assert_eq!(err.kind(), UtfError::InvalidLead);
+        }
+        let err = incomplete_suffix_len(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80]).unwrap_err();
+        assert_eq!(err.offset(), 2);
+        // The window starts inside a complete character.
+        let err = incomplete_suffix_len(&[0xf0, 0x9f, 0x98, 0x80, 0x80]).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (4, UtfError::InvalidLead));
+        let err = incomplete_suffix_len(&[0xf0, 0x9f, 0x98, 0x80, 0x80, 0x80, 0x80]).unwrap_err();
+        assert_eq!(err.offset(), 4);
+        let err = incomplete_suffix_len(&[0x61, 0xc3, 0xa9, 0x80, 0x80, 0x80, 0x80]).unwrap_err();
+        assert_eq!(err.offset(), 3);
+        let err = incomplete_suffix_len(&[0x61, 0xe2, 0x80, 0x80, 0x80, 0x80]).unwrap_err();
+        assert_eq!(err.offset(), 4);
+
+        // Prefixes that can never be completed.
+        let err = incomplete_suffix_len(&[0x61, 0xe0, 0x80]).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, UtfError::OverlongSequence));
+        let err = incomplete_suffix_len(&[0x61, 0xed, 0xa0]).unwrap_err();
+        assert_eq!((err.offset(), err.kind()), (1, UtfError::InvalidCodePoint));
+        assert!(incomplete_suffix_len(&[0xff]).is_err());
+
+        // Only the tail is looked at.
+        assert_eq!(
+            incomplete_suffix_len(&[0xff, 0x61, 0x62, 0x63, 0x64]),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn test_split_valid_matches_std() {
+        let mut rng = XorShift::new(217);
+        for _ in 0..2000 {
+            let fragments = rng.below(40);
+            let input = random_corrupt(&mut rng, fragments);
+            let valid_up_to =
+                std::str::from_utf8(&input).map_or_else(|e| e.valid_up_to(), str::len);
+            let (valid, rest) = split_valid(&input);
+            assert_eq!(valid.len(), valid_up_to, "{:x?}", input);
+            assert_eq!(rest, &input[valid_up_to..]);
+        }
+        assert_eq!(split_valid(b"ab\xf0\x9f"), ("ab", &[0xf0, 0x9f][..]));
+        assert_eq!(split_valid("é€".as_bytes()), ("é€", &[][..]));
+    }
+
+    #[test]
+    fn test_cut_inside_sequences() {
+        let text = "aé€😀";
+        let bytes = text.as_bytes();
+        let boundaries = [0, 1, 3, 6, 10];
+        for max_len in 0..=bytes.len() + 1 {
+            let end = *boundaries.iter().rev().find(|&&b| b <= max_len).unwrap();
+            let cut = &bytes[..max_len.min(bytes.len())];
+            assert_eq!(split_valid(cut), (&text[..end], &bytes[end..cut.len()]));
+            assert_eq!(truncate_to_boundary(bytes, max_len), &bytes[..end]);
+            // Already cut short.
+            assert_eq!(truncate_to_boundary(cut, usize::MAX), &bytes[..end]);
+        }
+        // Invalid data before the cut is left for `split_valid`.
+        assert_eq!(truncate_to_boundary(b"\xffab\xe2\x82", 5), b"\xffab");
+        assert_eq!(truncate_to_boundary(b"ab\xff", 3), b"ab\xff");
+    }
+}